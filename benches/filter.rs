@@ -0,0 +1,93 @@
+// benchmark of filters, it prints timings and asserts that results are equal
+// example: cargo bench --bench filter
+extern crate qdb_cli2;
+
+use qdb_cli2::execution::filter::Filter;
+use qdb_cli2::execution::result::Record;
+use qdb_cli2::text_processing::ast::scope::Scope;
+use qdb_cli2::text_processing::ast::types::{BinaryExpr, DataType, ScalarExpr};
+use qdb_cli2::text_processing::parser::states::{Parser, ParserDefault};
+use std::time::{Duration, Instant};
+
+#[path = "../src/execution/filter/fixtures.rs"]
+mod fixtures;
+
+// count of records which are matched by every filter
+const RECORDS: i64 = 1_000_000;
+
+// binary expression like it was before operator was parsed,
+// operator is kept as text and matched on every evaluation
+struct TextExpr(ScalarExpr, ScalarExpr, String);
+
+impl TextExpr {
+    fn from_binary_expr(expr: &BinaryExpr) -> TextExpr {
+        TextExpr(
+            expr.get_lterm().clone(),
+            expr.get_rterm().clone(),
+            expr.get_operator().to_string(),
+        )
+    }
+
+    fn eval(&self, record: &Record) -> Option<bool> {
+        let lterm = self.0.eval(record)?;
+        let rterm = self.1.eval(record)?;
+        TextExpr::compare_values(&lterm, &rterm, self.2.as_str())
+    }
+
+    fn compare_values(lterm: &DataType, rterm: &DataType, operator: &str) -> Option<bool> {
+        match operator {
+            "==" => Some(lterm == rterm),
+            "!=" => Some(lterm != rterm),
+            ">=" => Some(lterm >= rterm),
+            ">" => Some(lterm > rterm),
+            "<=" => Some(lterm <= rterm),
+            "<" => Some(lterm < rterm),
+            _ => None,
+        }
+    }
+}
+
+fn exprs(filter: &str) -> Option<Vec<BinaryExpr>> {
+    let script = format!("onRead(users)({})", filter);
+    let exprs = ParserDefault::from_script(script, &mut Scope::new()).unwrap();
+    exprs[0].get_binary_exprs().clone()
+}
+
+// count of matched records and time of matching
+fn measure<F: Fn(&Record) -> bool>(records: &[Record], is_matched: F) -> (usize, Duration) {
+    let start = Instant::now();
+    let count = records.iter().filter(|e| is_matched(e)).count();
+    (count, start.elapsed())
+}
+
+fn main() {
+    let records = fixtures::records(RECORDS);
+    let exprs = exprs("id >= 100, name != 'eve', score * 2 > 10");
+    let text_exprs: Vec<TextExpr> = exprs
+        .iter()
+        .flatten()
+        .map(TextExpr::from_binary_expr)
+        .collect();
+    let filter = Filter::compile(&exprs);
+
+    let (by_text, by_text_time) = measure(&records, |record| {
+        text_exprs.iter().all(|e| e.eval(record).unwrap_or(false))
+    });
+    let (evaluated, evaluated_time) = measure(&records, |record| {
+        exprs
+            .iter()
+            .flatten()
+            .all(|e| e.eval(record).unwrap_or(false))
+    });
+    let (compiled, compiled_time) = measure(&records, |record| filter.is_matched(record));
+
+    println!(
+        "{} records, operator by text: {:?}, BinaryExpr::eval: {:?}, compiled filter: {:?}",
+        records.len(),
+        by_text_time,
+        evaluated_time,
+        compiled_time
+    );
+    assert_eq!(by_text, evaluated);
+    assert_eq!(evaluated, compiled);
+}
@@ -1,5 +1,10 @@
 extern crate clap;
+use crate::environment::uri::Uri;
+use crate::execution::mvcc::RETAINED_VERSIONS;
+use crate::text_processing::ast::definitions::Definitions;
+use crate::text_processing::ast::params::Params;
 use clap::*;
+use std::io::Read;
 
 fn build_clap_app() -> ArgMatches<'static> {
     /* APP INFO */
@@ -23,12 +28,88 @@ fn build_clap_app() -> ArgMatches<'static> {
     let uri = Arg::with_name("uri")
         .short("U")
         .long("uri")
-        .help("(string) Set uri for connection. \n Example: qdb --uri \"example.com:6060/mynode\" \n qdb -U \"example.com:6060/mynode\"")
+        .help("(string) Set uri for connection, mem:// executes script locally in memory, file:// stores channels in directory. \n Example: qdb --uri \"example.com:6060/mynode\" \n qdb -U \"mem://\" \n qdb -U \"file:///path/to/db\"")
         .takes_value(true)
         .value_name("URI")
-        .default_value("localhost:6060");
+        .default_value("localhost:6060")
+        .global(true);
 
-    app.arg(debug).arg(uri).get_matches()
+    // Query params
+    let param = Arg::with_name("param")
+        .short("P")
+        .long("param")
+        .help("(string) Bind value to query placeholder, number as name binds positional placeholder. \n Example: qdb --param \"name=bob:text\" --param \"1=21:int\" \n qdb -P \"name=bob:text\"")
+        .takes_value(true)
+        .value_name("NAME=VALUE:TYPE")
+        .multiple(true)
+        .number_of_values(1);
+
+    // Stored queries file
+    let definitions = Arg::with_name("definitions")
+        .long("definitions")
+        .help("(string) Set file of stored queries created by define. \n Example: qdb --definitions \"./queries.qdb\"")
+        .takes_value(true)
+        .value_name("PATH")
+        .default_value(".qdb_definitions");
+
+    // Retained versions
+    let retention = Arg::with_name("retention")
+        .long("retention")
+        .help("(number) Set count of last committed versions kept by mem:// and file:// storage for reads as of version and history. \n Example: qdb -U \"file:///path/to/db\" --retention 100 serve \"0.0.0.0:6060\"")
+        .takes_value(true)
+        .value_name("COUNT")
+        .global(true);
+
+    // Script
+    let script = Arg::with_name("script")
+        .help("(string) Script of statements, it's read from stdin when not set. \n Example: qdb -U \"mem://\" \"onCreate(users)(name: text = 'bob'); onRead(users)\"")
+        .value_name("SCRIPT")
+        .index(1);
+
+    /* SUBCOMMANDS */
+    // Backup of file storage
+    let backup = SubCommand::with_name("backup")
+        .about("Write point-in-time snapshot of file storage into one file, storage can be written meanwhile. \n Example: qdb -U \"file:///path/to/db\" backup \"./db.backup\"")
+        .arg(
+            Arg::with_name("file")
+                .help("(string) Path of backup file.")
+                .value_name("FILE")
+                .required(true)
+                .index(1),
+        );
+
+    // Restore of file storage
+    let restore = SubCommand::with_name("restore")
+        .about("Rebuild empty file storage from backup file. \n Example: qdb -U \"file:///path/to/db\" restore \"./db.backup\"")
+        .arg(
+            Arg::with_name("file")
+                .help("(string) Path of backup file.")
+                .value_name("FILE")
+                .required(true)
+                .index(1),
+        );
+
+    // Server of storage for remote uri
+    let serve = SubCommand::with_name("serve")
+        .about("Serve memory or file storage to clients with remote uri, clients are served one by one. \n Example: qdb -U \"file:///path/to/db\" serve \"0.0.0.0:6060\"")
+        .arg(
+            Arg::with_name("address")
+                .help("(string) Address of server.")
+                .value_name("ADDRESS")
+                .required(true)
+                .index(1),
+        );
+
+    app.arg(debug)
+        .arg(uri)
+        .arg(param)
+        .arg(definitions)
+        .arg(retention)
+        .arg(script)
+        .subcommand(backup)
+        .subcommand(restore)
+        .subcommand(serve)
+        .get_matches()
 }
 
 lazy_static! {
@@ -38,3 +119,58 @@ lazy_static! {
 pub fn get_app_config() -> &'static ArgMatches<'static> {
     &*ARG_MATCHES
 }
+
+pub fn get_uri() -> Uri {
+    let raw_uri = get_app_config().value_of("uri").unwrap();
+    Uri::from_string(raw_uri).expect(format!("uri is not correctly: {}", raw_uri).as_str())
+}
+
+pub fn get_params() -> Params {
+    let mut params = Params::new();
+    for raw_param in get_app_config().values_of("param").into_iter().flatten() {
+        params
+            .insert_from_string(raw_param)
+            .expect(format!("param is not correctly: {}", raw_param).as_str());
+    }
+    params
+}
+
+pub fn get_definitions() -> Definitions {
+    let path = get_app_config().value_of("definitions").unwrap();
+    Definitions::open(path).expect(format!("definitions file is not correctly: {}", path).as_str())
+}
+
+// count of versions retained by local storage
+pub fn get_retention() -> u64 {
+    match get_app_config().value_of("retention") {
+        Some(raw_retention) => raw_retention
+            .parse()
+            .expect(format!("retention is not correctly: {}", raw_retention).as_str()),
+        None => RETAINED_VERSIONS,
+    }
+}
+
+// name of subcommand and its file or address
+// example: qdb backup ./db.backup -> ("backup", "./db.backup")
+pub fn get_subcommand() -> Option<(String, String)> {
+    match get_app_config().subcommand() {
+        (name, Some(matches)) => {
+            let value = matches.value_of("file").or(matches.value_of("address"))?;
+            Some((name.to_string(), value.to_string()))
+        }
+        _ => None,
+    }
+}
+
+pub fn get_script() -> String {
+    match get_app_config().value_of("script") {
+        Some(script) => script.to_string(),
+        None => {
+            let mut script = String::new();
+            std::io::stdin()
+                .read_to_string(&mut script)
+                .expect("script is not read from stdin");
+            script
+        }
+    }
+}
@@ -1,2 +1,3 @@
-mod about;
+pub mod about;
 pub mod logger;
+pub mod uri;
@@ -0,0 +1,96 @@
+#[derive(Debug, Clone, PartialEq)]
+// connection uri from --uri flag
+// example: example.com:6060/mynode, mem://, file:///path/to/db
+pub struct Uri {
+    scheme: Option<String>,
+    host: String,
+    port: Option<u16>,
+    path: String,
+}
+
+impl Uri {
+    pub fn from_string<T: ToString>(raw_value: T) -> Option<Uri> {
+        let raw_value = raw_value.to_string();
+        let (scheme, rest) = match raw_value.find("://") {
+            Some(i) => (Some(raw_value[..i].to_lowercase()), &raw_value[i + 3..]),
+            None => (None, raw_value.as_str()),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, ""),
+        };
+        let (host, port) = match authority.rfind(':') {
+            Some(i) => match authority[i + 1..].parse::<u16>() {
+                Ok(port) => (&authority[..i], Some(port)),
+                Err(_) => return None,
+            },
+            None => (authority, None),
+        };
+        Some(Uri {
+            scheme,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    pub fn get_scheme(&self) -> &Option<String> {
+        &self.scheme
+    }
+    pub fn get_host(&self) -> &String {
+        &self.host
+    }
+    pub fn get_port(&self) -> Option<u16> {
+        self.port
+    }
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    // mem:// executes statements locally on channels kept in memory
+    pub fn is_memory(&self) -> bool {
+        self.scheme.as_deref() == Some("mem")
+    }
+
+    // file:///path/to/db executes statements locally on channels stored in directory
+    pub fn is_file(&self) -> bool {
+        self.scheme.as_deref() == Some("file")
+    }
+
+    // node path is namespace for bare channel names
+    // example: example.com:6060/mynode -> mynode
+    pub fn get_node_path(&self) -> &str {
+        self.path.trim_matches('/')
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::environment::uri::Uri;
+
+    #[test]
+    fn test_uri_from_string() -> Result<(), ()> {
+        let uri = Uri::from_string("example.com:6060/mynode").unwrap();
+        assert_eq!(&None, uri.get_scheme());
+        assert_eq!("example.com", uri.get_host());
+        assert_eq!(Some(6060), uri.get_port());
+        assert_eq!("mynode", uri.get_node_path());
+
+        let uri = Uri::from_string("localhost:6060").unwrap();
+        assert_eq!("", uri.get_node_path());
+
+        let uri = Uri::from_string("file:///path/to/db").unwrap();
+        assert_eq!(&Some("file".to_string()), uri.get_scheme());
+        assert_eq!("", uri.get_host());
+        assert_eq!("/path/to/db", uri.get_path());
+        assert_eq!(true, uri.is_file());
+
+        let uri = Uri::from_string("mem://").unwrap();
+        assert_eq!(true, uri.is_memory());
+        assert_eq!("", uri.get_node_path());
+
+        assert_eq!(None, Uri::from_string("localhost:port"));
+        Ok(())
+    }
+}
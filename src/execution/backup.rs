@@ -0,0 +1,167 @@
+use crate::environment::logger::Logger;
+use crate::execution::memory::StorageExecutor;
+use crate::execution::storage::{MemoryStorage, StorageBackend};
+use crate::execution::wal::{checksum, encode_batch, read_batches, Change, Wal};
+use std::path::Path;
+
+// version of backup file, restore reads this version only
+pub const BACKUP_VERSION: u32 = 1;
+const BACKUP_HEADER: &str = "qdb-backup";
+
+// backup file: header with version and sequence number of snapshot,
+// changes which create every channel with its records, schema and indexes
+// as one batch of log lines with checksums, then checksum of whole file
+// example:
+//   qdb-backup 1 12
+//   6c3d1a2b9f0e4d21 create users 1 id i:0 -
+//   0b5e7f3c2a1d9e84 commit 12
+//   checksum 9a4c7e1f3b2d5a60
+
+// write point-in-time snapshot of storage directory into file
+// storage is only read, so it can be written by other process at the same time
+// returns count of channels in backup
+pub fn backup<T: AsRef<Path>>(dir: T, path: T) -> Option<usize> {
+    let (lsn, changes) = Wal::read_snapshot(dir.as_ref())?;
+    // snapshot is compacted: records are stored as they're at moment of snapshot
+    let changes = MemoryStorage::replay(changes)?.dump();
+    let channels = changes
+        .iter()
+        .filter(|e| matches!(e, Change::Create(_, _)))
+        .count();
+    let mut content = format!("{} {} {}\n", BACKUP_HEADER, BACKUP_VERSION, lsn);
+    content.push_str(encode_batch(&changes, lsn)?.as_str());
+    content.push_str(format!("checksum {:016x}\n", checksum(content.as_bytes())).as_str());
+
+    let path = path.as_ref();
+    let tmp_path = path.with_extension("tmp");
+    let result = std::fs::write(&tmp_path, content).and_then(|_| std::fs::rename(&tmp_path, path));
+    if let Err(e) = result {
+        Logger::error(format!("backup file is not written: {}", e).as_str());
+        return None;
+    }
+    Some(channels)
+}
+
+// rebuild empty storage directory from backup file
+// returns count of restored channels
+pub fn restore<T: AsRef<Path>>(path: T, dir: T) -> Option<usize> {
+    let changes = read_backup(path.as_ref())?;
+    let channels = changes
+        .iter()
+        .filter(|e| matches!(e, Change::Create(_, _)))
+        .count();
+    let mut executor = StorageExecutor::open("", dir.as_ref())?;
+    executor.restore(changes)?;
+    Some(channels)
+}
+
+// changes of backup file, file is checked by version and checksums
+fn read_backup(path: &Path) -> Option<Vec<Change>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            Logger::error(format!("backup file is not read: {}", e).as_str());
+            return None;
+        }
+    };
+    let header_len = content.find('\n').map(|e| e + 1).unwrap_or(content.len());
+    let header: Vec<&str> = content[..header_len].trim_end().split(' ').collect();
+    let version = match header.as_slice() {
+        [BACKUP_HEADER, version, _] => version.parse::<u32>().ok(),
+        _ => None,
+    };
+    match version {
+        Some(BACKUP_VERSION) => {}
+        Some(version) => {
+            Logger::error(format!("backup version {} is not supported", version).as_str());
+            return None;
+        }
+        None => {
+            Logger::error("file is not backup");
+            return None;
+        }
+    }
+
+    // last line is checksum of content before it
+    let body = content.trim_end_matches('\n');
+    let (rest, last_line) = match body.rfind('\n') {
+        Some(i) => (&content[..i + 1], &body[i + 1..]),
+        None => ("", body),
+    };
+    let expected = format!("checksum {:016x}", checksum(rest.as_bytes()));
+    if rest.is_empty() || last_line != expected {
+        Logger::error("backup file is damaged: checksum is not matched");
+        return None;
+    }
+    let batch = &rest[header_len..];
+    match read_batches(batch) {
+        (mut batches, len) if len == batch.len() && batches.len() == 1 => Some(batches.remove(0).1),
+        _ => {
+            Logger::error("backup file is damaged");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::backup::{backup, restore};
+    use crate::execution::executor::{execute_block, Executor};
+    use crate::execution::memory::StorageExecutor;
+    use crate::text_processing::ast::scope::Scope;
+    use crate::text_processing::ast::types::{ChannelName, DataType};
+    use crate::text_processing::parser::states::{Parser, ParserDefault};
+
+    #[test]
+    fn test_backup_restore() -> Result<(), ()> {
+        let root = std::env::temp_dir().join(format!("qdb_backup_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let (dir, restored_dir) = (root.join("db"), root.join("restored"));
+        let path = root.join("db.backup");
+
+        let mut executor = StorageExecutor::open("", &dir).unwrap();
+        let blocks = ParserDefault::from_script_blocks(
+            "onCreate(users)(id: int = nextval(users_id), name: text = 'bob');\
+             onCreate(users)(name: text = 'alice');\
+             onIndex(users)(name);\
+             onDelete(users)(name == 'bob')",
+            &mut Scope::new(),
+        )
+        .unwrap();
+        execute_block(&mut executor, &blocks[0]);
+
+        // storage is still opened by executor and written after snapshot
+        assert_eq!(Some(1), backup(&dir, &path));
+        let expr =
+            &ParserDefault::from_unary_func_expr("onCreate(users)(name: text = 'eve')").unwrap()[0];
+        executor.execute(expr);
+
+        assert_eq!(Some(1), restore(&path, &restored_dir));
+        let mut restored = StorageExecutor::open("", &restored_dir).unwrap();
+        let users = ChannelName::from_string("users").unwrap();
+        let records = restored.get_records(&users).unwrap();
+        assert_eq!(1, records.len());
+        assert_eq!(
+            Some(&DataType::Text("'alice'".to_string())),
+            records[0].get("name")
+        );
+        // schema and sequence are restored
+        let result = restored.execute(expr);
+        assert_eq!(1, result.get_affected());
+        let records = restored.get_records(&users).unwrap();
+        assert_eq!(Some(&DataType::Int(3)), records[1].get("id"));
+        drop(restored);
+
+        // storage with data is not replaced
+        assert_eq!(None, restore(&path, &restored_dir));
+        // damaged backup is not restored
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, content.replace("alice", "alica")).unwrap();
+        assert_eq!(None, restore(&path, &root.join("other")));
+        std::fs::write(&path, content.replacen("qdb-backup 1", "qdb-backup 2", 1)).unwrap();
+        assert_eq!(None, restore(&path, &root.join("other")));
+        std::fs::remove_dir_all(&root).unwrap();
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use crate::environment::logger::Logger;
+use crate::execution::result::Record;
+use crate::text_processing::ast::types::{DataType, DataVar, DefaultExpr};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+// named sequences for nextval(seq), sequence is created at first call
+pub struct Sequences {
+    values: BTreeMap<String, i64>,
+}
+
+impl Sequences {
+    pub fn new() -> Sequences {
+        Sequences::default()
+    }
+
+    // next value of sequence, first value is 1
+    pub fn nextval(&mut self, name: &str) -> i64 {
+        let value = self.values.entry(name.to_string()).or_insert(0);
+        *value += 1;
+        *value
+    }
+
+    // current value of sequence, 0 if sequence is not used yet
+    pub fn get(&self, name: &str) -> i64 {
+        self.values.get(name).cloned().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, name: &str, value: i64) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn get_values(&self) -> &BTreeMap<String, i64> {
+        &self.values
+    }
+}
+
+// evaluate computed default, expression takes fields from record
+pub fn eval_default(
+    default: &DefaultExpr,
+    record: &Record,
+    sequences: &mut Sequences,
+) -> Option<DataType> {
+    let value = match default {
+        DefaultExpr::Now => DataType::Int(now_millis()),
+        DefaultExpr::Uuid => DataType::Text(format!("'{}'", uuid())),
+        DefaultExpr::NextVal(sequence) => DataType::Int(sequences.nextval(sequence)),
+        DefaultExpr::Expr(expr) => expr.eval(record)?,
+    };
+    Some(value)
+}
+
+// value of statement: computed default or value as is
+// computed value must have declared type of statement or be null
+// example: (full: text = concat(first, last)), record { first: 'a', last: 'b' } -> 'ab'
+pub fn eval_var(var: &DataVar, record: &Record, sequences: &mut Sequences) -> Option<DataType> {
+    let default = match var.get_default() {
+        Some(default) => default,
+        None => return Some(var.get_value().clone()),
+    };
+    let value = eval_default(default, record, sequences)?;
+    if value != DataType::Null && value.get_type_name() != var.get_value().get_type_name() {
+        Logger::error(
+            format!(
+                "{} expects {} but {} is computed",
+                var.get_name(),
+                var.get_value().get_type_name(),
+                value.get_type_name()
+            )
+            .as_str(),
+        );
+        return None;
+    }
+    Some(value)
+}
+
+// fill fields not supplied by insert with computed defaults of schema,
+// literal of first record is not default, so field without computed default stays missing
+// example: schema (id: int = nextval(s), name: text), record { name: 'bob' } -> { id: 1, name: 'bob' }
+pub fn fill_defaults(
+    record: &mut Record,
+    schema: &[DataVar],
+    sequences: &mut Sequences,
+) -> Option<()> {
+    for var in schema {
+        if var.get_default().is_some() && !record.contains_key(var.get_name()) {
+            let value = eval_var(var, record, sequences)?;
+            record.insert(var.get_name().to_owned(), value);
+        }
+    }
+    Some(())
+}
+
+// fields of record must have types declared by schema or be null,
+// fields not declared by schema are not checked
+// example: schema (age: int), record { age: 'x' } -> None
+pub fn check_types(record: &Record, schema: &[DataVar]) -> Option<()> {
+    for var in schema {
+        let expected = var.get_value().get_type_name();
+        let value = match record.get(var.get_name()) {
+            Some(value) => value,
+            None => continue,
+        };
+        if *value == DataType::Null || expected == DataType::Null.get_type_name() {
+            continue;
+        }
+        if value.get_type_name() != expected {
+            Logger::error(
+                format!(
+                    "{} expects {} but {} is given",
+                    var.get_name(),
+                    expected,
+                    value.get_type_name()
+                )
+                .as_str(),
+            );
+            return None;
+        }
+    }
+    Some(())
+}
+
+// current time in milliseconds since epoch, value of now()
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|e| e.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// random uuid v4, randomness is taken from random keys of hasher
+fn uuid() -> String {
+    let random = |salt: u128| -> u64 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(salt);
+        hasher.finish()
+    };
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|e| e.as_nanos())
+        .unwrap_or(0);
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&random(time).to_be_bytes());
+    bytes[8..].copy_from_slice(&random(time.wrapping_add(1)).to_be_bytes());
+    // version 4 and RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|e| format!("{:02x}", e)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::defaults::{
+        check_types, eval_default, eval_var, fill_defaults, uuid, Sequences,
+    };
+    use crate::execution::result::Record;
+    use crate::text_processing::ast::types::{DataType, DataVar, DefaultExpr};
+
+    #[test]
+    fn test_eval_default() -> Result<(), ()> {
+        let mut sequences = Sequences::new();
+        let sequence = DefaultExpr::NextVal("s".to_string());
+        assert_eq!(
+            Some(DataType::Int(1)),
+            eval_default(&sequence, &Record::new(), &mut sequences)
+        );
+        assert_eq!(
+            Some(DataType::Int(2)),
+            eval_default(&sequence, &Record::new(), &mut sequences)
+        );
+        assert_eq!(2, sequences.get("s"));
+
+        match eval_default(&DefaultExpr::Now, &Record::new(), &mut sequences) {
+            Some(DataType::Int(millis)) => assert_eq!(true, millis > 0),
+            _ => return Err(()),
+        }
+
+        let (a, b) = (uuid(), uuid());
+        assert_eq!(36, a.len());
+        assert_eq!(Some('4'), a.chars().nth(14));
+        assert_eq!(false, a == b);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_defaults() -> Result<(), ()> {
+        let schema = vec![
+            DataVar::new("id".to_string(), DataType::Int(0))
+                .with_default(Some(DefaultExpr::NextVal("s".to_string()))),
+            DataVar::new("name".to_string(), DataType::Text("''".to_string())),
+            DataVar::new("score".to_string(), DataType::Real(1.5)),
+        ];
+        let mut sequences = Sequences::new();
+        let mut record = Record::new();
+        record.insert("name".to_string(), DataType::Text("'bob'".to_string()));
+        assert_eq!(
+            Some(()),
+            fill_defaults(&mut record, &schema, &mut sequences)
+        );
+        assert_eq!(Some(&DataType::Int(1)), record.get("id"));
+        assert_eq!(
+            Some(&DataType::Text("'bob'".to_string())),
+            record.get("name")
+        );
+        // literal of schema is not default
+        assert_eq!(None, record.get("score"));
+
+        // supplied value is kept, sequence is not moved
+        let mut record = Record::new();
+        record.insert("id".to_string(), DataType::Int(10));
+        assert_eq!(
+            Some(()),
+            fill_defaults(&mut record, &schema, &mut sequences)
+        );
+        assert_eq!(Some(&DataType::Int(10)), record.get("id"));
+        assert_eq!(1, sequences.get("s"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_types() -> Result<(), ()> {
+        let schema = vec![
+            DataVar::new("age".to_string(), DataType::Int(30)),
+            DataVar::new("name".to_string(), DataType::Text("''".to_string())),
+        ];
+        let mut record = Record::new();
+        record.insert("age".to_string(), DataType::Int(18));
+        record.insert("extra".to_string(), DataType::Bool(true));
+        assert_eq!(Some(()), check_types(&record, &schema));
+
+        record.insert("name".to_string(), DataType::Null);
+        assert_eq!(Some(()), check_types(&record, &schema));
+
+        record.insert("age".to_string(), DataType::Text("'x'".to_string()));
+        assert_eq!(None, check_types(&record, &schema));
+        Ok(())
+    }
+
+    #[test]
+    fn test_eval_var_expr() -> Result<(), ()> {
+        use crate::text_processing::ast::functions::ScalarFunc;
+        use crate::text_processing::ast::types::ScalarExpr;
+
+        let upper = |name: &str| {
+            DefaultExpr::Expr(ScalarExpr::Call(
+                ScalarFunc::Upper,
+                vec![ScalarExpr::Value(DataType::Symbol(name.to_string()))],
+            ))
+        };
+        let mut record = Record::new();
+        record.insert("name".to_string(), DataType::Text("'bob'".to_string()));
+        record.insert("age".to_string(), DataType::Int(2));
+        let mut sequences = Sequences::new();
+
+        let var = DataVar::new("name".to_string(), DataType::Text("''".to_string()))
+            .with_default(Some(upper("name")));
+        assert_eq!(
+            Some(DataType::Text("'BOB'".to_string())),
+            eval_var(&var, &record, &mut sequences)
+        );
+        // computed value does not match type of statement
+        let var =
+            DataVar::new("age".to_string(), DataType::Int(0)).with_default(Some(upper("name")));
+        assert_eq!(None, eval_var(&var, &record, &mut sequences));
+        // function gets wrong type at execution
+        let var = DataVar::new("name".to_string(), DataType::Text("''".to_string()))
+            .with_default(Some(upper("age")));
+        assert_eq!(None, eval_var(&var, &record, &mut sequences));
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+use crate::execution::plan::PlanNode;
+use crate::execution::result::{StatementResult, Status};
+use crate::text_processing::ast::scope::Scope;
+use crate::text_processing::ast::types::{Block, BlockKind, UnaryFuncExpr};
+use crate::text_processing::parser::states::{Parser, ParserDefault};
+
+// executor of statements on some storage
+// begin, commit and rollback mark bounds of transaction
+// failed commit rolls back transaction
+pub trait Executor {
+    fn execute(&mut self, expr: &UnaryFuncExpr) -> StatementResult;
+    // plan of statement, statement is not executed
+    fn explain(&self, expr: &UnaryFuncExpr) -> PlanNode;
+    fn begin(&mut self);
+    fn commit(&mut self) -> Option<()>;
+    fn rollback(&mut self);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// how block has been finished
+pub enum BlockStatus {
+    // statements outside of transaction have been applied one by one
+    Applied,
+    Committed,
+    // rolled back by rollback statement
+    RolledBack,
+    // rolled back automatically because of failed statement
+    Aborted(String),
+}
+
+impl std::fmt::Display for BlockStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockStatus::Applied => write!(f, "applied"),
+            BlockStatus::Committed => write!(f, "committed"),
+            BlockStatus::RolledBack => write!(f, "rolled back"),
+            BlockStatus::Aborted(mes) => write!(f, "rolled back: {}", mes),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+// results of block statements, statements after failed one are not executed
+pub struct BlockResult {
+    status: BlockStatus,
+    results: Vec<StatementResult>,
+}
+
+impl BlockResult {
+    pub fn get_status(&self) -> &BlockStatus {
+        &self.status
+    }
+    pub fn get_results(&self) -> &Vec<StatementResult> {
+        &self.results
+    }
+}
+
+// execute block, transaction block is applied as one atomic unit:
+// failure on any channel of any statement rolls back whole block
+pub fn execute_block<E: Executor>(executor: &mut E, block: &Block) -> BlockResult {
+    let mut results: Vec<StatementResult> = vec![];
+    if block.get_kind() == BlockKind::AutoCommit {
+        for expr in block.get_exprs() {
+            results.push(execute_statement(executor, expr));
+        }
+        return BlockResult {
+            status: BlockStatus::Applied,
+            results,
+        };
+    }
+
+    executor.begin();
+    for expr in block.get_exprs() {
+        let result = execute_statement(executor, expr);
+        let failure = result
+            .get_reports()
+            .iter()
+            .find_map(|e| match e.get_status() {
+                Status::Failed(mes) => Some(format!("{}: {}", e.get_channel(), mes)),
+                Status::Ok => None,
+            });
+        results.push(result);
+        if let Some(mes) = failure {
+            executor.rollback();
+            return BlockResult {
+                status: BlockStatus::Aborted(mes),
+                results,
+            };
+        }
+    }
+    let status = match block.get_kind() {
+        BlockKind::Rollback => {
+            executor.rollback();
+            BlockStatus::RolledBack
+        }
+        _ => match executor.commit() {
+            Some(()) => BlockStatus::Committed,
+            None => BlockStatus::Aborted("commit is not written".to_string()),
+        },
+    };
+    BlockResult { status, results }
+}
+
+// parse and execute script block by block, so let and define of block are kept
+// only when block is applied or committed, definitions are saved then
+// None if block is not parsed, blocks before it are executed
+pub fn execute_script<F>(script: &str, scope: &mut Scope, mut execute: F) -> Option<()>
+where
+    F: FnMut(&Block) -> BlockResult,
+{
+    for text in ParserDefault::split_on_blocks(script) {
+        let saved = scope.clone();
+        let mut is_aborted = false;
+        for block in ParserDefault::from_script_blocks(text, scope)? {
+            let result = execute(&block);
+            is_aborted |= matches!(result.get_status(), BlockStatus::Aborted(_));
+        }
+        match is_aborted {
+            true => *scope = saved,
+            false => scope.save()?,
+        }
+    }
+    Some(())
+}
+
+// explained statement is planned instead of executed
+fn execute_statement<E: Executor>(executor: &mut E, expr: &UnaryFuncExpr) -> StatementResult {
+    match expr.get_explain() {
+        Some(_) => StatementResult::from_plan(expr, executor.explain(expr)),
+        None => executor.execute(expr),
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::executor::{execute_block, execute_script, BlockStatus, Executor};
+    use crate::execution::plan::{build_plan, PlanNode};
+    use crate::execution::result::{fan_out, ChannelOutcome, StatementResult};
+    use crate::text_processing::ast::scope::Scope;
+    use crate::text_processing::ast::types::{
+        Block, BlockKind, ChannelName, DataType, FuncType, UnaryFuncExpr,
+    };
+
+    // executor with counter per channel, missing channel fails
+    struct CounterExecutor {
+        committed: Vec<(String, usize)>,
+        pending: Option<Vec<(String, usize)>>,
+    }
+
+    impl Executor for CounterExecutor {
+        fn execute(&mut self, expr: &UnaryFuncExpr) -> StatementResult {
+            let state = self.pending.as_mut().unwrap_or(&mut self.committed);
+            fan_out(expr, "", |channel| {
+                match state.iter_mut().find(|(e, _)| e == channel.get_name()) {
+                    Some((_, counter)) => {
+                        *counter += 1;
+                        ChannelOutcome::Affected(1, vec![])
+                    }
+                    None => ChannelOutcome::Failed("channel not found".to_string()),
+                }
+            })
+        }
+        fn explain(&self, expr: &UnaryFuncExpr) -> PlanNode {
+            build_plan(expr, "", None)
+        }
+        fn begin(&mut self) {
+            self.pending = Some(self.committed.clone());
+        }
+        fn commit(&mut self) -> Option<()> {
+            self.committed = self.pending.take().unwrap();
+            Some(())
+        }
+        fn rollback(&mut self) {
+            self.pending = None;
+        }
+    }
+
+    fn update(channels: &[&str]) -> UnaryFuncExpr {
+        let channels = channels
+            .iter()
+            .map(|e| ChannelName::from_string(e).unwrap())
+            .collect();
+        UnaryFuncExpr::new(FuncType::OnUpdate, channels, None, None)
+    }
+
+    #[test]
+    fn test_execute_block() -> Result<(), ()> {
+        let mut executor = CounterExecutor {
+            committed: vec![("a".to_string(), 0), ("b".to_string(), 0)],
+            pending: None,
+        };
+
+        let block = Block::new(BlockKind::Commit, vec![update(&["a"]), update(&["a", "b"])]);
+        let result = execute_block(&mut executor, &block);
+        assert_eq!(&BlockStatus::Committed, result.get_status());
+        assert_eq!(
+            vec![("a".to_string(), 2), ("b".to_string(), 1)],
+            executor.committed
+        );
+
+        // failure on second statement rolls back first one
+        let block = Block::new(
+            BlockKind::Commit,
+            vec![update(&["a"]), update(&["b", "c"]), update(&["a"])],
+        );
+        let result = execute_block(&mut executor, &block);
+        assert_eq!(
+            &BlockStatus::Aborted("c: channel not found".to_string()),
+            result.get_status()
+        );
+        assert_eq!(2, result.get_results().len());
+        assert_eq!(
+            vec![("a".to_string(), 2), ("b".to_string(), 1)],
+            executor.committed
+        );
+
+        let block = Block::new(BlockKind::Rollback, vec![update(&["a"])]);
+        let result = execute_block(&mut executor, &block);
+        assert_eq!(&BlockStatus::RolledBack, result.get_status());
+        assert_eq!(("a".to_string(), 2), executor.committed[0]);
+
+        // without transaction failure does not undo previous statements
+        let block = Block::new(BlockKind::AutoCommit, vec![update(&["a"]), update(&["c"])]);
+        let result = execute_block(&mut executor, &block);
+        assert_eq!(&BlockStatus::Applied, result.get_status());
+        assert_eq!(("a".to_string(), 3), executor.committed[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_execute_script() -> Result<(), ()> {
+        let mut executor = CounterExecutor {
+            committed: vec![("a".to_string(), 0)],
+            pending: None,
+        };
+        let mut scope = Scope::new();
+        let mut statuses: Vec<BlockStatus> = vec![];
+        let script = "onCreate(a)(x: int = 1); let v = 2;\
+             begin; let w = 5; define d() = onRead(a); onUpdate(a)()(x: int = :w); onUpdate(b)()(x: int = 1); commit;\
+             onRead(a)(x == :v)";
+        let result = execute_script(script, &mut scope, |block| {
+            let result = execute_block(&mut executor, block);
+            statuses.push(result.get_status().to_owned());
+            result
+        });
+        assert_eq!(Some(()), result);
+        assert_eq!(
+            BlockStatus::Aborted("b: channel not found".to_string()),
+            statuses[1]
+        );
+        // let and define of aborted block are discarded
+        assert_eq!(Some(&DataType::Int(2)), scope.get_params().get_by_name("v"));
+        assert_eq!(None, scope.get_params().get_by_name("w"));
+        assert_eq!(None, scope.get_definition("d"));
+
+        // statement after aborted block can't use its let
+        let result = execute_script(
+            "begin; let w = 5; onUpdate(b)()(x: int = 1); commit; onRead(a)(x == :w)",
+            &mut scope,
+            |block| execute_block(&mut executor, block),
+        );
+        assert_eq!(None, result);
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::execution::memory::StorageExecutor;
+use crate::execution::result::Record;
+use crate::text_processing::ast::types::{DataType, Expiry};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// pause of sweeper between removals of expired records
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+// time of expiry of record created now, only ttl in seconds gives it
+// expiry field is read from record every time, so it can be changed by onUpdate
+// example: ttl(60) -> now + 60000
+pub fn deadline(expiry: &Option<Expiry>, now: i64) -> Option<i64> {
+    match expiry {
+        Some(Expiry::Ttl(seconds)) => {
+            Some(now.saturating_add((*seconds as i64).saturating_mul(1000)))
+        }
+        _ => None,
+    }
+}
+
+// expired record is not read and not changed, it's removed by sweeper later
+// record without value of expiry field never expires
+pub fn is_expired(
+    record: &Record,
+    deadline: Option<i64>,
+    expiry: &Option<Expiry>,
+    now: i64,
+) -> bool {
+    if matches!(deadline, Some(e) if e <= now) {
+        return true;
+    }
+    match expiry {
+        Some(Expiry::Field(field)) => {
+            matches!(record.get(field), Some(DataType::Int(e)) if *e <= now)
+        }
+        _ => false,
+    }
+}
+
+// background thread which removes expired records of executor,
+// executor is locked while records are removed, sweeper is stopped when it's dropped
+pub struct Sweeper {
+    stop: Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Sweeper {
+    // expired records are removed right away, then after every interval
+    pub fn spawn(executor: Arc<Mutex<StorageExecutor>>, interval: Duration) -> Sweeper {
+        let (stop, receiver) = channel::<()>();
+        let handle = std::thread::spawn(move || loop {
+            if let Ok(mut executor) = executor.lock() {
+                executor.sweep();
+            }
+            match receiver.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+        });
+        Sweeper {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Sweeper {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::expiry::{deadline, is_expired};
+    use crate::execution::result::Record;
+    use crate::text_processing::ast::types::{DataType, Expiry};
+
+    #[test]
+    fn test_is_expired() -> Result<(), ()> {
+        let mut record = Record::new();
+        record.insert("expires_at".to_string(), DataType::Int(100));
+        let field = Some(Expiry::Field("expires_at".to_string()));
+        let ttl = Some(Expiry::Ttl(2));
+
+        assert_eq!(Some(2100), deadline(&ttl, 100));
+        assert_eq!(None, deadline(&field, 100));
+        assert_eq!(false, is_expired(&record, Some(2100), &ttl, 2099));
+        assert_eq!(true, is_expired(&record, Some(2100), &ttl, 2100));
+        assert_eq!(false, is_expired(&record, None, &field, 99));
+        assert_eq!(true, is_expired(&record, None, &field, 100));
+        // record without expiry field doesn't expire
+        assert_eq!(false, is_expired(&Record::new(), None, &field, 100));
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use crate::execution::result::Record;
+use crate::text_processing::ast::types::{
+    apply_arithmetic, BinaryExpr, CompareOperator, DataType, ScalarExpr,
+};
+use std::borrow::Cow;
+
+#[cfg(test)]
+mod fixtures;
+
+// value of field which is not in record, like ScalarExpr::eval gives
+static NULL: DataType = DataType::Null;
+// count of fields which slots are not allocated for every record
+const SLOTS: usize = 8;
+
+// expression with fields, it gets values of fields by slots
+type Compute = Box<dyn Fn(&[&DataType]) -> Option<DataType> + Send + Sync>;
+
+// term of compiled comparison
+enum Term {
+    // literal or expression without fields, it's computed once by compile
+    Const(DataType),
+    // slot of field, value is borrowed from record
+    Field(usize),
+    // expression with fields, it's computed for every record
+    Computed(Compute),
+}
+
+impl Term {
+    fn get<'a>(&'a self, slots: &[&'a DataType]) -> Option<Cow<'a, DataType>> {
+        match self {
+            Term::Const(val) => Some(Cow::Borrowed(val)),
+            Term::Field(slot) => Some(Cow::Borrowed(slots[*slot])),
+            Term::Computed(compute) => compute(slots).map(Cow::Owned),
+        }
+    }
+}
+
+// binary expression with operator and terms resolved by compile
+struct Comparison {
+    lterm: Term,
+    rterm: Term,
+    operator: CompareOperator,
+}
+
+impl Comparison {
+    fn is_true(&self, slots: &[&DataType]) -> bool {
+        match (self.lterm.get(slots), self.rterm.get(slots)) {
+            (Some(lterm), Some(rterm)) => self.operator.apply(&lterm, &rterm),
+            _ => false,
+        }
+    }
+}
+
+// filter compiled once for statement and then matched with every record,
+// it gives same result as BinaryExpr::eval for every expression
+// example: age >= 18, lower(name) == 'bob' -> fields [age, name] and two comparisons
+pub struct Filter {
+    // names of fields read by filter, position of name is slot of field
+    fields: Vec<String>,
+    comparisons: Vec<Comparison>,
+    // some expression is false for any record, example: 1 == 2
+    is_empty: bool,
+}
+
+impl Filter {
+    // None filter matches every record
+    pub fn compile(exprs: &Option<Vec<BinaryExpr>>) -> Filter {
+        let mut filter = Filter {
+            fields: vec![],
+            comparisons: vec![],
+            is_empty: false,
+        };
+        for expr in exprs.iter().flatten() {
+            let operator = *expr.get_operator();
+            // expression without fields is true or false for every record
+            if !expr.get_lterm().has_field() && !expr.get_rterm().has_field() {
+                filter.is_empty |= expr.compare() != Some(true);
+                continue;
+            }
+            let lterm = filter.compile_term(expr.get_lterm());
+            let rterm = filter.compile_term(expr.get_rterm());
+            match (lterm, rterm) {
+                (Some(lterm), Some(rterm)) => filter.comparisons.push(Comparison {
+                    lterm,
+                    rterm,
+                    operator,
+                }),
+                _ => filter.is_empty = true,
+            }
+        }
+        filter
+    }
+
+    // true if every expression is true for record
+    pub fn is_matched(&self, record: &Record) -> bool {
+        if self.is_empty {
+            return false;
+        }
+        if self.comparisons.is_empty() {
+            return true;
+        }
+        let get = |e: &String| record.get(e).unwrap_or(&NULL);
+        // slots of most filters are kept on stack
+        if self.fields.len() <= SLOTS {
+            let mut slots = [&NULL; SLOTS];
+            for (slot, e) in slots.iter_mut().zip(self.fields.iter()) {
+                *slot = get(e);
+            }
+            return self.comparisons.iter().all(|e| e.is_true(&slots));
+        }
+        let slots: Vec<&DataType> = self.fields.iter().map(get).collect();
+        self.comparisons.iter().all(|e| e.is_true(&slots))
+    }
+
+    // slot of field, field gets new one at first use
+    fn get_slot(&mut self, name: &str) -> usize {
+        match self.fields.iter().position(|e| e == name) {
+            Some(slot) => slot,
+            None => {
+                self.fields.push(name.to_owned());
+                self.fields.len() - 1
+            }
+        }
+    }
+
+    // None if term can't be computed for any record
+    fn compile_term(&mut self, expr: &ScalarExpr) -> Option<Term> {
+        match expr {
+            ScalarExpr::Value(DataType::Symbol(name)) => Some(Term::Field(self.get_slot(name))),
+            _ if !expr.has_field() => expr.eval(&Record::new()).map(Term::Const),
+            _ => Some(Term::Computed(self.compile_compute(expr))),
+        }
+    }
+
+    fn compile_compute(&mut self, expr: &ScalarExpr) -> Compute {
+        match expr {
+            ScalarExpr::Value(DataType::Symbol(name)) => {
+                let slot = self.get_slot(name);
+                Box::new(move |slots| Some(slots[slot].clone()))
+            }
+            ScalarExpr::Arithmetic(lterm, operator, rterm) => {
+                let operator = *operator;
+                let lterm = self.compile_compute(lterm);
+                let rterm = self.compile_compute(rterm);
+                Box::new(move |slots| apply_arithmetic(lterm(slots)?, &operator, rterm(slots)?))
+            }
+            ScalarExpr::Call(func, args) => {
+                let func = *func;
+                let args: Vec<Compute> = args.iter().map(|e| self.compile_compute(e)).collect();
+                Box::new(move |slots| {
+                    let values: Option<Vec<DataType>> = args.iter().map(|e| e(slots)).collect();
+                    func.apply(values?)
+                })
+            }
+            // literal is cloned like ScalarExpr::eval does, aggregate fails without group
+            _ => {
+                let expr = expr.clone();
+                Box::new(move |_| expr.eval(&Record::new()))
+            }
+        }
+    }
+}
+
+#[cfg(test)] // test module
+mod test {
+    use crate::execution::filter::fixtures::{record, records};
+    use crate::execution::filter::Filter;
+    use crate::execution::result::Record;
+    use crate::text_processing::ast::scope::Scope;
+    use crate::text_processing::ast::types::BinaryExpr;
+    use crate::text_processing::parser::states::{Parser, ParserDefault};
+
+    fn exprs(filter: &str) -> Option<Vec<BinaryExpr>> {
+        let script = format!("onRead(users)({})", filter);
+        let exprs = ParserDefault::from_script(script, &mut Scope::new()).unwrap();
+        exprs[0].get_binary_exprs().clone()
+    }
+
+    // filter is matched like every expression is evaluated for record
+    fn is_matched(record: &Record, exprs: &Option<Vec<BinaryExpr>>) -> bool {
+        exprs
+            .iter()
+            .flatten()
+            .all(|e| e.eval(record).unwrap_or(false))
+    }
+
+    #[test]
+    fn test_filter() -> Result<(), ()> {
+        let records = records(100);
+        let filters = [
+            "id >= 10",
+            "id >= 10, id < 20",
+            "20 > id, name == 'bob'",
+            "id % 3 == 0, upper(name) != 'ALICE'",
+            "score == null",
+            "score * 3 >= id, length(name) == 5",
+            "coalesce(score, 0) < 1.5",
+            "missing == null, id == id",
+            "1 == 1",
+            "1 == 2, id >= 0",
+            "id + 'a' == 1",
+        ];
+        for e in filters.iter() {
+            let exprs = exprs(e);
+            let filter = Filter::compile(&exprs);
+            for record in records.iter() {
+                assert_eq!(is_matched(record, &exprs), filter.is_matched(record));
+            }
+        }
+        assert_eq!(true, Filter::compile(&None).is_matched(&records[0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_slots() -> Result<(), ()> {
+        let filter = Filter::compile(&exprs("id > 1, id < 5, lower(name) == 'bob', 2 > 1"));
+        assert_eq!(vec!["id".to_string(), "name".to_string()], filter.fields);
+        assert_eq!(3, filter.comparisons.len());
+        assert_eq!(false, filter.is_empty);
+
+        let filter = Filter::compile(&exprs("id > 1, 2 < 1"));
+        assert_eq!(true, filter.is_empty);
+        assert_eq!(false, filter.is_matched(&record(2, "bob", None)));
+        Ok(())
+    }
+}
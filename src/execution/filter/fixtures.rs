@@ -0,0 +1,27 @@
+// records of filter tests and benchmarks, benchmarks include this file by path
+use super::{DataType, Record};
+
+pub fn record(id: i64, name: &str, score: Option<f64>) -> Record {
+    let mut record = Record::new();
+    record.insert("id".to_string(), DataType::Int(id));
+    record.insert("name".to_string(), DataType::Text(format!("'{}'", name)));
+    if let Some(score) = score {
+        record.insert("score".to_string(), DataType::Real(score));
+    }
+    record
+}
+
+// every 7th record hasn't score
+pub fn records(count: i64) -> Vec<Record> {
+    let names = ["bob", "alice", "eve"];
+    (0..count)
+        .map(|e| {
+            let score = if e % 7 == 0 {
+                None
+            } else {
+                Some(e as f64 / 3.0)
+            };
+            record(e, names[e as usize % names.len()], score)
+        })
+        .collect()
+}
@@ -0,0 +1,253 @@
+use crate::execution::result::Record;
+use crate::text_processing::ast::types::{BinaryExpr, CompareOperator, DataType, ScalarExpr};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+
+#[derive(Debug, Clone, PartialEq)]
+// value of indexed field, ordered like values are compared by filter
+// example: null < false < 1 < 1.5 < 'a'
+struct IndexKey(DataType);
+
+impl Eq for IndexKey {}
+
+impl PartialOrd for IndexKey {
+    fn partial_cmp(&self, other: &IndexKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IndexKey {
+    // NaN is never indexed, so every pair of keys is ordered
+    fn cmp(&self, other: &IndexKey) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl IndexKey {
+    // missing field is indexed as null, like it's read by filter
+    fn from_record(record: &Record, field: &str) -> Option<IndexKey> {
+        IndexKey::from_value(record.get(field).cloned().unwrap_or(DataType::Null))
+    }
+
+    // NaN matches no comparison, so it's not indexed
+    fn from_value(value: DataType) -> Option<IndexKey> {
+        match value {
+            DataType::Real(val) if val.is_nan() => None,
+            DataType::Symbol(_) | DataType::Param(_) => None,
+            value => Some(IndexKey(value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// ordered index over one field of channel: value of field -> rows of records
+// row ids are stable, so change of one record changes only its entry
+// example: onIndex(users)(age)
+pub struct Index {
+    field: String,
+    entries: BTreeMap<IndexKey, BTreeSet<u64>>,
+}
+
+impl Index {
+    pub fn new<'a, T: ToString, I: IntoIterator<Item = (&'a u64, &'a Record)>>(
+        field: T,
+        records: I,
+    ) -> Index {
+        let mut index = Index {
+            field: field.to_string(),
+            entries: BTreeMap::new(),
+        };
+        for (row, record) in records {
+            index.insert(record, *row);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, record: &Record, row: u64) {
+        if let Some(key) = IndexKey::from_record(record, &self.field) {
+            self.entries.entry(key).or_default().insert(row);
+        }
+    }
+
+    pub fn update(&mut self, old: &Record, new: &Record, row: u64) {
+        self.delete(old, row);
+        self.insert(new, row);
+    }
+
+    pub fn delete(&mut self, record: &Record, row: u64) {
+        let key = match IndexKey::from_record(record, &self.field) {
+            Some(key) => key,
+            None => return,
+        };
+        if let Some(rows) = self.entries.get_mut(&key) {
+            rows.remove(&row);
+            if rows.is_empty() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    // sorted rows of records with field matched by comparison with value
+    // None if comparison can't be answered by index
+    // example: (>=, 18) -> rows of records with age >= 18
+    pub fn lookup(&self, operator: &CompareOperator, value: &DataType) -> Option<Vec<u64>> {
+        let key = match IndexKey::from_value(value.clone()) {
+            Some(key) => key,
+            // NaN matches nothing
+            None if matches!(value, DataType::Real(_)) => return Some(vec![]),
+            None => return None,
+        };
+        let range = match operator {
+            CompareOperator::Eq => (Bound::Included(key.clone()), Bound::Included(key)),
+            CompareOperator::Gt => (Bound::Excluded(key), Bound::Unbounded),
+            CompareOperator::Ge => (Bound::Included(key), Bound::Unbounded),
+            CompareOperator::Lt => (Bound::Unbounded, Bound::Excluded(key)),
+            CompareOperator::Le => (Bound::Unbounded, Bound::Included(key)),
+            CompareOperator::Ne => return None,
+        };
+        let mut rows: Vec<u64> = self
+            .entries
+            .range(range)
+            .flat_map(|(_, e)| e.iter().cloned())
+            .collect();
+        rows.sort_unstable();
+        Some(rows)
+    }
+}
+
+// rows of records which can be matched by filter, None if no filter uses index
+// every filter is checked on records after lookup, so index only narrows scan
+// example: (age >= 18, name == 'bob') with index on age -> rows of age >= 18
+pub fn lookup_filter(
+    indexes: &BTreeMap<String, Index>,
+    exprs: &Option<Vec<BinaryExpr>>,
+) -> Option<Vec<u64>> {
+    choose_lookup(indexes, exprs).map(|(_, rows)| rows)
+}
+
+// filter answered by index and rows found by it, the narrowest lookup is chosen
+pub fn choose_lookup<'a>(
+    indexes: &BTreeMap<String, Index>,
+    exprs: &'a Option<Vec<BinaryExpr>>,
+) -> Option<(&'a BinaryExpr, Vec<u64>)> {
+    exprs
+        .iter()
+        .flatten()
+        .filter_map(|e| {
+            let (field, operator, value) = split_comparison(e)?;
+            Some((e, indexes.get(field)?.lookup(&operator, value)?))
+        })
+        .min_by_key(|(_, rows)| rows.len())
+}
+
+// field, operator and constant of comparison with field on left side
+// example: 18 < age -> (age, >, 18)
+fn split_comparison(expr: &BinaryExpr) -> Option<(&String, CompareOperator, &DataType)> {
+    let operator = *expr.get_operator();
+    match (expr.get_lterm(), expr.get_rterm()) {
+        (ScalarExpr::Value(DataType::Symbol(_)), ScalarExpr::Value(DataType::Symbol(_))) => None,
+        (ScalarExpr::Value(DataType::Symbol(field)), ScalarExpr::Value(value)) => {
+            Some((field, operator, value))
+        }
+        (ScalarExpr::Value(value), ScalarExpr::Value(DataType::Symbol(field))) => {
+            let operator = match operator {
+                CompareOperator::Gt => CompareOperator::Lt,
+                CompareOperator::Ge => CompareOperator::Le,
+                CompareOperator::Lt => CompareOperator::Gt,
+                CompareOperator::Le => CompareOperator::Ge,
+                operator => operator,
+            };
+            Some((field, operator, value))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::index::{lookup_filter, Index};
+    use crate::execution::result::Record;
+    use crate::text_processing::ast::types::{BinaryExpr, CompareOperator, DataType};
+    use std::collections::BTreeMap;
+
+    fn record(age: Option<DataType>) -> Record {
+        let mut record = Record::new();
+        if let Some(age) = age {
+            record.insert("age".to_string(), age);
+        }
+        record
+    }
+
+    fn filter(lterm: DataType, rterm: DataType, operator: &str) -> Vec<BinaryExpr> {
+        let operator = CompareOperator::from_string(operator).unwrap();
+        vec![BinaryExpr::new(lterm, rterm, operator)]
+    }
+
+    #[test]
+    fn test_index_lookup() -> Result<(), ()> {
+        let records: BTreeMap<u64, Record> = vec![
+            record(Some(DataType::Int(30))),
+            record(Some(DataType::Int(18))),
+            record(None),
+            record(Some(DataType::Real(f64::NAN))),
+            record(Some(DataType::Int(30))),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| (i as u64, e))
+        .collect();
+        let mut index = Index::new("age", &records);
+        assert_eq!(
+            Some(vec![0, 4]),
+            index.lookup(&CompareOperator::Eq, &DataType::Int(30))
+        );
+        assert_eq!(
+            Some(vec![0, 1, 4]),
+            index.lookup(&CompareOperator::Ge, &DataType::Int(18))
+        );
+        // null of missing field is less than any value, like filter compares it
+        assert_eq!(
+            Some(vec![1, 2]),
+            index.lookup(&CompareOperator::Lt, &DataType::Int(30))
+        );
+        assert_eq!(None, index.lookup(&CompareOperator::Ne, &DataType::Int(30)));
+
+        index.update(&records[&1], &record(Some(DataType::Int(40))), 1);
+        assert_eq!(
+            Some(vec![0, 1, 4]),
+            index.lookup(&CompareOperator::Gt, &DataType::Int(20))
+        );
+        // rows of other records are kept by delete
+        index.delete(&records[&0], 0);
+        assert_eq!(
+            Some(vec![4]),
+            index.lookup(&CompareOperator::Eq, &DataType::Int(30))
+        );
+        assert_eq!(
+            Some(vec![1]),
+            index.lookup(&CompareOperator::Eq, &DataType::Int(40))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_filter() -> Result<(), ()> {
+        let mut records = BTreeMap::new();
+        records.insert(0, record(Some(DataType::Int(30))));
+        records.insert(1, record(Some(DataType::Int(18))));
+        let mut indexes = BTreeMap::new();
+        indexes.insert("age".to_string(), Index::new("age", &records));
+        let age = DataType::Symbol("age".to_string());
+
+        let exprs = Some(filter(DataType::Int(20), age.clone(), "<"));
+        assert_eq!(Some(vec![0]), lookup_filter(&indexes, &exprs));
+        let exprs = Some(filter(age.clone(), DataType::Int(20), "!="));
+        assert_eq!(None, lookup_filter(&indexes, &exprs));
+        let exprs = Some(filter(DataType::Symbol("name".to_string()), age, "=="));
+        assert_eq!(None, lookup_filter(&indexes, &exprs));
+        assert_eq!(None, lookup_filter(&indexes, &None));
+        Ok(())
+    }
+}
@@ -0,0 +1,1126 @@
+use crate::environment::logger::Logger;
+use crate::execution::defaults::{check_types, eval_var, fill_defaults, now_millis, Sequences};
+use crate::execution::executor::Executor;
+use crate::execution::expiry::deadline;
+use crate::execution::filter::Filter;
+use crate::execution::mvcc::ReadSnapshot;
+use crate::execution::pipeline::{project, read_statement, upsert_record};
+use crate::execution::plan::{build_plan, PlanNode, Statistics};
+use crate::execution::result::{fan_out, ChannelOutcome, Record, StatementResult, UpsertAction};
+use crate::execution::storage::{FileStorage, MemoryStorage, StorageBackend};
+use crate::execution::wal::Change;
+use crate::execution::watch::{ChangeEvent, ChangeFeed, ChangeKind, Subscription};
+use crate::text_processing::ast::types::{
+    BinaryExpr, ChannelName, DataType, DataVar, FuncType, Projection, ScalarExpr, UnaryFuncExpr,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// executor of statements on channels of storage, channels are kept in memory by default
+// channel is created by first onCreate, next onCreate inserts records,
+// fields not supplied by them are filled by computed defaults of schema
+// and supplied fields must have types declared by schema
+// with --uri file:///path/to/db every change is written to log before it is applied
+pub struct StorageExecutor {
+    node_path: String,
+    storage: Box<dyn StorageBackend>,
+    in_transaction: bool,
+    // changes of transaction are published on commit
+    pending_events: Vec<(ChannelName, ChangeEvent)>,
+    feed: ChangeFeed,
+    subscriptions: Vec<Subscription>,
+}
+
+impl StorageExecutor {
+    pub fn new<T: ToString>(node_path: T) -> StorageExecutor {
+        StorageExecutor::with_storage(node_path, Box::new(MemoryStorage::new()))
+    }
+
+    // executor on storage directory, state is restored from checkpoint and log
+    pub fn open<T: ToString, P: Into<PathBuf>>(node_path: T, dir: P) -> Option<StorageExecutor> {
+        let storage = FileStorage::open(dir)?;
+        Some(StorageExecutor::with_storage(node_path, Box::new(storage)))
+    }
+
+    // executor in memory with state created by changes
+    pub fn replay<T: ToString>(node_path: T, changes: Vec<Change>) -> Option<StorageExecutor> {
+        let storage = MemoryStorage::replay(changes)?;
+        Some(StorageExecutor::with_storage(node_path, Box::new(storage)))
+    }
+
+    // executor on any storage, example: remote storage or storage of tests
+    pub fn with_storage<T: ToString>(
+        node_path: T,
+        storage: Box<dyn StorageBackend>,
+    ) -> StorageExecutor {
+        StorageExecutor {
+            node_path: node_path.to_string(),
+            storage,
+            in_transaction: false,
+            pending_events: vec![],
+            feed: ChangeFeed::new(),
+            subscriptions: vec![],
+        }
+    }
+
+    // count of last versions kept by storage for reads as of version and history
+    // remote storage keeps versions by its server, so it's not changed
+    pub fn with_retention(self, retention: u64) -> StorageExecutor {
+        if let Some(versions) = self.storage.get_versions() {
+            versions.with_retention(retention);
+        }
+        self
+    }
+
+    // changes which create current state from empty storage
+    pub fn dump(&self) -> Vec<Change> {
+        self.storage.dump()
+    }
+
+    // restore state of empty storage from changes, storage gets them all together
+    pub fn restore(&mut self, changes: Vec<Change>) -> Option<()> {
+        if !self.storage.get_channels().is_empty() {
+            Logger::error("storage is not empty");
+            return None;
+        }
+        // changes are checked first, so storage doesn't keep broken state
+        MemoryStorage::replay(changes.clone())?;
+        self.storage.apply(&changes)
+    }
+
+    // remove expired records, it's done outside of transaction only
+    // returns count of removed records
+    pub fn sweep(&mut self) -> usize {
+        if self.in_transaction {
+            return 0;
+        }
+        let now = now_millis();
+        let mut changes: Vec<Change> = vec![];
+        let mut events: Vec<(ChannelName, ChangeEvent)> = vec![];
+        for name in self.storage.get_channels() {
+            let channel = match ChannelName::from_string(&name) {
+                Some(channel) => channel,
+                None => continue,
+            };
+            for (row, record) in self.storage.scan_expired(&name, now) {
+                changes.push(Change::Delete(name.to_owned(), row));
+                let event =
+                    ChangeEvent::new(name.to_owned(), ChangeKind::Deleted, Some(record), None);
+                events.push((channel.clone(), event));
+            }
+        }
+        if changes.is_empty() || self.write(changes).is_none() {
+            return 0;
+        }
+        let removed = events.len();
+        self.publish(events);
+        removed
+    }
+
+    // storage of executor, example: server of remote storage answers requests by it
+    pub fn get_storage(&self) -> &dyn StorageBackend {
+        self.storage.as_ref()
+    }
+
+    // changes applied to storage as they are, example: changes sent by client of remote storage
+    pub fn apply(&mut self, changes: &[Change]) -> Option<()> {
+        self.write(changes.to_vec())
+    }
+
+    // consistent read of committed records, it can be read from other thread
+    // while executor goes on, changes of open transaction are not seen
+    // None if storage doesn't keep versions of records
+    pub fn read_snapshot(&self) -> Option<ReadSnapshot> {
+        self.storage.read_snapshot()
+    }
+
+    pub fn get_feed(&self) -> &ChangeFeed {
+        &self.feed
+    }
+
+    // subscriptions of executed onWatch statements
+    pub fn take_subscriptions(&mut self) -> Vec<Subscription> {
+        self.subscriptions.drain(..).collect()
+    }
+
+    // records of channel, None if channel is not created
+    pub fn get_records(&self, channel: &ChannelName) -> Option<Vec<Record>> {
+        let channel = channel.resolve(&self.node_path);
+        let records = self.storage.scan(&channel.to_string(), &None)?;
+        Some(records.into_iter().map(|(_, e)| e).collect())
+    }
+
+    fn write(&mut self, changes: Vec<Change>) -> Option<()> {
+        self.storage.apply(&changes)
+    }
+
+    fn publish(&mut self, events: Vec<(ChannelName, ChangeEvent)>) {
+        match self.in_transaction {
+            true => self.pending_events.extend(events),
+            false => {
+                for (channel, event) in events {
+                    self.feed.publish(&channel, event);
+                }
+            }
+        }
+    }
+
+    // rows and records of channel matched by filter, None if channel is not created
+    fn find(
+        &self,
+        channel: &ChannelName,
+        exprs: &Option<Vec<BinaryExpr>>,
+    ) -> Option<Vec<(u64, Record)>> {
+        let mut records = self.storage.scan(&channel.to_string(), exprs)?;
+        let filter = Filter::compile(exprs);
+        records.retain(|(_, e)| filter.is_matched(e));
+        Some(records)
+    }
+
+    // records of channel found by filter, None if channel is not created
+    fn fetch(&self, channel: &ChannelName, exprs: &Option<Vec<BinaryExpr>>) -> Option<Vec<Record>> {
+        // records are filtered again by read, with joined channels
+        let records = self.storage.scan(&channel.to_string(), exprs)?;
+        Some(records.into_iter().map(|(_, e)| e).collect())
+    }
+
+    // onRead is read from snapshot taken at start of statement, returned read doesn't need executor,
+    // so records can be read after executor is released, see SharedExecutor
+    // current read in open transaction reads storage to see changes of transaction,
+    // onRead as of version and history don't see them
+    pub fn prepare_read(&self, expr: &UnaryFuncExpr) -> Box<dyn FnOnce() -> StatementResult> {
+        let node_path = self.node_path.to_owned();
+        match self.snapshot_of(expr) {
+            Some(Ok(snapshot)) => {
+                let expr = expr.clone();
+                Box::new(move || snapshot.execute(&expr, &node_path))
+            }
+            Some(Err(mes)) => {
+                let result = fan_out(expr, &node_path, |_| ChannelOutcome::Failed(mes.to_owned()));
+                Box::new(move || result)
+            }
+            None => {
+                let result = read_statement(expr, &node_path, |channel, exprs| {
+                    self.fetch(channel, exprs)
+                });
+                Box::new(move || result)
+            }
+        }
+    }
+
+    // snapshot which answers onRead, None if read goes to storage,
+    // error if retained versions can't answer it
+    fn snapshot_of(&self, expr: &UnaryFuncExpr) -> Option<Result<ReadSnapshot, String>> {
+        let versions = self.storage.get_versions();
+        let is_current = expr.get_as_of().is_none() && !expr.is_history();
+        if is_current && (self.in_transaction || versions.is_none()) {
+            return None;
+        }
+        let snapshot = match (versions, expr.get_as_of()) {
+            (None, _) => Err("record versions are not kept by storage".to_string()),
+            (Some(versions), Some(as_of)) => versions.snapshot_as_of(as_of),
+            // outside of transaction storage keeps last version, so its indexes find rows of snapshot
+            (Some(versions), None) if is_current => {
+                let lookups = self.lookups(expr);
+                Ok(versions.snapshot().with_lookups(lookups))
+            }
+            (Some(versions), None) => Ok(versions.snapshot()),
+        };
+        Some(snapshot)
+    }
+
+    // rows found by indexes of storage for filter of read, by channel
+    fn lookups(&self, expr: &UnaryFuncExpr) -> BTreeMap<String, Vec<u64>> {
+        let lookups = expr.get_channel_names().iter().filter_map(|e| {
+            let name = e.resolve(&self.node_path).to_string();
+            let rows = self.storage.lookup_rows(&name, expr.get_binary_exprs())?;
+            Some((name, rows))
+        });
+        lookups.collect()
+    }
+
+    fn create(&mut self, channel: &ChannelName, expr: &UnaryFuncExpr) -> ChannelOutcome {
+        let vars: &[DataVar] = expr.get_vars().as_deref().unwrap_or(&[]);
+        let before = match self.storage.get_sequences() {
+            Some(sequences) => sequences,
+            None => return failed_sequences(),
+        };
+        let mut sequences = before.clone();
+        let mut changes: Vec<Change> = vec![];
+        let name = channel.to_string();
+        // channel is created only with its first record, ttl is declared by it only
+        let (schema, expiry, row) = match self.storage.get_schema(&name) {
+            Some(_) if expr.get_expiry().is_some() => return failed_ttl(),
+            Some(schema) => (
+                schema,
+                self.storage.get_expiry(&name),
+                self.storage.get_next_row(&name).unwrap_or_default(),
+            ),
+            None => {
+                changes.push(Change::Create(name.to_owned(), vars.to_vec()));
+                if let Some(expiry) = expr.get_expiry() {
+                    changes.push(Change::Ttl(name.to_owned(), expiry.clone()));
+                }
+                (vars.to_vec(), expr.get_expiry().clone(), 0)
+            }
+        };
+        let mut record = Record::new();
+        for var in vars {
+            match eval_var(var, &record, &mut sequences) {
+                Some(value) => record.insert(var.get_name().to_owned(), value),
+                None => return failed_statement(var),
+            };
+        }
+        if fill_defaults(&mut record, &schema, &mut sequences).is_none() {
+            return ChannelOutcome::Failed("default of schema is not computed".to_string());
+        }
+        if check_types(&record, &schema).is_none() {
+            return failed_types();
+        }
+        let returned = match returning(&[record.clone()], expr.get_returning()) {
+            Some(returned) => returned,
+            None => return failed_returning(),
+        };
+        changes.push(Change::Insert(name.to_owned(), row, record.clone()));
+        if let Some(deadline) = deadline(&expiry, now_millis()) {
+            changes.push(Change::Deadline(name.to_owned(), row, deadline));
+        }
+        changes.extend(sequence_changes(&before, &sequences));
+        if self.write(changes).is_none() {
+            return failed_write();
+        }
+        let event = ChangeEvent::new(name, ChangeKind::Created, None, Some(record));
+        self.publish(vec![(channel.clone(), event)]);
+        ChannelOutcome::Affected(1, returned)
+    }
+
+    // onUpdate and onUpsert: statements are computed over record before update
+    fn update(&mut self, channel: &ChannelName, expr: &UnaryFuncExpr) -> ChannelOutcome {
+        let vars: &[DataVar] = expr.get_vars().as_deref().unwrap_or(&[]);
+        let before = match self.storage.get_sequences() {
+            Some(sequences) => sequences,
+            None => return failed_sequences(),
+        };
+        let mut sequences = before.clone();
+        let found = match self.find(channel, expr.get_binary_exprs()) {
+            Some(found) => found,
+            None => return ChannelOutcome::Failed("channel not found".to_string()),
+        };
+        let name = channel.to_string();
+        let schema = self.storage.get_schema(&name).unwrap_or_default();
+        // new records are computed first, so failed statement changes nothing
+        let mut updates: Vec<(u64, Record, Record)> = vec![];
+        for (row, old) in found {
+            let mut new = old.clone();
+            for var in vars {
+                match eval_var(var, &old, &mut sequences) {
+                    Some(value) => new.insert(var.get_name().to_owned(), value),
+                    None => return failed_statement(var),
+                };
+            }
+            if check_types(&new, &schema).is_none() {
+                return failed_types();
+            }
+            updates.push((row, old, new));
+        }
+
+        let is_upsert = *expr.get_func_type() == FuncType::OnUpsert;
+        if is_upsert && updates.is_empty() {
+            let mut record =
+                match upsert_record(expr.get_binary_exprs(), expr.get_vars(), &mut sequences) {
+                    Some(record) => record,
+                    None => return ChannelOutcome::Failed("statement is not computed".to_string()),
+                };
+            if fill_defaults(&mut record, &schema, &mut sequences).is_none() {
+                return ChannelOutcome::Failed("default of schema is not computed".to_string());
+            }
+            if check_types(&record, &schema).is_none() {
+                return failed_types();
+            }
+            let returned = match returning(&[record.clone()], expr.get_returning()) {
+                Some(returned) => returned,
+                None => return failed_returning(),
+            };
+            let row = self.storage.get_next_row(&name).unwrap_or_default();
+            let mut changes = vec![Change::Insert(name.to_owned(), row, record.clone())];
+            if let Some(deadline) = deadline(&self.storage.get_expiry(&name), now_millis()) {
+                changes.push(Change::Deadline(name.to_owned(), row, deadline));
+            }
+            changes.extend(sequence_changes(&before, &sequences));
+            if self.write(changes).is_none() {
+                return failed_write();
+            }
+            let event = ChangeEvent::new(name, ChangeKind::Created, None, Some(record));
+            self.publish(vec![(channel.clone(), event)]);
+            return ChannelOutcome::Upserted(UpsertAction::Inserted, 1, returned);
+        }
+
+        let records: Vec<Record> = updates.iter().map(|(_, _, e)| e.clone()).collect();
+        let returned = match returning(&records, expr.get_returning()) {
+            Some(returned) => returned,
+            None => return failed_returning(),
+        };
+        let mut changes: Vec<Change> = vec![];
+        let mut events: Vec<(ChannelName, ChangeEvent)> = vec![];
+        for (row, old, new) in updates {
+            let event = ChangeEvent::new(
+                name.to_owned(),
+                ChangeKind::Updated,
+                Some(old),
+                Some(new.clone()),
+            );
+            events.push((channel.clone(), event));
+            changes.push(Change::Update(name.to_owned(), row, new));
+        }
+        changes.extend(sequence_changes(&before, &sequences));
+        if self.write(changes).is_none() {
+            return failed_write();
+        }
+        let affected = events.len();
+        self.publish(events);
+        match is_upsert {
+            true => ChannelOutcome::Upserted(UpsertAction::Updated, affected, returned),
+            false => ChannelOutcome::Affected(affected, returned),
+        }
+    }
+
+    fn delete(&mut self, channel: &ChannelName, expr: &UnaryFuncExpr) -> ChannelOutcome {
+        let (rows, deleted): (Vec<u64>, Vec<Record>) =
+            match self.find(channel, expr.get_binary_exprs()) {
+                Some(found) => found.into_iter().unzip(),
+                None => return ChannelOutcome::Failed("channel not found".to_string()),
+            };
+        let returned = match returning(&deleted, expr.get_returning()) {
+            Some(returned) => returned,
+            None => return failed_returning(),
+        };
+        let changes = rows
+            .into_iter()
+            .map(|e| Change::Delete(channel.to_string(), e))
+            .collect();
+        if self.write(changes).is_none() {
+            return failed_write();
+        }
+        let affected = deleted.len();
+        let events = deleted
+            .into_iter()
+            .map(|e| {
+                let event =
+                    ChangeEvent::new(channel.to_string(), ChangeKind::Deleted, Some(e), None);
+                (channel.clone(), event)
+            })
+            .collect();
+        self.publish(events);
+        ChannelOutcome::Affected(affected, returned)
+    }
+
+    // index is built over records of channel, existing index is kept
+    fn index(&mut self, channel: &ChannelName, expr: &UnaryFuncExpr) -> ChannelOutcome {
+        let field = match expr.get_projection().as_deref() {
+            Some([projection]) => match projection.get_expr() {
+                ScalarExpr::Value(DataType::Symbol(field)) => field.to_owned(),
+                _ => return ChannelOutcome::Failed("index field is not correctly".to_string()),
+            },
+            _ => return ChannelOutcome::Failed("index field is not correctly".to_string()),
+        };
+        let name = channel.to_string();
+        let affected = match self.storage.get_row_count(&name) {
+            Some(affected) => affected,
+            None => return ChannelOutcome::Failed("channel not found".to_string()),
+        };
+        if self.storage.has_index(&name, &field) {
+            return ChannelOutcome::Affected(affected, vec![]);
+        }
+        if self.write(vec![Change::Index(name, field)]).is_none() {
+            return failed_write();
+        }
+        ChannelOutcome::Affected(affected, vec![])
+    }
+}
+
+impl Statistics for StorageExecutor {
+    fn get_row_count(&self, channel: &ChannelName) -> Option<usize> {
+        self.storage.get_row_count(&channel.to_string())
+    }
+    fn get_index_lookup(
+        &self,
+        channel: &ChannelName,
+        exprs: &Option<Vec<BinaryExpr>>,
+    ) -> Option<(BinaryExpr, usize)> {
+        self.storage.get_index_lookup(&channel.to_string(), exprs)
+    }
+}
+
+impl Executor for StorageExecutor {
+    fn execute(&mut self, expr: &UnaryFuncExpr) -> StatementResult {
+        let node_path = self.node_path.to_owned();
+        match expr.get_func_type() {
+            FuncType::OnRead => self.prepare_read(expr)(),
+            FuncType::OnCreate => fan_out(expr, &node_path, |channel| self.create(channel, expr)),
+            FuncType::OnUpdate | FuncType::OnUpsert => {
+                fan_out(expr, &node_path, |channel| self.update(channel, expr))
+            }
+            FuncType::OnDelete => fan_out(expr, &node_path, |channel| self.delete(channel, expr)),
+            FuncType::OnIndex => fan_out(expr, &node_path, |channel| self.index(channel, expr)),
+            FuncType::OnWatch => {
+                let subscription = self.feed.subscribe(expr, &node_path);
+                self.subscriptions.push(subscription);
+                fan_out(expr, &node_path, |_| ChannelOutcome::Affected(0, vec![]))
+            }
+        }
+    }
+    fn explain(&self, expr: &UnaryFuncExpr) -> PlanNode {
+        build_plan(expr, &self.node_path, Some(self))
+    }
+    fn begin(&mut self) {
+        self.storage.begin();
+        self.in_transaction = true;
+    }
+    // transaction is committed when storage keeps its changes
+    fn commit(&mut self) -> Option<()> {
+        if self.storage.commit().is_none() {
+            self.rollback();
+            return None;
+        }
+        self.in_transaction = false;
+        let events: Vec<(ChannelName, ChangeEvent)> = self.pending_events.drain(..).collect();
+        self.publish(events);
+        Some(())
+    }
+    fn rollback(&mut self) {
+        self.storage.rollback();
+        self.in_transaction = false;
+        self.pending_events.clear();
+    }
+}
+
+#[derive(Clone)]
+// executor shared with sweeper and other threads, it's locked for one statement,
+// onRead is read from its snapshot after executor is released, so reads don't block writers
+pub struct SharedExecutor {
+    executor: Arc<Mutex<StorageExecutor>>,
+}
+
+impl SharedExecutor {
+    pub fn new(executor: StorageExecutor) -> SharedExecutor {
+        SharedExecutor {
+            executor: Arc::new(Mutex::new(executor)),
+        }
+    }
+
+    pub fn get_executor(&self) -> Arc<Mutex<StorageExecutor>> {
+        self.executor.clone()
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, StorageExecutor> {
+        self.executor.lock().unwrap()
+    }
+}
+
+impl Executor for SharedExecutor {
+    fn execute(&mut self, expr: &UnaryFuncExpr) -> StatementResult {
+        match expr.get_func_type() {
+            FuncType::OnRead => {
+                let read = self.lock().prepare_read(expr);
+                read()
+            }
+            _ => self.lock().execute(expr),
+        }
+    }
+    fn explain(&self, expr: &UnaryFuncExpr) -> PlanNode {
+        self.lock().explain(expr)
+    }
+    fn begin(&mut self) {
+        self.lock().begin()
+    }
+    fn commit(&mut self) -> Option<()> {
+        self.lock().commit()
+    }
+    fn rollback(&mut self) {
+        self.lock().rollback()
+    }
+}
+
+// records of returning clause, empty without returning clause
+fn returning(records: &[Record], returning: &Option<Vec<Projection>>) -> Option<Vec<Record>> {
+    match returning {
+        Some(_) => records
+            .iter()
+            .map(|e| project(e.clone(), returning))
+            .collect(),
+        None => Some(vec![]),
+    }
+}
+
+// sequences advanced by statement
+fn sequence_changes(before: &Sequences, after: &Sequences) -> Vec<Change> {
+    after
+        .get_values()
+        .iter()
+        .filter(|(name, value)| before.get(name) != **value)
+        .map(|(name, value)| Change::Sequence(name.to_owned(), *value))
+        .collect()
+}
+
+fn failed_write() -> ChannelOutcome {
+    ChannelOutcome::Failed("change is not written to log".to_string())
+}
+
+fn failed_sequences() -> ChannelOutcome {
+    ChannelOutcome::Failed("sequences are not read from storage".to_string())
+}
+
+fn failed_statement(var: &DataVar) -> ChannelOutcome {
+    ChannelOutcome::Failed(format!("statement {} is not computed", var.get_name()))
+}
+
+fn failed_types() -> ChannelOutcome {
+    ChannelOutcome::Failed("record does not match types of schema".to_string())
+}
+
+fn failed_returning() -> ChannelOutcome {
+    ChannelOutcome::Failed("returning is not computed".to_string())
+}
+
+fn failed_ttl() -> ChannelOutcome {
+    ChannelOutcome::Failed("ttl is declared by first onCreate of channel only".to_string())
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::executor::{execute_block, BlockStatus, Executor};
+    use crate::execution::memory::{SharedExecutor, StorageExecutor};
+    use crate::execution::result::{Status, UpsertAction};
+    use crate::execution::wal::Change;
+    use crate::execution::watch::{spawn_events, ChangeEvent};
+    use crate::text_processing::ast::scope::Scope;
+    use crate::text_processing::ast::types::{
+        BinaryExpr, ChannelName, CompareOperator, DataType, Expiry, UnaryFuncExpr,
+    };
+    use crate::text_processing::parser::states::{Parser, ParserDefault};
+
+    fn parse(script: &str) -> Vec<UnaryFuncExpr> {
+        ParserDefault::from_script(script, &mut Scope::new()).unwrap()
+    }
+
+    fn text(val: &str) -> DataType {
+        DataType::Text(format!("'{}'", val))
+    }
+
+    #[test]
+    fn test_storage_executor() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("mynode");
+        let script =
+            "onCreate(users)(id: int = nextval(users_id), name: text = 'bob', age: int = 20);\
+            onCreate(users)(name: text = 'alice', age: int = 30) returning(id);\
+            onCreate(users)(name: text = 'eve', age: int = 10);\
+            onUpdate(users)(age >= 20)(age: int = age + 1) returning(name, age);\
+            onDelete(users)(name == 'eve');\
+            onRead(users)(age > 0)(upper(name) as name, age) orderBy(age desc) limit(1);\
+            onRead(orders)";
+        let results: Vec<_> = parse(script).iter().map(|e| executor.execute(e)).collect();
+
+        // id is filled by default of schema
+        assert_eq!(
+            Some(&DataType::Int(2)),
+            results[1].get_rows()[0].get_record().get("id")
+        );
+        assert_eq!(2, results[3].get_affected());
+        assert_eq!(
+            Some(&DataType::Int(21)),
+            results[3].get_rows()[0].get_record().get("age")
+        );
+        assert_eq!(1, results[4].get_affected());
+        assert_eq!(1, results[5].get_rows().len());
+        assert_eq!("mynode.users", results[5].get_rows()[0].get_channel());
+        assert_eq!(
+            Some(&text("ALICE")),
+            results[5].get_rows()[0].get_record().get("name")
+        );
+        assert_eq!(1, results[5].get_affected());
+        assert_eq!(
+            &Status::Failed("channel not found".to_string()),
+            results[6].get_reports()[0].get_status()
+        );
+        let users = ChannelName::from_string("users").unwrap();
+        assert_eq!(2, executor.get_records(&users).unwrap().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_read() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("");
+        let script = "onCreate(users)(id: int = 1, country: text = 'fr');\
+            onCreate(users)(id: int = 2, country: text = 'fr');\
+            onCreate(users)(id: int = 3, country: text = 'de');\
+            onCreate(orders)(user_id: int = 1, total: int = 5);\
+            onCreate(orders)(user_id: int = 1, total: int = 7);\
+            onRead(users)()(country, count(*) as n) groupBy(country) orderBy(n desc);\
+            onRead(orders)(users.country == 'fr')(orders.total) join(users)(orders.user_id == users.id);\
+            onUpsert(users)(id == 4)(country: text = 'es')";
+        let results: Vec<_> = parse(script).iter().map(|e| executor.execute(e)).collect();
+
+        let groups = results[5].get_rows();
+        assert_eq!(2, groups.len());
+        assert_eq!(Some(&text("fr")), groups[0].get_record().get("country"));
+        assert_eq!(Some(&DataType::Int(2)), groups[0].get_record().get("n"));
+        assert_eq!(2, results[6].get_rows().len());
+        assert_eq!(
+            Some(UpsertAction::Inserted),
+            results[7].get_reports()[0].get_upsert_action()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_transaction() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("");
+        let mut scope = Scope::new();
+        let blocks = ParserDefault::from_script_blocks(
+            "onCreate(a)(x: int = 1);\
+             onCreate(b)(x: int = 1);\
+             begin; onUpdate(a)()(x: int = 2); onUpdate(c)()(x: int = 2); commit;\
+             begin; onDelete(b); rollback",
+            &mut scope,
+        )
+        .unwrap();
+        let subscription = executor
+            .get_feed()
+            .subscribe(&parse("onWatch(a, b)")[0], "");
+        let statuses: Vec<BlockStatus> = blocks
+            .iter()
+            .map(|e| execute_block(&mut executor, e).get_status().to_owned())
+            .collect();
+        assert_eq!(
+            BlockStatus::Aborted("c: channel not found".to_string()),
+            statuses[1]
+        );
+        assert_eq!(BlockStatus::RolledBack, statuses[2]);
+
+        let a = ChannelName::from_string("a").unwrap();
+        let b = ChannelName::from_string("b").unwrap();
+        assert_eq!(
+            Some(&DataType::Int(1)),
+            executor.get_records(&a).unwrap()[0].get("x")
+        );
+        assert_eq!(1, executor.get_records(&b).unwrap().len());
+
+        // changes of rolled back transactions are not published
+        subscription.cancel();
+        assert_eq!(2, subscription.count());
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_watch_events() -> Result<(), ()> {
+        use std::sync::mpsc::channel;
+        use std::time::Duration;
+
+        let mut executor = StorageExecutor::new("");
+        let exprs = parse(
+            "onCreate(a)(x: int = 1);\
+             onWatch(a)(x > 1);\
+             onCreate(a)(x: int = 2);\
+             onCreate(a)(x: int = 3)",
+        );
+        executor.execute(&exprs[0]);
+        executor.execute(&exprs[1]);
+        let subscription = executor.take_subscriptions().remove(0);
+        let canceller = subscription.get_canceller();
+        let (sender, receiver) = channel();
+        let handle = spawn_events(subscription, move |e| {
+            let _ = sender.send(e);
+        });
+        let x = |event: ChangeEvent| event.get_new().as_ref().unwrap().get("x").cloned();
+
+        // event arrives before next statement of script is executed
+        executor.execute(&exprs[2]);
+        let event = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(Some(DataType::Int(2)), x(event));
+        executor.execute(&exprs[3]);
+        let event = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(Some(DataType::Int(3)), x(event));
+
+        canceller.cancel();
+        handle.join().unwrap();
+        assert_eq!(true, receiver.recv().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_file() -> Result<(), ()> {
+        let dir = std::env::temp_dir().join(format!("qdb_storage_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut executor = StorageExecutor::open("", &dir).unwrap();
+        let mut scope = Scope::new();
+        let blocks = ParserDefault::from_script_blocks(
+            "onCreate(users)(id: int = nextval(users_id), name: text = 'bob');\
+             onCreate(users)(name: text = 'alice');\
+             begin; onUpdate(users)(id == 1)(name: text = 'eve'); onDelete(users)(id == 2); commit;\
+             begin; onDelete(users); rollback",
+            &mut scope,
+        )
+        .unwrap();
+        for block in blocks.iter() {
+            execute_block(&mut executor, block);
+        }
+        drop(executor);
+
+        // state is replayed from log, sequence goes on
+        let mut executor = StorageExecutor::open("", &dir).unwrap();
+        let users = ChannelName::from_string("users").unwrap();
+        let records = executor.get_records(&users).unwrap();
+        assert_eq!(1, records.len());
+        assert_eq!(Some(&text("eve")), records[0].get("name"));
+        let result = executor.execute(&parse("onCreate(users)(name: text = 'x') returning(id)")[0]);
+        assert_eq!(
+            Some(&DataType::Int(3)),
+            result.get_rows()[0].get_record().get("id")
+        );
+
+        // versions before replay are not kept, reads older than it are rejected
+        let results: Vec<_> = parse(
+            "onRead(users) as of timestamp 0;\
+             onRead(users) as of version 0;\
+             onRead(users)()(name) as of version 1",
+        )
+        .iter()
+        .map(|e| executor.execute(e))
+        .collect();
+        match results[0].get_reports()[0].get_status() {
+            Status::Failed(mes) => assert_eq!(
+                true,
+                mes.starts_with("timestamp 0 is before storage was opened")
+            ),
+            _ => return Err(()),
+        }
+        assert_eq!(
+            &Status::Failed("version 0 is not retained".to_string()),
+            results[1].get_reports()[0].get_status()
+        );
+        assert_eq!(1, results[2].get_rows().len());
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_types() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("");
+        let script = "onCreate(users)(id: int = 1, age: int = 30);\
+            onCreate(users)(id: int = 2, age: text = 'old');\
+            onUpdate(users)(id == 1)(age: text = 'old');\
+            onUpsert(users)(id == 3)(age: real = 1.5);\
+            onCreate(users)(id: int = 4);\
+            onUpdate(users)(id == 1)(age: int = 31)";
+        let results: Vec<_> = parse(script).iter().map(|e| executor.execute(e)).collect();
+        let failed = Status::Failed("record does not match types of schema".to_string());
+        for result in results[1..4].iter() {
+            assert_eq!(&failed, result.get_reports()[0].get_status());
+        }
+        assert_eq!(1, results[4].get_affected());
+        assert_eq!(1, results[5].get_affected());
+
+        let users = ChannelName::from_string("users").unwrap();
+        let records = executor.get_records(&users).unwrap();
+        assert_eq!(2, records.len());
+        assert_eq!(Some(&DataType::Int(31)), records[0].get("age"));
+        // literal of first record is not default of schema
+        assert_eq!(None, records[1].get("age"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_index() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("");
+        let script = "onCreate(users)(id: int = 1, age: int = 30);\
+            onCreate(users)(id: int = 2, age: int = 18);\
+            onIndex(users)(age);\
+            onCreate(users)(id: int = 3, age: int = 25);\
+            onCreate(users)(id: int = 4, age: int = 30);\
+            onUpdate(users)(id == 1)(age: int = 17);\
+            onDelete(users)(age == 18);\
+            onRead(users)(age >= 20)(id);\
+            onRead(users)(20 > age)(id);\
+            onIndex(orders)(age)";
+        let results: Vec<_> = parse(script).iter().map(|e| executor.execute(e)).collect();
+        assert_eq!(2, results[2].get_affected());
+
+        let ids = |i: usize| -> Vec<DataType> {
+            results[i]
+                .get_rows()
+                .iter()
+                .map(|e| e.get_record().get("id").unwrap().clone())
+                .collect()
+        };
+        assert_eq!(vec![DataType::Int(3), DataType::Int(4)], ids(7));
+        assert_eq!(vec![DataType::Int(1)], ids(8));
+        assert_eq!(
+            &Status::Failed("channel not found".to_string()),
+            results[9].get_reports()[0].get_status()
+        );
+
+        // index is changed with records, rows are kept by delete
+        assert_eq!(true, executor.storage.has_index("users", "age"));
+        let rows = |age: i64| -> Vec<u64> {
+            let age = BinaryExpr::new(
+                DataType::Symbol("age".to_string()),
+                DataType::Int(age),
+                CompareOperator::Eq,
+            );
+            let records = executor.storage.scan("users", &Some(vec![age])).unwrap();
+            records.into_iter().map(|(i, _)| i).collect()
+        };
+        assert_eq!(vec![0], rows(17));
+        assert_eq!(vec![2], rows(25));
+        assert_eq!(vec![3], rows(30));
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_explain() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("");
+        let mut scope = Scope::new();
+        let blocks = ParserDefault::from_script_blocks(
+            "onCreate(users)(id: int = 1, age: int = 30);\
+             onCreate(users)(id: int = 2, age: int = 18);\
+             onIndex(users)(age);\
+             explain onDelete(users)(age > 20, id != 0);\
+             explain json onRead(users)(id == 1)",
+            &mut scope,
+        )
+        .unwrap();
+        let result = execute_block(&mut executor, &blocks[0]);
+        let results = result.get_results();
+        assert_eq!(
+            "delete users (rows: 1)\n\
+             -> filter id != 0 (rows: 1)\n  \
+               -> index lookup users by age > 20 (rows: 1)",
+            results[3].to_string()
+        );
+        assert_eq!(
+            "{\"operation\": \"filter\", \"detail\": \"id == 1\", \"rows\": 2, \"children\": \
+             [{\"operation\": \"scan\", \"detail\": \"users\", \"rows\": 2, \"children\": []}]}",
+            results[4].to_string()
+        );
+        // explained statement is not executed
+        let users = ChannelName::from_string("users").unwrap();
+        assert_eq!(2, executor.get_records(&users).unwrap().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_snapshot() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("");
+        let exprs = parse(
+            "onCreate(users)(id: int = 1, age: int = 30);\
+             onCreate(users)(id: int = 2, age: int = 18);\
+             onUpdate(users)()(age: int = age + 1);\
+             onDelete(users)(id == 1)",
+        );
+        executor.execute(&exprs[0]);
+        executor.execute(&exprs[1]);
+        let snapshot = executor.read_snapshot().unwrap();
+
+        // snapshot is read by other thread while records are changed
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let reader = std::thread::spawn(move || {
+            receiver.recv().unwrap();
+            let expr = &parse("onRead(users)(age > 20)(id, age)")[0];
+            snapshot.execute(expr, "")
+        });
+        executor.begin();
+        executor.execute(&exprs[2]);
+        // changes of open transaction are not seen by snapshot
+        let records = executor
+            .read_snapshot()
+            .unwrap()
+            .get_records("users")
+            .unwrap();
+        assert_eq!(Some(&DataType::Int(18)), records[1].get("age"));
+        executor.commit();
+        executor.execute(&exprs[3]);
+        sender.send(()).unwrap();
+
+        let result = reader.join().unwrap();
+        assert_eq!(1, result.get_rows().len());
+        assert_eq!(
+            Some(&DataType::Int(30)),
+            result.get_rows()[0].get_record().get("age")
+        );
+        let records = executor
+            .read_snapshot()
+            .unwrap()
+            .get_records("users")
+            .unwrap();
+        assert_eq!(1, records.len());
+        assert_eq!(Some(&DataType::Int(19)), records[0].get("age"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_prepared_read() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("");
+        let exprs = parse(
+            "onCreate(users)(id: int = 1, age: int = 30);\
+             onIndex(users)(age);\
+             onRead(users)(age > 20)(id);\
+             onCreate(users)(id: int = 2, age: int = 40)",
+        );
+        executor.execute(&exprs[0]);
+        executor.execute(&exprs[1]);
+
+        // read doesn't hold executor, record created after start of read is not seen by it
+        let read = executor.prepare_read(&exprs[2]);
+        executor.execute(&exprs[3]);
+        assert_eq!(1, read().get_rows().len());
+        assert_eq!(2, executor.execute(&exprs[2]).get_rows().len());
+
+        // current read in open transaction sees changes of transaction
+        executor.begin();
+        executor.execute(&parse("onDelete(users)(id == 1)")[0]);
+        assert_eq!(1, executor.execute(&exprs[2]).get_rows().len());
+        executor.rollback();
+
+        // shared executor is released while onRead is read
+        let mut shared = SharedExecutor::new(executor);
+        let blocks = ParserDefault::from_script_blocks(
+            "begin; onUpdate(users)(id == 2)(age: int = 10); onRead(users)(age > 20)(id); commit;\
+             onRead(users)(age > 20)(id)",
+            &mut Scope::new(),
+        )
+        .unwrap();
+        let results: Vec<_> = blocks
+            .iter()
+            .map(|e| execute_block(&mut shared, e))
+            .collect();
+        assert_eq!(&BlockStatus::Committed, results[0].get_status());
+        assert_eq!(1, results[0].get_results()[1].get_rows().len());
+        assert_eq!(1, results[1].get_results()[0].get_rows().len());
+        assert_eq!(false, shared.get_executor().is_poisoned());
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_expiry() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("");
+        let script = "onCreate(sessions)(token: text = 'a') ttl(3600);\
+            onCreate(sessions)(token: text = 'b');\
+            onCreate(tmp)(x: int = 1) ttl(0);\
+            onCreate(cache)(key: text = 'old', expires_at: int = 1) ttl(expires_at);\
+            onCreate(cache)(key: text = 'new', expires_at: int = 99999999999999);\
+            onRead(cache)()(key);\
+            onUpdate(cache)()(expires_at: int = 2);\
+            onRead(tmp, sessions);\
+            onCreate(sessions)(token: text = 'c') ttl(60)";
+        let results: Vec<_> = parse(script).iter().map(|e| executor.execute(e)).collect();
+
+        // expired records are not read and not changed
+        assert_eq!(1, results[5].get_rows().len());
+        assert_eq!(
+            Some(&text("new")),
+            results[5].get_rows()[0].get_record().get("key")
+        );
+        assert_eq!(1, results[6].get_affected());
+        assert_eq!(2, results[7].get_rows().len());
+        // ttl of next onCreate is not ignored silently
+        assert_eq!(
+            &Status::Failed("ttl is declared by first onCreate of channel only".to_string()),
+            results[8].get_reports()[0].get_status()
+        );
+        assert_eq!(
+            true,
+            executor
+                .read_snapshot()
+                .unwrap()
+                .get_records("cache")
+                .unwrap()
+                .is_empty()
+        );
+
+        // ttl of channel is kept with records
+        let restored = StorageExecutor::replay("", executor.dump()).unwrap();
+        assert_eq!(
+            Some(Expiry::Ttl(3600)),
+            restored.storage.get_expiry("sessions")
+        );
+        let deadlines = restored
+            .dump()
+            .iter()
+            .filter(|e| matches!(e, Change::Deadline(channel, _, _) if channel == "sessions"))
+            .count();
+        assert_eq!(2, deadlines);
+
+        assert_eq!(3, executor.sweep());
+        let cache = ChannelName::from_string("cache").unwrap();
+        assert_eq!(true, executor.get_records(&cache).unwrap().is_empty());
+        assert_eq!(0, executor.sweep());
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_as_of() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("");
+        let script = "onCreate(users)(id: int = 1, age: int = 20);\
+            onCreate(users)(id: int = 2, age: int = 30);\
+            onUpdate(users)(id == 1)(age: int = 21);\
+            onDelete(users)(id == 2);\
+            onRead(users)(age >= 20)(id, age) as of version 2;\
+            onRead(users)(age == 20)(age, _version, _version_end) history;\
+            onRead(users)()(count(*) as n) history as of version 3;\
+            onRead(users) as of version 10";
+        let results: Vec<_> = parse(script).iter().map(|e| executor.execute(e)).collect();
+
+        assert_eq!(2, results[4].get_rows().len());
+        assert_eq!(
+            Some(&DataType::Int(20)),
+            results[4].get_rows()[0].get_record().get("age")
+        );
+        let history = results[5].get_rows();
+        assert_eq!(2, history.len());
+        assert_eq!(Some(&DataType::Int(21)), history[1].get_record().get("age"));
+        assert_eq!(
+            Some(&DataType::Int(3)),
+            history[0].get_record().get("_version_end")
+        );
+        assert_eq!(
+            Some(&DataType::Int(3)),
+            results[6].get_rows()[0].get_record().get("n")
+        );
+        assert_eq!(
+            &Status::Failed("version 10 is not retained".to_string()),
+            results[7].get_reports()[0].get_status()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_executor_retention() -> Result<(), ()> {
+        let mut executor = StorageExecutor::new("").with_retention(1);
+        let exprs = parse(
+            "onCreate(users)(id: int = 1, age: int = 20);\
+             onUpdate(users)(id == 1)(age: int = 21);\
+             onUpdate(users)(id == 1)(age: int = 22);\
+             onRead(users)()(age) as of version 2;\
+             onRead(users)()(age) as of version 1",
+        );
+        for expr in exprs[..3].iter() {
+            executor.execute(expr);
+        }
+
+        // versions older than retained ones are collected
+        executor.storage.get_versions().unwrap().collect_garbage();
+        let result = executor.execute(&exprs[3]);
+        assert_eq!(
+            Some(&DataType::Int(21)),
+            result.get_rows()[0].get_record().get("age")
+        );
+        assert_eq!(
+            &Status::Failed("version 1 is not retained".to_string()),
+            executor.execute(&exprs[4]).get_reports()[0].get_status()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,15 @@
+pub mod backup;
+pub mod defaults;
+pub mod executor;
+pub mod expiry;
+pub mod filter;
+pub mod index;
+pub mod memory;
+pub mod mvcc;
+pub mod pipeline;
+pub mod plan;
+pub mod remote;
+pub mod result;
+pub mod storage;
+pub mod wal;
+pub mod watch;
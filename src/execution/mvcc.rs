@@ -0,0 +1,628 @@
+use crate::execution::defaults::now_millis;
+use crate::execution::expiry::is_expired;
+use crate::execution::filter::Filter;
+use crate::execution::pipeline::read_statement;
+use crate::execution::result::{fan_out, ChannelOutcome, Record, StatementResult};
+use crate::execution::wal::Change;
+use crate::text_processing::ast::types::{
+    AsOf, BinaryExpr, DataType, Expiry, FuncType, UnaryFuncExpr,
+};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+// garbage is collected when count of old versions grows by interval since last collection
+pub const GC_INTERVAL: usize = 1000;
+// default count of last committed versions kept for reads as of version and history,
+// it's set by --retention
+pub const RETAINED_VERSIONS: u64 = 1000;
+
+// fields of version added to records of history
+pub const ROW_FIELD: &str = "_row";
+pub const VERSION_FIELD: &str = "_version";
+pub const VERSION_END_FIELD: &str = "_version_end";
+pub const VALID_FROM_FIELD: &str = "_valid_from";
+pub const VALID_TO_FIELD: &str = "_valid_to";
+
+#[derive(Debug, Clone, PartialEq)]
+// version of record, it's visible to snapshots from begin until end
+// example: record updated by version 5 has end 5 and new version with begin 5
+struct Version {
+    record: Arc<Record>,
+    begin: u64,
+    // None while record is not updated or deleted
+    end: Option<u64>,
+    // time of expiry given by ttl of channel
+    deadline: Option<i64>,
+}
+
+impl Version {
+    fn is_visible(&self, version: u64) -> bool {
+        match self.end {
+            Some(end) => self.begin <= version && version < end,
+            None => self.begin <= version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+// versions of records of channel
+struct VersionedChannel {
+    // version which created channel
+    begin: u64,
+    expiry: Option<Expiry>,
+    // versions of record by row id, from oldest, last one is current until record is deleted
+    rows: BTreeMap<u64, Vec<Version>>,
+}
+
+impl VersionedChannel {
+    // current version of record of row, None if record is deleted
+    fn get_current(&mut self, row: u64) -> Option<&mut Version> {
+        self.rows
+            .get_mut(&row)?
+            .last_mut()
+            .filter(|e| e.end.is_none())
+    }
+}
+
+#[derive(Debug, Default)]
+struct VersionStore {
+    // version of last committed changes
+    version: u64,
+    channels: BTreeMap<String, VersionedChannel>,
+    // versions read by snapshots -> count of snapshots
+    readers: BTreeMap<u64, usize>,
+    // count of versions which are not current
+    garbage: usize,
+    // count of old versions kept by last garbage collection
+    retained: usize,
+    // count of last versions kept by garbage collection
+    retention: u64,
+    // oldest version which can be read, older versions are collected
+    horizon: u64,
+    // version and time of commit, from horizon
+    commits: Vec<(u64, i64)>,
+    // time when storage was opened by replay of log, replayed changes get one version,
+    // so versions committed before it are not kept
+    opened: Option<i64>,
+}
+
+impl VersionStore {
+    // committed changes get next version, changes of records only are versioned
+    // record of version is shared with storage when shared gives it for row
+    fn commit(
+        &mut self,
+        changes: &[Change],
+        now: i64,
+        shared: &dyn Fn(&str, u64) -> Option<Arc<Record>>,
+    ) {
+        let version = self.version + 1;
+        // record changed again by later change of same commit is not shared
+        let versioned = |channel: &str, row: u64, record: &Record| {
+            shared(channel, row)
+                .filter(|e| e.as_ref() == record)
+                .unwrap_or_else(|| Arc::new(record.clone()))
+        };
+        for change in changes {
+            match change {
+                Change::Create(channel, _) => {
+                    let table = VersionedChannel {
+                        begin: version,
+                        ..VersionedChannel::default()
+                    };
+                    self.channels.entry(channel.to_owned()).or_insert(table);
+                }
+                Change::Drop(channel) => {
+                    self.channels.remove(channel);
+                }
+                Change::Insert(channel, row, record) => {
+                    if let Some(table) = self.channels.get_mut(channel) {
+                        table.rows.entry(*row).or_default().push(Version {
+                            record: versioned(channel, *row, record),
+                            begin: version,
+                            end: None,
+                            deadline: None,
+                        });
+                    }
+                }
+                Change::Update(channel, row, record) => {
+                    if let Some(table) = self.channels.get_mut(channel) {
+                        if let Some(current) = table.get_current(*row) {
+                            current.end = Some(version);
+                            let new = Version {
+                                record: versioned(channel, *row, record),
+                                begin: version,
+                                end: None,
+                                deadline: current.deadline,
+                            };
+                            table.rows.entry(*row).or_default().push(new);
+                            self.garbage += 1;
+                        }
+                    }
+                }
+                Change::Delete(channel, row) => {
+                    if let Some(current) = self
+                        .channels
+                        .get_mut(channel)
+                        .and_then(|e| e.get_current(*row))
+                    {
+                        current.end = Some(version);
+                        self.garbage += 1;
+                    }
+                }
+                Change::Ttl(channel, expiry) => {
+                    if let Some(table) = self.channels.get_mut(channel) {
+                        table.expiry = Some(expiry.clone());
+                    }
+                }
+                Change::Deadline(channel, row, deadline) => {
+                    if let Some(current) = self
+                        .channels
+                        .get_mut(channel)
+                        .and_then(|e| e.get_current(*row))
+                    {
+                        current.deadline = Some(*deadline);
+                    }
+                }
+                Change::Index(_, _) | Change::Sequence(_, _) => {}
+            }
+        }
+        self.version = version;
+        self.commits.push((version, now));
+        if self.garbage >= self.retained + GC_INTERVAL {
+            self.collect_garbage();
+        }
+    }
+
+    // old versions which are not visible to any snapshot and not retained are removed
+    fn collect_garbage(&mut self) {
+        let retained = self.version.saturating_sub(self.retention);
+        let oldest = match self.readers.keys().next() {
+            Some(reader) => retained.min(*reader),
+            None => retained,
+        };
+        let mut garbage = 0;
+        for table in self.channels.values_mut() {
+            for versions in table.rows.values_mut() {
+                versions.retain(|e| !matches!(e.end, Some(end) if end <= oldest));
+                garbage += versions.iter().filter(|e| e.end.is_some()).count();
+            }
+            table.rows.retain(|_, e| !e.is_empty());
+        }
+        self.garbage = garbage;
+        self.retained = garbage;
+        self.horizon = self.horizon.max(oldest);
+        let horizon = self.horizon;
+        self.commits.retain(|(e, _)| *e >= horizon);
+    }
+
+    // version read as of version or time, error if it's not retained
+    // example: as of timestamp between commits of versions 4 and 5 -> 4
+    fn resolve(&self, as_of: &AsOf) -> Result<u64, String> {
+        let version = match as_of {
+            AsOf::Version(version) => *version,
+            AsOf::Timestamp(timestamp) if matches!(self.opened, Some(e) if *timestamp < e) => {
+                let mes = format!(
+                    "{} is before storage was opened at timestamp {}, older versions are not kept",
+                    as_of,
+                    self.opened.unwrap_or_default()
+                );
+                return Err(mes);
+            }
+            AsOf::Timestamp(timestamp) => {
+                let position = self.commits.iter().rposition(|(_, e)| e <= timestamp);
+                match position {
+                    Some(position) => self.commits[position].0,
+                    // nothing is committed before time, it's empty storage if nothing is collected
+                    None => 0,
+                }
+            }
+        };
+        match version >= self.horizon && version <= self.version {
+            true => Ok(version),
+            false => Err(format!("{} is not retained", as_of)),
+        }
+    }
+
+    // time of commit of version
+    fn get_commit_time(&self, version: u64) -> Option<i64> {
+        self.commits
+            .iter()
+            .find(|(e, _)| *e == version)
+            .map(|(_, e)| *e)
+    }
+
+    // every version of records matched by filter, committed until version
+    // record is matched if one of its versions is matched
+    fn get_history(
+        &self,
+        channel: &str,
+        version: u64,
+        exprs: &Option<Vec<BinaryExpr>>,
+    ) -> Option<Vec<Record>> {
+        let table = self.channels.get(channel).filter(|e| e.begin <= version)?;
+        let filter = Filter::compile(exprs);
+        let mut history: Vec<(u64, &Version)> = vec![];
+        for (row, versions) in table.rows.iter() {
+            let versions = versions.iter().filter(|e| e.begin <= version);
+            if versions.clone().any(|e| filter.is_matched(&e.record)) {
+                history.extend(versions.map(|e| (*row, e)));
+            }
+        }
+
+        let time = |version: Option<u64>| match version.and_then(|e| self.get_commit_time(e)) {
+            Some(time) => DataType::Int(time),
+            None => DataType::Null,
+        };
+        let records = history.into_iter().map(|(row, e)| {
+            // version ended after snapshot is current for snapshot
+            let end = e.end.filter(|end| *end <= version);
+            let mut record = e.record.as_ref().clone();
+            record.insert(ROW_FIELD.to_string(), DataType::Int(row as i64));
+            record.insert(VERSION_FIELD.to_string(), DataType::Int(e.begin as i64));
+            let version_end = end.map_or(DataType::Null, |end| DataType::Int(end as i64));
+            record.insert(VERSION_END_FIELD.to_string(), version_end);
+            record.insert(VALID_FROM_FIELD.to_string(), time(Some(e.begin)));
+            record.insert(VALID_TO_FIELD.to_string(), time(end));
+            record
+        });
+        Some(records.collect())
+    }
+
+    // records of channel visible to snapshot, in order of rows
+    // expired records are not visible, they're not removed by sweeper yet
+    // rows narrow records to given rows, they're sorted
+    fn get_records(
+        &self,
+        channel: &str,
+        version: u64,
+        now: i64,
+        rows: Option<&Vec<u64>>,
+    ) -> Option<Vec<Arc<Record>>> {
+        let table = self.channels.get(channel).filter(|e| e.begin <= version)?;
+        let versions: Box<dyn Iterator<Item = &Vec<Version>>> = match rows {
+            Some(rows) => Box::new(rows.iter().filter_map(|e| table.rows.get(e))),
+            None => Box::new(table.rows.values()),
+        };
+        let records = versions
+            .filter_map(|e| e.iter().rev().find(|e| e.is_visible(version)))
+            .filter(|e| !is_expired(&e.record, e.deadline, &table.expiry, now))
+            .map(|e| e.record.clone());
+        Some(records.collect())
+    }
+}
+
+#[derive(Clone)]
+// multi-version records of committed changes, shared by executor and snapshots
+// executor commits changes, snapshots read records of their version from other threads,
+// so long read is not changed by next commits and doesn't wait for them
+pub struct Versions {
+    store: Arc<RwLock<VersionStore>>,
+}
+
+impl Versions {
+    pub fn new() -> Versions {
+        let store = VersionStore {
+            retention: RETAINED_VERSIONS,
+            ..VersionStore::default()
+        };
+        Versions {
+            store: Arc::new(RwLock::new(store)),
+        }
+    }
+
+    // set count of last versions kept for reads as of version and history
+    pub fn with_retention(self, retention: u64) -> Versions {
+        self.store.write().unwrap().retention = retention;
+        self
+    }
+
+    pub fn commit(&self, changes: &[Change]) {
+        self.commit_shared(changes, |_, _| None);
+    }
+
+    // commit changes with records kept by storage, so records are not copied for versions
+    // shared gives current record of row after changes, None if it's removed by them
+    pub fn commit_shared<F: Fn(&str, u64) -> Option<Arc<Record>>>(
+        &self,
+        changes: &[Change],
+        shared: F,
+    ) {
+        if !changes.is_empty() {
+            let mut store = self.store.write().unwrap();
+            store.commit(changes, now_millis(), &shared);
+        }
+    }
+
+    // snapshot of last committed version, its versions are kept until it's dropped
+    pub fn snapshot(&self) -> ReadSnapshot {
+        let mut store = self.store.write().unwrap();
+        let version = store.version;
+        *store.readers.entry(version).or_default() += 1;
+        ReadSnapshot {
+            version,
+            time: None,
+            versions: self.clone(),
+            lookups: BTreeMap::new(),
+        }
+    }
+
+    // snapshot of version read as of version or time, error if it's not retained
+    // records are expired by time of version, so they're read like at that time
+    pub fn snapshot_as_of(&self, as_of: &AsOf) -> Result<ReadSnapshot, String> {
+        let mut store = self.store.write().unwrap();
+        let version = store.resolve(as_of)?;
+        let time = match as_of {
+            AsOf::Timestamp(timestamp) => Some(*timestamp),
+            AsOf::Version(_) => store.get_commit_time(version),
+        };
+        *store.readers.entry(version).or_default() += 1;
+        Ok(ReadSnapshot {
+            version,
+            time,
+            versions: self.clone(),
+            lookups: BTreeMap::new(),
+        })
+    }
+
+    // versions of storage opened by replay of log, last version keeps replayed changes,
+    // so reads as of older version or time are rejected
+    pub fn set_opened(&self) {
+        let mut store = self.store.write().unwrap();
+        store.horizon = store.version;
+        store.opened = Some(
+            store
+                .get_commit_time(store.version)
+                .unwrap_or_else(now_millis),
+        );
+    }
+
+    // remove old versions which are not read by snapshots
+    pub fn collect_garbage(&self) {
+        self.store.write().unwrap().collect_garbage();
+    }
+
+    fn release(&self, version: u64) {
+        let mut store = self.store.write().unwrap();
+        if let Some(count) = store.readers.get_mut(&version) {
+            *count -= 1;
+            if *count == 0 {
+                store.readers.remove(&version);
+            }
+        }
+    }
+}
+
+impl Default for Versions {
+    fn default() -> Versions {
+        Versions::new()
+    }
+}
+
+// consistent read of storage at one version, it can be moved to other thread
+// snapshot doesn't see changes committed after it's taken
+pub struct ReadSnapshot {
+    version: u64,
+    // time of expiry of records, None expires them by current time
+    time: Option<i64>,
+    versions: Versions,
+    // rows of channels found by index for filter of read, channel -> sorted rows
+    lookups: BTreeMap<String, Vec<u64>>,
+}
+
+impl ReadSnapshot {
+    // set rows found by index when snapshot is taken, filtered reads of channel are narrowed by them
+    // index is read at version of snapshot, so rows are the same rows which snapshot sees
+    pub fn with_lookups(mut self, lookups: BTreeMap<String, Vec<u64>>) -> ReadSnapshot {
+        self.lookups = lookups;
+        self
+    }
+
+    // records of channel at version of snapshot, None if channel is not created
+    pub fn get_records(&self, channel: &str) -> Option<Vec<Record>> {
+        self.read_records(channel, None)
+    }
+
+    fn read_records(&self, channel: &str, rows: Option<&Vec<u64>>) -> Option<Vec<Record>> {
+        // lock is held while versions are selected, records are copied after it
+        let records = self.versions.store.read().unwrap().get_records(
+            channel,
+            self.version,
+            self.get_time(),
+            rows,
+        )?;
+        Some(records.iter().map(|e| e.as_ref().clone()).collect())
+    }
+
+    // every version of records of channel matched by filter, committed until snapshot
+    // fields of version are added to records
+    // example: {id: 1, _row: 0, _version: 3, _version_end: 5, _valid_from: .., _valid_to: ..}
+    pub fn get_history(
+        &self,
+        channel: &str,
+        exprs: &Option<Vec<BinaryExpr>>,
+    ) -> Option<Vec<Record>> {
+        let store = self.versions.store.read().unwrap();
+        store.get_history(channel, self.version, exprs)
+    }
+
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    fn get_time(&self) -> i64 {
+        self.time.unwrap_or_else(now_millis)
+    }
+
+    // execute onRead on records of snapshot, other statements change nothing
+    pub fn execute(&self, expr: &UnaryFuncExpr, node_path: &str) -> StatementResult {
+        match expr.get_func_type() {
+            // versions are matched by filter, then every version of matched records is read
+            FuncType::OnRead if expr.is_history() => {
+                let unfiltered = expr.clone().with_binary_exprs(None);
+                read_statement(&unfiltered, node_path, |channel, _| {
+                    self.get_history(&channel.to_string(), expr.get_binary_exprs())
+                })
+            }
+            // records are filtered by read after joins, rows found by index only narrow them,
+            // joined channels are read without filter, so rows found by index are not used for them
+            FuncType::OnRead => read_statement(expr, node_path, |channel, exprs| {
+                let name = channel.to_string();
+                let rows = exprs.as_ref().and(self.lookups.get(&name));
+                self.read_records(&name, rows)
+            }),
+            _ => fan_out(expr, node_path, |_| {
+                ChannelOutcome::Failed("snapshot is read only".to_string())
+            }),
+        }
+    }
+}
+
+impl Drop for ReadSnapshot {
+    fn drop(&mut self) {
+        self.versions.release(self.version);
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::mvcc::{Versions, VERSION_END_FIELD, VERSION_FIELD};
+    use crate::execution::result::Record;
+    use crate::execution::wal::Change;
+    use crate::text_processing::ast::types::{AsOf, BinaryExpr, CompareOperator, DataType};
+
+    fn record(age: i64) -> Record {
+        let mut record = Record::new();
+        record.insert("age".to_string(), DataType::Int(age));
+        record
+    }
+
+    fn ages(records: Option<Vec<Record>>) -> Vec<DataType> {
+        records
+            .unwrap()
+            .iter()
+            .map(|e| e.get("age").unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_versions() -> Result<(), ()> {
+        let versions = Versions::new().with_retention(0);
+        let empty = versions.snapshot();
+        versions.commit(&[
+            Change::Create("users".to_string(), vec![]),
+            Change::Insert("users".to_string(), 0, record(1)),
+            Change::Insert("users".to_string(), 1, record(2)),
+        ]);
+        let first = versions.snapshot();
+        versions.commit(&[
+            Change::Update("users".to_string(), 0, record(10)),
+            Change::Delete("users".to_string(), 1),
+            Change::Insert("users".to_string(), 2, record(3)),
+        ]);
+        let second = versions.snapshot();
+
+        assert_eq!(None, empty.get_records("users"));
+        assert_eq!(
+            vec![DataType::Int(1), DataType::Int(2)],
+            ages(first.get_records("users"))
+        );
+        assert_eq!(
+            vec![DataType::Int(10), DataType::Int(3)],
+            ages(second.get_records("users"))
+        );
+
+        // versions of first snapshot are kept until it's dropped
+        versions.collect_garbage();
+        assert_eq!(2, ages(first.get_records("users")).len());
+        drop((empty, first));
+        versions.collect_garbage();
+        assert_eq!(
+            2,
+            versions.store.read().unwrap().channels["users"]
+                .rows
+                .values()
+                .map(|e| e.len())
+                .sum::<usize>()
+        );
+        versions.commit(&[Change::Update("users".to_string(), 2, record(4))]);
+        assert_eq!(
+            vec![DataType::Int(10), DataType::Int(3)],
+            ages(second.get_records("users"))
+        );
+        assert_eq!(
+            vec![DataType::Int(10), DataType::Int(4)],
+            ages(versions.snapshot().get_records("users"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_versions_as_of() -> Result<(), ()> {
+        let versions = Versions::new().with_retention(1);
+        versions.commit(&[
+            Change::Create("users".to_string(), vec![]),
+            Change::Insert("users".to_string(), 0, record(1)),
+            Change::Insert("users".to_string(), 1, record(2)),
+        ]);
+        versions.commit(&[Change::Update("users".to_string(), 0, record(10))]);
+        versions.commit(&[Change::Delete("users".to_string(), 1)]);
+
+        let first = versions.snapshot_as_of(&AsOf::Version(1)).unwrap();
+        assert_eq!(
+            vec![DataType::Int(1), DataType::Int(2)],
+            ages(first.get_records("users"))
+        );
+        let last = versions.snapshot_as_of(&AsOf::Timestamp(i64::MAX)).unwrap();
+        assert_eq!(3, last.get_version());
+        assert_eq!(
+            None,
+            versions
+                .snapshot_as_of(&AsOf::Version(4))
+                .ok()
+                .map(|e| e.get_version())
+        );
+
+        // every version of record matched by one of its versions
+        let age = DataType::Symbol("age".to_string());
+        let filter = Some(vec![BinaryExpr::new(
+            age,
+            DataType::Int(1),
+            CompareOperator::Eq,
+        )]);
+        let history = last.get_history("users", &filter).unwrap();
+        assert_eq!(2, history.len());
+        assert_eq!(Some(&DataType::Int(10)), history[1].get("age"));
+        assert_eq!(Some(&DataType::Int(2)), history[0].get(VERSION_END_FIELD));
+        assert_eq!(Some(&DataType::Null), history[1].get(VERSION_END_FIELD));
+        // version ended after snapshot is current for snapshot
+        let history = first.get_history("users", &None).unwrap();
+        assert_eq!(2, history.len());
+        assert_eq!(Some(&DataType::Int(1)), history[1].get(VERSION_FIELD));
+        assert_eq!(Some(&DataType::Null), history[1].get(VERSION_END_FIELD));
+
+        // versions older than retained ones are collected when they're not read
+        drop((first, last));
+        versions.collect_garbage();
+        assert_eq!(
+            None,
+            versions
+                .snapshot_as_of(&AsOf::Version(1))
+                .ok()
+                .map(|e| e.get_version())
+        );
+        assert_eq!(
+            None,
+            versions
+                .snapshot_as_of(&AsOf::Timestamp(0))
+                .ok()
+                .map(|e| e.get_version())
+        );
+        let retained = versions.snapshot_as_of(&AsOf::Version(2)).unwrap();
+        assert_eq!(
+            vec![DataType::Int(10), DataType::Int(2)],
+            ages(retained.get_records("users"))
+        );
+        Ok(())
+    }
+}
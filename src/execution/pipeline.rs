@@ -0,0 +1,633 @@
+use crate::execution::defaults::{eval_var, Sequences};
+use crate::execution::filter::Filter;
+use crate::execution::result::{fan_out, ChannelOutcome, Record, Row, StatementResult};
+use crate::text_processing::ast::types::{
+    BinaryExpr, ChannelName, CompareOperator, DataType, DataVar, Join, JoinKind, NullsOrder,
+    OrderBy, Projection, ScalarExpr, SortDirection, UnaryFuncExpr,
+};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// steps of onRead pipeline, applied to records found in channel
+
+// compute projection fields for record
+// None projection keeps record as is
+pub fn project(record: Record, projection: &Option<Vec<Projection>>) -> Option<Record> {
+    let projection = match projection {
+        Some(projection) => projection,
+        None => return Some(record),
+    };
+    let mut result = Record::new();
+    for field in projection {
+        result.insert(field.get_name(), field.get_expr().eval(&record)?);
+    }
+    Some(result)
+}
+
+// record created by onUpsert when filter matches nothing:
+// equalities field == value of filter, then statements (statement wins)
+// computed statements take fields of record created so far
+// example: onUpsert(users)(id == 5)(name: text = 'bob') -> { id: 5, name: 'bob' }
+pub fn upsert_record(
+    exprs: &Option<Vec<BinaryExpr>>,
+    vars: &Option<Vec<DataVar>>,
+    sequences: &mut Sequences,
+) -> Option<Record> {
+    let mut record = Record::new();
+    for expr in exprs.iter().flatten() {
+        if *expr.get_operator() != CompareOperator::Eq {
+            continue;
+        }
+        match (expr.get_lterm(), expr.get_rterm()) {
+            (ScalarExpr::Value(DataType::Symbol(_)), ScalarExpr::Value(DataType::Symbol(_))) => {}
+            (ScalarExpr::Value(DataType::Symbol(name)), ScalarExpr::Value(val))
+            | (ScalarExpr::Value(val), ScalarExpr::Value(DataType::Symbol(name))) => {
+                record.insert(name.to_owned(), val.clone());
+            }
+            _ => {}
+        }
+    }
+    for var in vars.iter().flatten() {
+        let value = eval_var(var, &record, sequences)?;
+        record.insert(var.get_name().to_owned(), value);
+    }
+    Some(record)
+}
+
+// prefix fields of record by channel name: id -> orders.id
+// fields of main channel are kept without prefix too, so both id and orders.id are correct
+pub fn qualify(record: Record, channel: &str, keep_bare: bool) -> Record {
+    let mut result = Record::new();
+    for (name, val) in record {
+        result.insert(format!("{}.{}", channel, name), val.clone());
+        if keep_bare {
+            result.insert(name, val);
+        }
+    }
+    result
+}
+
+// join records with qualified records of joined channel by equality of keys
+// unmatched record is kept by left join, its joined fields are absent (null)
+pub fn join(records: Vec<Record>, joined: Vec<Record>, join: &Join) -> Vec<Record> {
+    let (key, joined_key) = match join.get_keys() {
+        Some(keys) => keys,
+        None => return vec![],
+    };
+    let mut index: HashMap<String, Vec<&Record>> = HashMap::new();
+    for record in joined.iter() {
+        match record.get(joined_key) {
+            None | Some(DataType::Null) => {}
+            Some(val) => index.entry(format!("{:?}", val)).or_default().push(record),
+        }
+    }
+
+    let mut result: Vec<Record> = vec![];
+    for record in records {
+        let matched = match record.get(key) {
+            None | Some(DataType::Null) => None,
+            Some(val) => index.get(&format!("{:?}", val)),
+        };
+        match matched {
+            Some(matched) => {
+                for e in matched {
+                    let mut combined = record.clone();
+                    combined.extend(e.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    result.push(combined);
+                }
+            }
+            None if join.get_kind() == JoinKind::Left => result.push(record),
+            None => {}
+        }
+    }
+    result
+}
+
+// group rows by groupBy values and compute one row per group
+// without groupBy all rows are one group, so count(*) of empty channel is 0
+// row of group is tagged with channels of its records: a,b
+pub fn aggregate(
+    rows: Vec<Row>,
+    group_by: &Option<Vec<ScalarExpr>>,
+    projection: &Option<Vec<Projection>>,
+) -> Option<Vec<Row>> {
+    let empty: Vec<ScalarExpr> = vec![];
+    let group_by = group_by.as_ref().unwrap_or(&empty);
+
+    let mut groups: Vec<(Vec<String>, Vec<Record>)> = vec![];
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        let keys: Option<Vec<DataType>> =
+            group_by.iter().map(|e| e.eval(row.get_record())).collect();
+        let key = format!("{:?}", keys?);
+        let position = *positions.entry(key).or_insert_with(|| {
+            groups.push((vec![], vec![]));
+            groups.len() - 1
+        });
+        let (channels, records) = &mut groups[position];
+        if !channels.contains(row.get_channel()) {
+            channels.push(row.get_channel().to_owned());
+        }
+        records.push(row.get_record().clone());
+    }
+    if groups.is_empty() && group_by.is_empty() {
+        groups.push((vec![], vec![]));
+    }
+
+    let mut result: Vec<Row> = vec![];
+    for (channels, records) in groups {
+        let mut record = Record::new();
+        match projection {
+            Some(projection) => {
+                for field in projection {
+                    record.insert(field.get_name(), field.get_expr().eval_group(&records)?);
+                }
+            }
+            None => {
+                for expr in group_by {
+                    record.insert(expr.to_string(), expr.eval_group(&records)?);
+                }
+            }
+        }
+        result.push(Row::new(channels.join(","), record));
+    }
+    Some(result)
+}
+
+// keep rows where every expression is true
+pub fn filter(rows: Vec<Row>, exprs: &Option<Vec<BinaryExpr>>) -> Vec<Row> {
+    let filter = Filter::compile(exprs);
+    rows.into_iter()
+        .filter(|row| filter.is_matched(row.get_record()))
+        .collect()
+}
+
+// total order of values: int and real are compared as numbers,
+// values of different types are ordered by type
+pub fn compare_values(lterm: &DataType, rterm: &DataType) -> Ordering {
+    use crate::text_processing::ast::types::DataType::*;
+
+    match (lterm, rterm) {
+        (Int(l), Real(r)) => (*l as f64).partial_cmp(r).unwrap_or(Ordering::Equal),
+        (Real(l), Int(r)) => l.partial_cmp(&(*r as f64)).unwrap_or(Ordering::Equal),
+        _ => lterm.partial_cmp(rterm).unwrap_or(Ordering::Equal),
+    }
+}
+
+// sort rows by orderBy items, sort is stable
+// orderBy item can use alias from projection
+pub fn order(rows: &mut Vec<Row>, order_by: &[OrderBy], projection: &Option<Vec<Projection>>) {
+    let resolve = |expr: &ScalarExpr| -> ScalarExpr {
+        if let (ScalarExpr::Value(DataType::Symbol(name)), Some(projection)) = (expr, projection) {
+            let aliased = projection
+                .iter()
+                .find(|e| e.get_alias().as_ref() == Some(name));
+            if let Some(aliased) = aliased {
+                return aliased.get_expr().clone();
+            }
+        }
+        expr.clone()
+    };
+    let exprs: Vec<ScalarExpr> = order_by.iter().map(|e| resolve(e.get_expr())).collect();
+
+    let mut keyed: Vec<(Vec<DataType>, Row)> = rows
+        .drain(..)
+        .map(|row| {
+            let keys = exprs
+                .iter()
+                .map(|e| e.eval(row.get_record()).unwrap_or(DataType::Null))
+                .collect();
+            (keys, row)
+        })
+        .collect();
+    keyed.sort_by(|(lkeys, _), (rkeys, _)| {
+        for (item, (l, r)) in order_by.iter().zip(lkeys.iter().zip(rkeys.iter())) {
+            let ordering = match (l, r) {
+                (DataType::Null, DataType::Null) => Ordering::Equal,
+                (DataType::Null, _) if item.get_nulls() == NullsOrder::First => Ordering::Less,
+                (DataType::Null, _) => Ordering::Greater,
+                (_, DataType::Null) if item.get_nulls() == NullsOrder::First => Ordering::Greater,
+                (_, DataType::Null) => Ordering::Less,
+                _ => match item.get_direction() {
+                    SortDirection::Asc => compare_values(l, r),
+                    SortDirection::Desc => compare_values(r, l),
+                },
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    });
+    rows.extend(keyed.into_iter().map(|(_, row)| row));
+}
+
+// steps of onRead over union of filtered rows of all channels:
+// groups and having, order, projection, then offset and limit
+pub fn read(rows: Vec<Row>, expr: &UnaryFuncExpr) -> Option<Vec<Row>> {
+    if expr.is_aggregate() {
+        let mut rows = aggregate(rows, expr.get_group_by(), expr.get_projection())?;
+        rows = filter(rows, expr.get_having());
+        // aggregate of orderBy is field of group named by text of expression
+        let order_by: Vec<OrderBy> = expr
+            .get_order_by()
+            .iter()
+            .flatten()
+            .map(|e| {
+                let expr = match e.get_expr().has_aggregate() {
+                    true => ScalarExpr::Value(DataType::Symbol(e.get_expr().to_string())),
+                    false => e.get_expr().clone(),
+                };
+                OrderBy::new(expr, e.get_direction(), Some(e.get_nulls()))
+            })
+            .collect();
+        order(&mut rows, &order_by, &None);
+        return Some(paginate(rows, expr.get_limit(), expr.get_offset()));
+    }
+    let mut rows = rows;
+    if let Some(order_by) = expr.get_order_by() {
+        order(&mut rows, order_by, expr.get_projection());
+    }
+    let rows = paginate(rows, expr.get_limit(), expr.get_offset());
+    rows.into_iter()
+        .map(|row| {
+            let record = project(row.get_record().clone(), expr.get_projection())?;
+            Some(Row::new(row.get_channel().to_owned(), record))
+        })
+        .collect()
+}
+
+// onRead over records fetched from storage: records of every channel, then read steps
+// fetch gets channel and its filter, records of joined channels are fetched without filter
+// filter only narrows fetched records, it's checked again after joins
+pub fn read_statement<F>(expr: &UnaryFuncExpr, node_path: &str, fetch: F) -> StatementResult
+where
+    F: Fn(&ChannelName, &Option<Vec<BinaryExpr>>) -> Option<Vec<Record>>,
+{
+    let result = fan_out(expr, node_path, |channel| {
+        read_channel(channel, expr, node_path, &fetch)
+    });
+    let rows = read(result.get_rows().to_owned(), expr).unwrap_or_default();
+    result.with_rows(rows)
+}
+
+// records of channel joined with records of join clauses, then filtered
+fn read_channel<F>(
+    channel: &ChannelName,
+    expr: &UnaryFuncExpr,
+    node_path: &str,
+    fetch: &F,
+) -> ChannelOutcome
+where
+    F: Fn(&ChannelName, &Option<Vec<BinaryExpr>>) -> Option<Vec<Record>>,
+{
+    let records = match fetch(channel, expr.get_binary_exprs()) {
+        Some(records) => records,
+        None => return ChannelOutcome::Failed("channel not found".to_string()),
+    };
+    let mut records = match expr.get_joins() {
+        Some(_) => records
+            .into_iter()
+            .map(|e| qualify(e, channel.get_name(), true))
+            .collect(),
+        None => records,
+    };
+    for e in expr.get_joins().iter().flatten() {
+        let joined_channel = e.get_channel().resolve(node_path);
+        let joined: Vec<Record> = match fetch(&joined_channel, &None) {
+            Some(joined) => joined
+                .into_iter()
+                .map(|record| qualify(record, joined_channel.get_name(), false))
+                .collect(),
+            None => {
+                let mes = format!("joined channel not found: {}", joined_channel);
+                return ChannelOutcome::Failed(mes);
+            }
+        };
+        records = join(records, joined, e);
+    }
+    let filter = Filter::compile(expr.get_binary_exprs());
+    records.retain(|e| filter.is_matched(e));
+    ChannelOutcome::Rows(records)
+}
+
+// skip offset rows, then keep at most limit rows
+pub fn paginate(rows: Vec<Row>, limit: Option<usize>, offset: Option<usize>) -> Vec<Row> {
+    rows.into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::pipeline::{
+        aggregate, filter, join, order, paginate, project, qualify, upsert_record, Sequences,
+    };
+    use crate::execution::result::{Record, Row};
+    use crate::text_processing::ast::types::{
+        AggregateFunc, ArithOperator, BinaryExpr, ChannelName, CompareOperator, DataType, DataVar,
+        Join, JoinKind, NullsOrder, OrderBy, Projection, ScalarExpr, SortDirection,
+    };
+
+    fn rows(values: Vec<DataType>) -> Vec<Row> {
+        values
+            .into_iter()
+            .map(|e| {
+                let mut record = Record::new();
+                record.insert("a".to_string(), e);
+                Row::new("ch".to_string(), record)
+            })
+            .collect()
+    }
+
+    fn values(rows: &[Row]) -> Vec<DataType> {
+        rows.iter()
+            .map(|e| e.get_record().get("a").unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_project() -> Result<(), ()> {
+        let mut record = Record::new();
+        record.insert("name".to_string(), DataType::Text("bob".to_string()));
+        record.insert("price".to_string(), DataType::Int(3));
+        record.insert("qty".to_string(), DataType::Real(1.5));
+
+        let projection = vec![
+            Projection::new(
+                ScalarExpr::Value(DataType::Symbol("name".to_string())),
+                Some("n".to_string()),
+            ),
+            Projection::new(
+                ScalarExpr::Arithmetic(
+                    Box::new(ScalarExpr::Value(DataType::Symbol("price".to_string()))),
+                    ArithOperator::Mul,
+                    Box::new(ScalarExpr::Value(DataType::Symbol("qty".to_string()))),
+                ),
+                None,
+            ),
+        ];
+        let result = project(record.clone(), &Some(projection)).unwrap();
+        assert_eq!(2, result.len());
+        assert_eq!(Some(&DataType::Text("bob".to_string())), result.get("n"));
+        assert_eq!(Some(&DataType::Real(4.5)), result.get("price*qty"));
+
+        assert_eq!(Some(record.clone()), project(record, &None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_order() -> Result<(), ()> {
+        let field = ScalarExpr::Value(DataType::Symbol("a".to_string()));
+        let mut test_rows = rows(vec![
+            DataType::Int(2),
+            DataType::Null,
+            DataType::Real(1.5),
+            DataType::Int(3),
+        ]);
+        order(
+            &mut test_rows,
+            &[OrderBy::new(field.clone(), SortDirection::Asc, None)],
+            &None,
+        );
+        assert_eq!(
+            vec![
+                DataType::Real(1.5),
+                DataType::Int(2),
+                DataType::Int(3),
+                DataType::Null
+            ],
+            values(&test_rows)
+        );
+
+        order(
+            &mut test_rows,
+            &[OrderBy::new(
+                field.clone(),
+                SortDirection::Desc,
+                Some(NullsOrder::Last),
+            )],
+            &None,
+        );
+        assert_eq!(
+            vec![
+                DataType::Int(3),
+                DataType::Int(2),
+                DataType::Real(1.5),
+                DataType::Null
+            ],
+            values(&test_rows)
+        );
+
+        // order by alias of projection
+        let alias = ScalarExpr::Value(DataType::Symbol("neg".to_string()));
+        let projection = vec![Projection::new(
+            ScalarExpr::Arithmetic(
+                Box::new(ScalarExpr::Value(DataType::Int(0))),
+                ArithOperator::Sub,
+                Box::new(field),
+            ),
+            Some("neg".to_string()),
+        )];
+        order(
+            &mut test_rows,
+            &[OrderBy::new(
+                alias,
+                SortDirection::Asc,
+                Some(NullsOrder::First),
+            )],
+            &Some(projection),
+        );
+        assert_eq!(
+            vec![
+                DataType::Null,
+                DataType::Int(3),
+                DataType::Int(2),
+                DataType::Real(1.5)
+            ],
+            values(&test_rows)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_paginate() -> Result<(), ()> {
+        let test_rows = rows((0..10).map(DataType::Int).collect());
+        let result = paginate(test_rows.clone(), Some(3), Some(8));
+        assert_eq!(vec![DataType::Int(8), DataType::Int(9)], values(&result));
+        let result = paginate(test_rows.clone(), Some(2), None);
+        assert_eq!(vec![DataType::Int(0), DataType::Int(1)], values(&result));
+        assert_eq!(10, paginate(test_rows, None, None).len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate() -> Result<(), ()> {
+        let symbol = |name: &str| ScalarExpr::Value(DataType::Symbol(name.to_string()));
+        let mut test_rows: Vec<Row> = vec![];
+        for (channel, country, age) in [
+            ("a", "ru", DataType::Int(20)),
+            ("b", "ru", DataType::Int(31)),
+            ("a", "de", DataType::Null),
+            ("a", "ru", DataType::Int(20)),
+        ] {
+            let mut record = Record::new();
+            record.insert("country".to_string(), DataType::Text(country.to_string()));
+            record.insert("age".to_string(), age);
+            test_rows.push(Row::new(channel.to_string(), record));
+        }
+        let projection = Some(vec![
+            Projection::new(symbol("country"), None),
+            Projection::new(ScalarExpr::Aggregate(AggregateFunc::Count, None), None),
+            Projection::new(
+                ScalarExpr::Aggregate(AggregateFunc::Avg, Some(Box::new(symbol("age")))),
+                Some("avg_age".to_string()),
+            ),
+            Projection::new(
+                ScalarExpr::Aggregate(AggregateFunc::CountDistinct, Some(Box::new(symbol("age")))),
+                None,
+            ),
+        ]);
+        let result = aggregate(
+            test_rows.clone(),
+            &Some(vec![symbol("country")]),
+            &projection,
+        )
+        .unwrap();
+        assert_eq!(2, result.len());
+        assert_eq!("a,b", result[0].get_channel());
+        let record = result[0].get_record();
+        assert_eq!(Some(&DataType::Int(3)), record.get("count(*)"));
+        assert_eq!(
+            Some(&DataType::Real(23.666666666666668)),
+            record.get("avg_age")
+        );
+        assert_eq!(Some(&DataType::Int(2)), record.get("count(distinct age)"));
+        let record = result[1].get_record();
+        assert_eq!(Some(&DataType::Null), record.get("avg_age"));
+        assert_eq!(Some(&DataType::Int(0)), record.get("count(distinct age)"));
+
+        let having = Some(vec![BinaryExpr::new(
+            DataType::Symbol("count(*)".to_string()),
+            DataType::Int(1),
+            CompareOperator::Gt,
+        )]);
+        assert_eq!(1, filter(result, &having).len());
+
+        // without groupBy empty input is one group
+        let projection = Some(vec![
+            Projection::new(ScalarExpr::Aggregate(AggregateFunc::Count, None), None),
+            Projection::new(
+                ScalarExpr::Aggregate(AggregateFunc::Sum, Some(Box::new(symbol("age")))),
+                None,
+            ),
+        ]);
+        let result = aggregate(vec![], &None, &projection).unwrap();
+        assert_eq!(1, result.len());
+        assert_eq!(
+            Some(&DataType::Int(0)),
+            result[0].get_record().get("count(*)")
+        );
+        assert_eq!(
+            Some(&DataType::Null),
+            result[0].get_record().get("sum(age)")
+        );
+
+        // sum of text is type error
+        let projection = Some(vec![Projection::new(
+            ScalarExpr::Aggregate(AggregateFunc::Sum, Some(Box::new(symbol("country")))),
+            None,
+        )]);
+        assert_eq!(None, aggregate(test_rows, &None, &projection));
+        Ok(())
+    }
+
+    #[test]
+    fn test_join() -> Result<(), ()> {
+        let record = |fields: Vec<(&str, DataType)>| -> Record {
+            fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect()
+        };
+        let orders: Vec<Record> = vec![
+            record(vec![
+                ("id", DataType::Int(1)),
+                ("user_id", DataType::Int(10)),
+            ]),
+            record(vec![
+                ("id", DataType::Int(2)),
+                ("user_id", DataType::Int(20)),
+            ]),
+            record(vec![
+                ("id", DataType::Int(3)),
+                ("user_id", DataType::Int(10)),
+            ]),
+        ]
+        .into_iter()
+        .map(|e| qualify(e, "orders", true))
+        .collect();
+        let users: Vec<Record> = vec![record(vec![
+            ("id", DataType::Int(10)),
+            ("name", DataType::Text("bob".to_string())),
+        ])]
+        .into_iter()
+        .map(|e| qualify(e, "users", false))
+        .collect();
+        let on = BinaryExpr::new(
+            DataType::Symbol("users.id".to_string()),
+            DataType::Symbol("orders.user_id".to_string()),
+            CompareOperator::Eq,
+        );
+        let channel = ChannelName::from_string("users").unwrap();
+
+        let inner = Join::new(JoinKind::Inner, channel.clone(), on.clone());
+        let result = join(orders.clone(), users.clone(), &inner);
+        assert_eq!(2, result.len());
+        assert_eq!(Some(&DataType::Int(3)), result[1].get("id"));
+        assert_eq!(Some(&DataType::Int(3)), result[1].get("orders.id"));
+        assert_eq!(Some(&DataType::Int(10)), result[1].get("users.id"));
+        assert_eq!(
+            Some(&DataType::Text("bob".to_string())),
+            result[1].get("users.name")
+        );
+
+        let left = Join::new(JoinKind::Left, channel, on);
+        let result = join(orders, users, &left);
+        assert_eq!(3, result.len());
+        assert_eq!(None, result[1].get("users.name"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_upsert_record() -> Result<(), ()> {
+        let exprs = Some(vec![
+            BinaryExpr::new(
+                DataType::Symbol("id".to_string()),
+                DataType::Int(5),
+                CompareOperator::Eq,
+            ),
+            BinaryExpr::new(
+                DataType::Symbol("age".to_string()),
+                DataType::Int(18),
+                CompareOperator::Gt,
+            ),
+            BinaryExpr::new(
+                DataType::Text("x".to_string()),
+                DataType::Symbol("name".to_string()),
+                CompareOperator::Eq,
+            ),
+        ]);
+        let vars = Some(vec![DataVar::new(
+            "name".to_string(),
+            DataType::Text("bob".to_string()),
+        )]);
+        let record = upsert_record(&exprs, &vars, &mut Sequences::new()).unwrap();
+        assert_eq!(2, record.len());
+        assert_eq!(Some(&DataType::Int(5)), record.get("id"));
+        assert_eq!(Some(&DataType::Text("bob".to_string())), record.get("name"));
+        Ok(())
+    }
+}
@@ -0,0 +1,488 @@
+use crate::execution::executor::Executor;
+use crate::execution::result::{fan_out, ChannelOutcome, StatementResult};
+use crate::text_processing::ast::types::{
+    BinaryExpr, ChannelName, FuncType, JoinKind, Projection, SortDirection, UnaryFuncExpr,
+};
+
+// statistics of storage used for estimation of plan
+// remote storage is explained without statistics, its access is chosen by server
+pub trait Statistics {
+    // count of records of channel, None if channel not found
+    fn get_row_count(&self, channel: &ChannelName) -> Option<usize>;
+    // filter answered by index of channel and count of records found by it
+    fn get_index_lookup(
+        &self,
+        channel: &ChannelName,
+        exprs: &Option<Vec<BinaryExpr>>,
+    ) -> Option<(BinaryExpr, usize)>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// step of plan, children are executed before step
+// rows is estimated count of records after step, None if it's unknown
+// example: filter age > 18 (rows: 2) <- scan users (rows: 3)
+pub struct PlanNode {
+    operation: String,
+    detail: String,
+    rows: Option<usize>,
+    children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    pub fn new<T: ToString>(operation: &str, detail: T, rows: Option<usize>) -> PlanNode {
+        PlanNode {
+            operation: operation.to_string(),
+            detail: detail.to_string(),
+            rows,
+            children: vec![],
+        }
+    }
+    pub fn with_children(mut self, children: Vec<PlanNode>) -> PlanNode {
+        self.children = children;
+        self
+    }
+    pub fn get_operation(&self) -> &String {
+        &self.operation
+    }
+    pub fn get_detail(&self) -> &String {
+        &self.detail
+    }
+    pub fn get_rows(&self) -> Option<usize> {
+        self.rows
+    }
+    pub fn get_children(&self) -> &Vec<PlanNode> {
+        &self.children
+    }
+
+    // next step which takes records of this step
+    fn then<T: ToString>(self, operation: &str, detail: T, rows: Option<usize>) -> PlanNode {
+        PlanNode::new(operation, detail, rows).with_children(vec![self])
+    }
+
+    pub fn to_json(&self) -> String {
+        let rows = match self.rows {
+            Some(rows) => rows.to_string(),
+            None => "null".to_string(),
+        };
+        let children: Vec<String> = self.children.iter().map(|e| e.to_json()).collect();
+        format!(
+            "{{\"operation\": {}, \"detail\": {}, \"rows\": {}, \"children\": [{}]}}",
+            json_string(&self.operation),
+            json_string(&self.detail),
+            rows,
+            children.join(", ")
+        )
+    }
+
+    fn fmt_tree(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let rows = match self.rows {
+            Some(rows) => rows.to_string(),
+            None => "?".to_string(),
+        };
+        let prefix = match depth {
+            0 => "".to_string(),
+            _ => format!("{}-> ", "  ".repeat(depth - 1)),
+        };
+        match self.detail.is_empty() {
+            true => write!(f, "{}{} (rows: {})", prefix, self.operation, rows)?,
+            false => write!(
+                f,
+                "{}{} {} (rows: {})",
+                prefix, self.operation, self.detail, rows
+            )?,
+        }
+        for child in self.children.iter() {
+            writeln!(f)?;
+            child.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+// text tree, first step is the deepest one
+// example:
+// filter age > 18 (rows: 3)
+// -> scan users (rows: 3)
+impl std::fmt::Display for PlanNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
+
+// plan of statement, channels are resolved against node path like on execution
+// estimates are upper bounds: filter keeps every record of its input,
+// join finds one record for every record of channel
+pub fn build_plan(
+    expr: &UnaryFuncExpr,
+    node_path: &str,
+    statistics: Option<&dyn Statistics>,
+) -> PlanNode {
+    let channels: Vec<ChannelName> = expr
+        .get_channel_names()
+        .iter()
+        .map(|e| e.resolve(node_path))
+        .collect();
+    let exprs = expr.get_binary_exprs();
+    // onRead as of version and history read retained versions of records
+    let versions = match (expr.get_as_of(), expr.is_history()) {
+        (Some(as_of), true) => Some(format!("history as of {}", as_of)),
+        (Some(as_of), false) => Some(format!("as of {}", as_of)),
+        (None, true) => Some("history".to_string()),
+        (None, false) => None,
+    };
+    let mut nodes: Vec<PlanNode> = vec![];
+    for channel in channels.iter() {
+        let node = match expr.get_func_type() {
+            FuncType::OnRead => {
+                // filter is checked after joins, it can take fields of joined channels
+                let (mut node, rest) = read_access(channel, exprs, statistics, &versions);
+                for join in expr.get_joins().iter().flatten() {
+                    let joined = join.get_channel().resolve(node_path);
+                    let operation = match join.get_kind() {
+                        JoinKind::Inner => "join",
+                        JoinKind::Left => "left join",
+                    };
+                    let rows = node.rows;
+                    let (joined, _) = read_access(&joined, &None, statistics, &versions);
+                    node = PlanNode::new(operation, join.get_on(), rows)
+                        .with_children(vec![node, joined]);
+                }
+                filter(node, rest)
+            }
+            FuncType::OnCreate => PlanNode::new("insert", channel, Some(1)),
+            FuncType::OnUpdate | FuncType::OnDelete | FuncType::OnUpsert => {
+                let (node, rest) = access(channel, exprs, statistics);
+                let node = filter(node, rest);
+                let rows = node.rows;
+                match expr.get_func_type() {
+                    FuncType::OnUpdate => node.then("update", channel, rows),
+                    FuncType::OnDelete => node.then("delete", channel, rows),
+                    // record is inserted when filter matches nothing
+                    _ => node.then(
+                        "upsert",
+                        format!("{} or insert", channel),
+                        rows.map(|e| e.max(1)),
+                    ),
+                }
+            }
+            FuncType::OnWatch => {
+                let filter = join_exprs(exprs.iter().flatten());
+                let detail = match filter.is_empty() {
+                    true => channel.to_string(),
+                    false => format!("{} where {}", channel, filter),
+                };
+                PlanNode::new("watch", detail, None)
+            }
+            FuncType::OnIndex => {
+                let field = join_projection(expr.get_projection());
+                let (node, _) = access(channel, &None, statistics);
+                let rows = node.rows;
+                node.then("build index", format!("{} ({})", channel, field), rows)
+            }
+        };
+        nodes.push(node);
+    }
+
+    let mut node = match nodes.len() {
+        1 => nodes.remove(0),
+        _ => {
+            let rows = nodes.iter().map(|e| e.rows).sum();
+            let operation = match expr.get_func_type() {
+                FuncType::OnRead => "union",
+                _ => "each channel",
+            };
+            PlanNode::new(operation, "", rows).with_children(nodes)
+        }
+    };
+    if *expr.get_func_type() != FuncType::OnRead {
+        if expr.get_returning().is_some() {
+            let rows = node.rows;
+            node = node.then("returning", join_projection(expr.get_returning()), rows);
+        }
+        return node;
+    }
+
+    // steps of onRead pipeline over union of channels
+    if expr.is_aggregate() {
+        let group_by: Vec<String> = expr
+            .get_group_by()
+            .iter()
+            .flatten()
+            .map(|e| e.to_string())
+            .collect();
+        let rows = match group_by.is_empty() {
+            true => Some(1),
+            false => node.rows,
+        };
+        node = node.then("aggregate", group_by.join(", "), rows);
+        if let Some(having) = expr.get_having() {
+            let rows = node.rows;
+            node = node.then("having", join_exprs(having.iter()), rows);
+        }
+    }
+    if let Some(order_by) = expr.get_order_by() {
+        let order_by: Vec<String> = order_by
+            .iter()
+            .map(|e| match e.get_direction() {
+                SortDirection::Asc => format!("{} asc", e.get_expr()),
+                SortDirection::Desc => format!("{} desc", e.get_expr()),
+            })
+            .collect();
+        let rows = node.rows;
+        node = node.then("order", order_by.join(", "), rows);
+    }
+    if expr.get_limit().is_some() || expr.get_offset().is_some() {
+        let offset = expr.get_offset().unwrap_or(0);
+        let rows = node.rows.map(|e| {
+            let rows = e.saturating_sub(offset);
+            expr.get_limit().map_or(rows, |limit| rows.min(limit))
+        });
+        let detail = match expr.get_limit() {
+            Some(limit) => format!("limit {} offset {}", limit, offset),
+            None => format!("offset {}", offset),
+        };
+        node = node.then("paginate", detail, rows);
+    }
+    if expr.get_projection().is_some() {
+        let rows = node.rows;
+        node = node.then("project", join_projection(expr.get_projection()), rows);
+    }
+    node
+}
+
+// executor of remote uri when storage is not connected, so statements are only explained
+pub struct PlanExecutor {
+    node_path: String,
+}
+
+impl PlanExecutor {
+    pub fn new<T: ToString>(node_path: T) -> PlanExecutor {
+        PlanExecutor {
+            node_path: node_path.to_string(),
+        }
+    }
+}
+
+impl Executor for PlanExecutor {
+    fn execute(&mut self, expr: &UnaryFuncExpr) -> StatementResult {
+        fan_out(expr, &self.node_path, |_| {
+            ChannelOutcome::Failed("storage is not connected, only explain is answered".to_string())
+        })
+    }
+    fn explain(&self, expr: &UnaryFuncExpr) -> PlanNode {
+        build_plan(expr, &self.node_path, None)
+    }
+    fn begin(&mut self) {}
+    fn commit(&mut self) -> Option<()> {
+        Some(())
+    }
+    fn rollback(&mut self) {}
+}
+
+// how records of channel are found: by index, by scan or by remote storage
+// and filters which are not answered by index
+fn access<'a>(
+    channel: &ChannelName,
+    exprs: &'a Option<Vec<BinaryExpr>>,
+    statistics: Option<&dyn Statistics>,
+) -> (PlanNode, Vec<&'a BinaryExpr>) {
+    let rest: Vec<&BinaryExpr> = exprs.iter().flatten().collect();
+    let statistics = match statistics {
+        Some(statistics) => statistics,
+        None => return (PlanNode::new("remote", channel, None), rest),
+    };
+    let count = match statistics.get_row_count(channel) {
+        Some(count) => count,
+        None => {
+            let detail = format!("{} (channel not found)", channel);
+            return (PlanNode::new("scan", detail, Some(0)), rest);
+        }
+    };
+    match statistics.get_index_lookup(channel, exprs) {
+        Some((used, rows)) => {
+            let detail = format!("{} by {}", channel, used);
+            let rest = rest.into_iter().filter(|e| **e != used).collect();
+            (PlanNode::new("index lookup", detail, Some(rows)), rest)
+        }
+        None => (PlanNode::new("scan", channel, Some(count)), rest),
+    }
+}
+
+// retained versions are scanned without indexes, count of versions is not known
+// example: versions scan users as of version 3
+fn read_access<'a>(
+    channel: &ChannelName,
+    exprs: &'a Option<Vec<BinaryExpr>>,
+    statistics: Option<&dyn Statistics>,
+    versions: &Option<String>,
+) -> (PlanNode, Vec<&'a BinaryExpr>) {
+    match (versions, statistics) {
+        (Some(versions), Some(_)) => {
+            let rest: Vec<&BinaryExpr> = exprs.iter().flatten().collect();
+            let detail = format!("{} {}", channel, versions);
+            (PlanNode::new("versions scan", detail, None), rest)
+        }
+        _ => access(channel, exprs, statistics),
+    }
+}
+
+// filters checked over every record of step
+fn filter(node: PlanNode, exprs: Vec<&BinaryExpr>) -> PlanNode {
+    match exprs.is_empty() {
+        true => node,
+        false => {
+            let rows = node.rows;
+            node.then("filter", join_exprs(exprs.into_iter()), rows)
+        }
+    }
+}
+
+fn join_exprs<'a, I: Iterator<Item = &'a BinaryExpr>>(exprs: I) -> String {
+    exprs
+        .map(|e| e.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn join_projection(projection: &Option<Vec<Projection>>) -> String {
+    projection
+        .iter()
+        .flatten()
+        .map(|e| e.get_name())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn json_string(val: &str) -> String {
+    let mut escaped = String::with_capacity(val.len() + 2);
+    escaped.push('"');
+    for e in val.chars() {
+        match e {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            e if (e as u32) < 0x20 => escaped.push_str(format!("\\u{:04x}", e as u32).as_str()),
+            e => escaped.push(e),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::plan::{build_plan, PlanNode, Statistics};
+    use crate::text_processing::ast::scope::Scope;
+    use crate::text_processing::ast::types::{BinaryExpr, ChannelName, UnaryFuncExpr};
+    use crate::text_processing::parser::states::{Parser, ParserDefault};
+
+    // users has 10 records and index on age, orders has 100 records
+    struct FixedStatistics;
+
+    impl Statistics for FixedStatistics {
+        fn get_row_count(&self, channel: &ChannelName) -> Option<usize> {
+            match channel.get_name().as_str() {
+                "users" => Some(10),
+                "orders" => Some(100),
+                _ => None,
+            }
+        }
+        fn get_index_lookup(
+            &self,
+            channel: &ChannelName,
+            exprs: &Option<Vec<BinaryExpr>>,
+        ) -> Option<(BinaryExpr, usize)> {
+            if channel.get_name() != "users" {
+                return None;
+            }
+            exprs
+                .iter()
+                .flatten()
+                .find(|e| e.to_string().starts_with("age"))
+                .map(|e| (e.clone(), 3))
+        }
+    }
+
+    fn parse(script: &str) -> UnaryFuncExpr {
+        ParserDefault::from_script(script, &mut Scope::new()).unwrap()[0].clone()
+    }
+
+    #[test]
+    fn test_build_plan_read() -> Result<(), ()> {
+        let expr =
+            parse("onRead(users, orders)(age > 18, name == 'bob')(name) orderBy(name) limit(2)");
+        let plan = build_plan(&expr, "", Some(&FixedStatistics));
+        assert_eq!(
+            "project name (rows: 2)\n\
+             -> paginate limit 2 offset 0 (rows: 2)\n  \
+               -> order name asc (rows: 103)\n    \
+                 -> union (rows: 103)\n      \
+                   -> filter name == 'bob' (rows: 3)\n        \
+                     -> index lookup users by age > 18 (rows: 3)\n      \
+                   -> filter age > 18, name == 'bob' (rows: 100)\n        \
+                     -> scan orders (rows: 100)",
+            plan.to_string()
+        );
+
+        // indexes are not used by reads of retained versions
+        let plan = build_plan(
+            &parse(
+                "onRead(users)(age > 18) join(orders)(users.id == orders.user_id) as of version 3",
+            ),
+            "",
+            Some(&FixedStatistics),
+        );
+        assert_eq!(
+            "filter age > 18 (rows: ?)\n\
+             -> join users.id == orders.user_id (rows: ?)\n  \
+               -> versions scan users as of version 3 (rows: ?)\n  \
+               -> versions scan orders as of version 3 (rows: ?)",
+            plan.to_string()
+        );
+        let plan = build_plan(
+            &parse("onRead(users)(age > 18) history"),
+            "",
+            Some(&FixedStatistics),
+        );
+        assert_eq!("versions scan", plan.get_children()[0].get_operation());
+        assert_eq!("users history", plan.get_children()[0].get_detail());
+
+        // remote plan has no estimates
+        let plan = build_plan(&expr, "mynode", None);
+        let union = &plan.get_children()[0].get_children()[0].get_children()[0];
+        let filter = &union.get_children()[0];
+        assert_eq!(None, plan.get_rows());
+        assert_eq!("remote", filter.get_children()[0].get_operation());
+        assert_eq!("mynode.users", filter.get_children()[0].get_detail());
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_plan_mutation() -> Result<(), ()> {
+        let expr = parse("onDelete(users)(age == 30) returning(name)");
+        let plan = build_plan(&expr, "", Some(&FixedStatistics));
+        assert_eq!(
+            "returning name (rows: 3)\n\
+             -> delete users (rows: 3)\n  \
+               -> index lookup users by age == 30 (rows: 3)",
+            plan.to_string()
+        );
+        let plan = build_plan(&parse("onCreate(users, orders)(id: int = 1)"), "", None);
+        assert_eq!(Some(2), plan.get_rows());
+        assert_eq!(2, plan.get_children().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_to_json() -> Result<(), ()> {
+        let plan = PlanNode::new("filter", "name == 'a\"b'", Some(1))
+            .with_children(vec![PlanNode::new("remote", "users", None)]);
+        assert_eq!(
+            "{\"operation\": \"filter\", \"detail\": \"name == 'a\\\"b'\", \"rows\": 1, \"children\": \
+             [{\"operation\": \"remote\", \"detail\": \"users\", \"rows\": null, \"children\": []}]}",
+            plan.to_json()
+        );
+        Ok(())
+    }
+}
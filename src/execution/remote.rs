@@ -0,0 +1,622 @@
+use crate::environment::logger::Logger;
+use crate::execution::defaults::{now_millis, Sequences};
+use crate::execution::executor::Executor;
+use crate::execution::expiry::{Sweeper, SWEEP_INTERVAL};
+use crate::execution::memory::StorageExecutor;
+use crate::execution::result::Record;
+use crate::execution::storage::StorageBackend;
+use crate::execution::wal::{
+    decode_record, decode_scalar_expr, decode_value, encode_record, encode_scalar_expr,
+    encode_value, escape, unescape, Change,
+};
+use crate::text_processing::ast::types::{BinaryExpr, CompareOperator, DataType, DataVar, Expiry};
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// protocol of remote storage: one request line, then lines of response
+// which end by ok, none when value is not found, or error with message
+// changes of apply and values are encoded like lines of log
+// example:
+//   > scan users 1 == v s:id v i:1
+//   < r 0 2 id i:1 name t:'bob'
+//   < ok
+//   > apply 1
+//   > delete users 0
+//   < ok
+const OK: &str = "ok";
+const NONE: &str = "none";
+const ERROR: &str = "error";
+// client which sends nothing for this time is disconnected, so next client is served
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+// client waits for response at most this time, server can serve other client before it
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(120);
+
+// storage kept by server, every call is one request, selected by --uri host:port
+// server is started by serve subcommand with file or memory storage
+pub struct RemoteStorage {
+    address: String,
+    connection: RefCell<Connection>,
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl RemoteStorage {
+    pub fn connect<T: ToSocketAddrs + ToString>(address: T) -> Option<RemoteStorage> {
+        let stream = match TcpStream::connect(&address) {
+            Ok(stream) => stream,
+            Err(e) => {
+                let mes = format!("storage {} is not connected: {}", address.to_string(), e);
+                Logger::error(mes.as_str());
+                return None;
+            }
+        };
+        let writer = stream.try_clone().ok()?;
+        if let Err(e) = stream.set_read_timeout(Some(RESPONSE_TIMEOUT)) {
+            Logger::error(
+                format!("storage {} is not connected: {}", address.to_string(), e).as_str(),
+            );
+            return None;
+        }
+        Some(RemoteStorage {
+            address: address.to_string(),
+            connection: RefCell::new(Connection {
+                reader: BufReader::new(stream),
+                writer,
+            }),
+        })
+    }
+
+    // lines of response, None if value is not found or request is failed
+    fn request(&self, lines: &[String]) -> Option<Vec<String>> {
+        let mut connection = self.connection.borrow_mut();
+        let mut content = lines.join("\n");
+        content.push('\n');
+        if let Err(e) = connection.writer.write_all(content.as_bytes()) {
+            Logger::error(format!("storage {} is not written: {}", self.address, e).as_str());
+            return None;
+        }
+        let mut response: Vec<String> = vec![];
+        loop {
+            let line = match read_line(&mut connection.reader) {
+                Some(line) => line,
+                None => {
+                    Logger::error(format!("storage {} is disconnected", self.address).as_str());
+                    return None;
+                }
+            };
+            match line.split_once(' ').unwrap_or((line.as_str(), "")) {
+                (OK, _) => return Some(response),
+                (NONE, _) => return None,
+                (ERROR, mes) => {
+                    let mes = unescape(mes).unwrap_or_default();
+                    Logger::error(format!("storage {}: {}", self.address, mes).as_str());
+                    return None;
+                }
+                _ => response.push(line),
+            }
+        }
+    }
+
+    // value of response with one value line
+    fn request_value(&self, line: String) -> Option<DataType> {
+        let response = self.request(&[line])?;
+        let token = response.first()?.strip_prefix("v ")?;
+        decode_value(token)
+    }
+
+    fn request_records(&self, line: String) -> Option<Vec<(u64, Record)>> {
+        self.request(&[line])?
+            .iter()
+            .map(|e| decode_row_record(e.strip_prefix("r ")?))
+            .collect()
+    }
+
+    fn request_changes(&self, line: String) -> Option<Vec<Change>> {
+        self.request(&[line])?
+            .iter()
+            .map(|e| Change::decode(e.strip_prefix("d ")?))
+            .collect()
+    }
+}
+
+impl StorageBackend for RemoteStorage {
+    fn create_channel(&mut self, channel: &str, schema: Vec<DataVar>) -> Option<()> {
+        self.apply(&[Change::Create(channel.to_owned(), schema)])
+    }
+    fn drop_channel(&mut self, channel: &str) -> Option<()> {
+        self.apply(&[Change::Drop(channel.to_owned())])
+    }
+    fn get_channels(&self) -> Vec<String> {
+        let response = self.request(&["channels".to_string()]);
+        response
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|e| unescape(e.strip_prefix("c ")?))
+            .collect()
+    }
+    fn get_schema(&self, channel: &str) -> Option<Vec<DataVar>> {
+        let changes = self.request_changes(format!("schema {}", escape(channel)))?;
+        match changes.into_iter().next()? {
+            Change::Create(_, schema) => Some(schema),
+            _ => None,
+        }
+    }
+    fn get_row_count(&self, channel: &str) -> Option<usize> {
+        match self.request_value(format!("count {}", escape(channel)))? {
+            DataType::Int(count) => Some(count as usize),
+            _ => None,
+        }
+    }
+    fn get_next_row(&self, channel: &str) -> Option<u64> {
+        match self.request_value(format!("next {}", escape(channel)))? {
+            DataType::Int(row) => Some(row as u64),
+            _ => None,
+        }
+    }
+    fn insert(&mut self, channel: &str, row: u64, record: Record) -> Option<()> {
+        self.apply(&[Change::Insert(channel.to_owned(), row, record)])
+    }
+    fn scan(&self, channel: &str, filter: &Option<Vec<BinaryExpr>>) -> Option<Vec<(u64, Record)>> {
+        let line = format!("scan {} {}", escape(channel), encode_filter(filter));
+        self.request_records(line)
+    }
+    fn update(&mut self, channel: &str, row: u64, record: Record) -> Option<()> {
+        self.apply(&[Change::Update(channel.to_owned(), row, record)])
+    }
+    fn delete(&mut self, channel: &str, row: u64) -> Option<()> {
+        self.apply(&[Change::Delete(channel.to_owned(), row)])
+    }
+    fn get_sequences(&self) -> Option<Sequences> {
+        let mut sequences = Sequences::new();
+        for change in self.request_changes("sequences".to_string())? {
+            if let Change::Sequence(name, value) = change {
+                sequences.set(&name, value);
+            }
+        }
+        Some(sequences)
+    }
+    fn set_sequence(&mut self, name: &str, value: i64) -> Option<()> {
+        self.apply(&[Change::Sequence(name.to_owned(), value)])
+    }
+    fn begin(&mut self) {
+        self.request(&["begin".to_string()]);
+    }
+    fn commit(&mut self) -> Option<()> {
+        self.request(&["commit".to_string()]).map(|_| ())
+    }
+    fn rollback(&mut self) {
+        self.request(&["rollback".to_string()]);
+    }
+    fn create_index(&mut self, channel: &str, field: &str) -> Option<()> {
+        self.apply(&[Change::Index(channel.to_owned(), field.to_owned())])
+    }
+    fn has_index(&self, channel: &str, field: &str) -> bool {
+        let line = format!("index {} {}", escape(channel), escape(field));
+        self.request_value(line) == Some(DataType::Bool(true))
+    }
+    fn get_index_lookup(
+        &self,
+        channel: &str,
+        filter: &Option<Vec<BinaryExpr>>,
+    ) -> Option<(BinaryExpr, usize)> {
+        let line = format!("lookup {} {}", escape(channel), encode_filter(filter));
+        let response = self.request(&[line])?;
+        let mut tokens = response.first()?.strip_prefix("l ")?.split(' ');
+        let expr: usize = tokens.next()?.parse().ok()?;
+        let count: usize = tokens.next()?.parse().ok()?;
+        Some((filter.as_ref()?.get(expr)?.clone(), count))
+    }
+    fn set_expiry(&mut self, channel: &str, expiry: Expiry) -> Option<()> {
+        self.apply(&[Change::Ttl(channel.to_owned(), expiry)])
+    }
+    fn get_expiry(&self, channel: &str) -> Option<Expiry> {
+        let changes = self.request_changes(format!("expiry {}", escape(channel)))?;
+        match changes.into_iter().next()? {
+            Change::Ttl(_, expiry) => Some(expiry),
+            _ => None,
+        }
+    }
+    fn set_deadline(&mut self, channel: &str, row: u64, deadline: i64) -> Option<()> {
+        self.apply(&[Change::Deadline(channel.to_owned(), row, deadline)])
+    }
+    fn scan_expired(&self, channel: &str, now: i64) -> Vec<(u64, Record)> {
+        let line = format!("expired {} {}", escape(channel), now);
+        self.request_records(line).unwrap_or_default()
+    }
+    // changes of statement are sent together, server applies all of them or none
+    fn apply(&mut self, changes: &[Change]) -> Option<()> {
+        let mut lines = vec![format!("apply {}", changes.len())];
+        for change in changes {
+            match change.encode() {
+                Some(line) => lines.push(line),
+                None => {
+                    Logger::error("change has value which can't be sent to storage");
+                    return None;
+                }
+            }
+        }
+        self.request(&lines).map(|_| ())
+    }
+    fn dump(&self) -> Vec<Change> {
+        self.request_changes("dump".to_string()).unwrap_or_default()
+    }
+}
+
+// serve storage of executor to clients of remote storage, clients are served one by one:
+// next client waits until previous one is disconnected or idle for IDLE_TIMEOUT
+// expired records of storage are removed by sweeper of server, not by clients
+pub fn listen<T: ToSocketAddrs + ToString>(executor: StorageExecutor, address: T) -> Option<()> {
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            let mes = format!("address {} is not bound: {}", address.to_string(), e);
+            Logger::error(mes.as_str());
+            return None;
+        }
+    };
+    let executor = Arc::new(Mutex::new(executor));
+    let _sweeper = Sweeper::spawn(executor.clone(), SWEEP_INTERVAL);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => serve(&executor, stream, IDLE_TIMEOUT),
+            Err(e) => Logger::error(format!("client is not connected: {}", e).as_str()),
+        }
+    }
+    Some(())
+}
+
+// requests of one client until it's disconnected or sends nothing for idle time,
+// transaction of disconnected client is rolled back
+// executor is locked for one request, so sweeper removes expired records between them
+pub fn serve(executor: &Mutex<StorageExecutor>, stream: TcpStream, idle: Duration) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+    if stream.set_read_timeout(Some(idle)).is_err() {
+        return;
+    }
+    let mut reader = BufReader::new(stream);
+    let mut in_transaction = false;
+    while let Some(line) = read_line(&mut reader) {
+        let mut tokens = line.split(' ');
+        let command = tokens.next().unwrap_or_default();
+        // executor is poisoned by panic of other thread, its state is not trusted, so client is dropped
+        let mut executor = match executor.lock() {
+            Ok(executor) => executor,
+            Err(_) => {
+                let content = format!("{} {}\n", ERROR, escape("storage is not available"));
+                let _ = writer.write_all(content.as_bytes());
+                return;
+            }
+        };
+        let response = match command {
+            "apply" => {
+                let count: usize = tokens.next().and_then(|e| e.parse().ok()).unwrap_or(0);
+                let changes: Option<Vec<Change>> = (0..count)
+                    .map(|_| read_line(&mut reader).and_then(|e| Change::decode(&e)))
+                    .collect();
+                match changes {
+                    Some(changes) => executor.apply(&changes).map(|_| vec![]),
+                    None => None,
+                }
+            }
+            "begin" => {
+                executor.begin();
+                in_transaction = true;
+                Some(vec![])
+            }
+            "commit" => {
+                in_transaction = false;
+                executor.commit().map(|_| vec![])
+            }
+            "rollback" => {
+                in_transaction = false;
+                executor.rollback();
+                Some(vec![])
+            }
+            _ => answer(executor.get_storage(), command, &mut tokens),
+        };
+        drop(executor);
+        let mut content = match response {
+            Some(lines) => lines.into_iter().map(|e| e + "\n").collect::<String>() + OK,
+            None if matches!(command, "apply" | "commit") => {
+                format!("{} {}", ERROR, escape("change is not applied by storage"))
+            }
+            None => NONE.to_string(),
+        };
+        content.push('\n');
+        if writer.write_all(content.as_bytes()).is_err() {
+            break;
+        }
+    }
+    if in_transaction {
+        if let Ok(mut executor) = executor.lock() {
+            executor.rollback();
+        }
+    }
+}
+
+// lines of response to request which doesn't change storage
+fn answer<'a, I: Iterator<Item = &'a str>>(
+    storage: &dyn StorageBackend,
+    command: &str,
+    tokens: &mut I,
+) -> Option<Vec<String>> {
+    let lines = match command {
+        "channels" => storage
+            .get_channels()
+            .iter()
+            .map(|e| format!("c {}", escape(e)))
+            .collect(),
+        "dump" => encode_changes(&storage.dump())?,
+        "sequences" => {
+            let changes: Vec<Change> = storage
+                .get_sequences()?
+                .get_values()
+                .iter()
+                .map(|(name, value)| Change::Sequence(name.to_owned(), *value))
+                .collect();
+            encode_changes(&changes)?
+        }
+        _ => {
+            let channel = unescape(tokens.next()?)?;
+            match command {
+                "schema" => {
+                    let schema = storage.get_schema(&channel)?;
+                    encode_changes(&[Change::Create(channel, schema)])?
+                }
+                "expiry" => {
+                    let expiry = storage.get_expiry(&channel)?;
+                    encode_changes(&[Change::Ttl(channel, expiry)])?
+                }
+                "count" => {
+                    let count = storage.get_row_count(&channel)? as i64;
+                    vec![format!("v {}", encode_value(&DataType::Int(count))?)]
+                }
+                "next" => {
+                    let row = storage.get_next_row(&channel)? as i64;
+                    vec![format!("v {}", encode_value(&DataType::Int(row))?)]
+                }
+                "index" => {
+                    let field = unescape(tokens.next()?)?;
+                    let found = DataType::Bool(storage.has_index(&channel, &field));
+                    vec![format!("v {}", encode_value(&found)?)]
+                }
+                "scan" => {
+                    let filter = decode_filter(tokens)?;
+                    encode_records(&storage.scan(&channel, &filter)?)?
+                }
+                "expired" => {
+                    let now = tokens.next()?.parse().unwrap_or_else(|_| now_millis());
+                    encode_records(&storage.scan_expired(&channel, now))?
+                }
+                "lookup" => {
+                    let filter = decode_filter(tokens)?;
+                    let (expr, count) = storage.get_index_lookup(&channel, &filter)?;
+                    let position = filter.iter().flatten().position(|e| *e == expr)?;
+                    vec![format!("l {} {}", position, count)]
+                }
+                _ => return None,
+            }
+        }
+    };
+    Some(lines)
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Option<String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(&['\n', '\r'][..]).to_string()),
+    }
+}
+
+// filter which can't be sent is not sent, scan returns all records then
+// example: Some([id == 1]) -> 1 == v s:id v i:1, None -> -
+fn encode_filter(filter: &Option<Vec<BinaryExpr>>) -> String {
+    let exprs = match filter {
+        Some(exprs) => exprs,
+        None => return "-".to_string(),
+    };
+    let mut tokens: Vec<String> = vec![exprs.len().to_string()];
+    for expr in exprs {
+        tokens.push(escape(&expr.get_operator().to_string()));
+        let encoded = encode_scalar_expr(expr.get_lterm(), &mut tokens)
+            .and_then(|_| encode_scalar_expr(expr.get_rterm(), &mut tokens));
+        if encoded.is_none() {
+            return "-".to_string();
+        }
+    }
+    tokens.join(" ")
+}
+
+fn decode_filter<'a, I: Iterator<Item = &'a str>>(
+    tokens: &mut I,
+) -> Option<Option<Vec<BinaryExpr>>> {
+    let len = match tokens.next()? {
+        "-" => return Some(None),
+        len => len.parse::<usize>().ok()?,
+    };
+    let mut exprs: Vec<BinaryExpr> = vec![];
+    for _ in 0..len {
+        let operator = CompareOperator::from_string(&unescape(tokens.next()?)?)?;
+        let lterm = decode_scalar_expr(tokens)?;
+        let rterm = decode_scalar_expr(tokens)?;
+        exprs.push(BinaryExpr::from_scalar_exprs(lterm, rterm, operator));
+    }
+    Some(Some(exprs))
+}
+
+fn encode_changes(changes: &[Change]) -> Option<Vec<String>> {
+    changes
+        .iter()
+        .map(|e| Some(format!("d {}", e.encode()?)))
+        .collect()
+}
+
+// example: (0, { id: 1 }) -> r 0 1 id i:1
+fn encode_records(records: &[(u64, Record)]) -> Option<Vec<String>> {
+    let mut lines: Vec<String> = vec![];
+    for (row, record) in records {
+        let mut tokens = vec!["r".to_string(), row.to_string()];
+        encode_record(record, &mut tokens)?;
+        lines.push(tokens.join(" "));
+    }
+    Some(lines)
+}
+
+fn decode_row_record(line: &str) -> Option<(u64, Record)> {
+    let mut tokens = line.split(' ');
+    let row: u64 = tokens.next()?.parse().ok()?;
+    Some((row, decode_record(&mut tokens)?))
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::executor::Executor;
+    use crate::execution::expiry::Sweeper;
+    use crate::execution::memory::StorageExecutor;
+    use crate::execution::remote::{serve, RemoteStorage, IDLE_TIMEOUT};
+    use crate::execution::result::Status;
+    use crate::execution::storage::StorageBackend;
+    use crate::text_processing::ast::scope::Scope;
+    use crate::text_processing::ast::types::{ChannelName, DataType, Expiry};
+    use crate::text_processing::parser::states::{Parser, ParserDefault};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn test_remote_storage() -> Result<(), ()> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let executor = Mutex::new(StorageExecutor::new(""));
+            let (stream, _) = listener.accept().unwrap();
+            serve(&executor, stream, IDLE_TIMEOUT);
+            executor.into_inner().unwrap()
+        });
+
+        let storage = RemoteStorage::connect(address.as_str()).unwrap();
+        let mut executor = StorageExecutor::with_storage("", Box::new(storage));
+        let script =
+            "onCreate(users)(id: int = nextval(users_id), name: text = 'bob smith') ttl(3600);\
+            onCreate(users)(name: text = 'alice');\
+            onIndex(users)(id);\
+            onUpdate(users)(id == 2)(name: text = 'eve');\
+            onRead(users)(id >= 1, id + 1 == 3)(name);\
+            onUpsert(users)(id == 3)(name: text = 'x');\
+            onDelete(users)(id == 1)";
+        let exprs = ParserDefault::from_script(script, &mut Scope::new()).unwrap();
+        let results: Vec<_> = exprs.iter().map(|e| executor.execute(e)).collect();
+        assert_eq!(
+            Some(&DataType::Text("'eve'".to_string())),
+            results[4].get_rows()[0].get_record().get("name")
+        );
+        assert_eq!(1, results[6].get_affected());
+
+        // failed transaction changes nothing on server
+        executor.begin();
+        executor.execute(&exprs[1]);
+        executor.rollback();
+        let users = ChannelName::from_string("users").unwrap();
+        assert_eq!(2, executor.get_records(&users).unwrap().len());
+        drop(executor);
+
+        let executor = server.join().unwrap();
+        let storage = executor.get_storage();
+        let records = storage.scan("users", &None).unwrap();
+        assert_eq!(Some(&DataType::Int(3)), records[1].1.get("id"));
+        assert_eq!(Some(Expiry::Ttl(3600)), storage.get_expiry("users"));
+        assert_eq!(true, storage.has_index("users", "id"));
+        Ok(())
+    }
+    #[test]
+    fn test_remote_idle_client() -> Result<(), ()> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let executor = Mutex::new(StorageExecutor::new(""));
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                serve(&executor, stream, Duration::from_millis(100));
+            }
+        });
+
+        // idle client is disconnected, so next client is served
+        let idle = TcpStream::connect(address.as_str()).unwrap();
+        let mut storage = RemoteStorage::connect(address.as_str()).unwrap();
+        assert_eq!(Some(()), storage.create_channel("users", vec![]));
+        assert_eq!(vec!["users".to_string()], storage.get_channels());
+        drop((idle, storage));
+        server.join().unwrap();
+        Ok(())
+    }
+    #[test]
+    fn test_remote_sweeper() -> Result<(), ()> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let executor = Arc::new(Mutex::new(StorageExecutor::new("")));
+            let sweeper = Sweeper::spawn(executor.clone(), Duration::from_millis(10));
+            let (stream, _) = listener.accept().unwrap();
+            serve(&executor, stream, IDLE_TIMEOUT);
+            drop(sweeper);
+        });
+
+        // expired records are removed by server while client is connected
+        let storage = RemoteStorage::connect(address.as_str()).unwrap();
+        let mut executor = StorageExecutor::with_storage("", Box::new(storage));
+        let exprs = ParserDefault::from_script(
+            "onCreate(sessions)(token: text = 'a') ttl(0)",
+            &mut Scope::new(),
+        )
+        .unwrap();
+        assert_eq!(1, executor.execute(&exprs[0]).get_affected());
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(Some(0), executor.get_storage().get_row_count("sessions"));
+        drop(executor);
+        server.join().unwrap();
+        Ok(())
+    }
+    #[test]
+    fn test_remote_poisoned_storage() -> Result<(), ()> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let executor = Arc::new(Mutex::new(StorageExecutor::new("")));
+            let poisoned = executor.clone();
+            let _ = std::thread::spawn(move || {
+                let _executor = poisoned.lock().unwrap();
+                panic!("executor is poisoned by test");
+            })
+            .join();
+            let (stream, _) = listener.accept().unwrap();
+            serve(&executor, stream, IDLE_TIMEOUT);
+        });
+
+        // error of server fails statement, sequences are not assumed to be empty
+        let storage = RemoteStorage::connect(address.as_str()).unwrap();
+        let mut executor = StorageExecutor::with_storage("", Box::new(storage));
+        let exprs = ParserDefault::from_script(
+            "onCreate(users)(id: int = nextval(users_id))",
+            &mut Scope::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            &Status::Failed("sequences are not read from storage".to_string()),
+            executor.execute(&exprs[0]).get_reports()[0].get_status()
+        );
+        drop(executor);
+        server.join().unwrap();
+        Ok(())
+    }
+}
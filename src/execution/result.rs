@@ -0,0 +1,445 @@
+use crate::execution::plan::PlanNode;
+pub use crate::text_processing::ast::types::Record;
+use crate::text_processing::ast::types::{
+    ChannelName, DataType, ExplainFormat, FuncType, UnaryFuncExpr,
+};
+
+// text of record for output
+// example: {a: 2, b: 'x'}
+pub fn format_record(record: &Record) -> String {
+    let fields: Vec<String> = record
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+// text of record with fields listed in order of columns
+// example: {b: x, a: 2}
+pub fn format_columns(record: &Record, columns: &[String]) -> String {
+    let fields: Vec<String> = columns
+        .iter()
+        .filter_map(|name| record.get(name).map(|value| format!("{}: {}", name, value)))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// row of read result, tagged with channel where record has been found
+pub struct Row {
+    channel: String,
+    record: Record,
+}
+
+impl Row {
+    pub fn new(channel: String, record: Record) -> Row {
+        Row { channel, record }
+    }
+    pub fn get_channel(&self) -> &String {
+        &self.channel
+    }
+    pub fn get_record(&self) -> &Record {
+        &self.record
+    }
+}
+
+impl std::fmt::Display for Row {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.channel, format_record(&self.record))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// execution status of statement on one channel
+pub enum Status {
+    Ok,
+    Failed(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// what onUpsert has done on channel
+pub enum UpsertAction {
+    Inserted,
+    Updated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// report of statement on one channel
+// affected is count of returned records for onRead and count of changed records for mutations
+pub struct ChannelReport {
+    channel: String,
+    status: Status,
+    affected: usize,
+    upsert_action: Option<UpsertAction>,
+}
+
+impl ChannelReport {
+    pub fn new(channel: String, status: Status, affected: usize) -> ChannelReport {
+        ChannelReport {
+            channel,
+            status,
+            affected,
+            upsert_action: None,
+        }
+    }
+    pub fn get_channel(&self) -> &String {
+        &self.channel
+    }
+    pub fn get_status(&self) -> &Status {
+        &self.status
+    }
+    pub fn get_affected(&self) -> usize {
+        self.affected
+    }
+    // Some for onUpsert: record has been inserted or updated
+    pub fn get_upsert_action(&self) -> Option<UpsertAction> {
+        self.upsert_action
+    }
+}
+
+impl std::fmt::Display for ChannelReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.status, self.upsert_action) {
+            (Status::Failed(mes), _) => write!(f, "{}: failed: {}", self.channel, mes),
+            (Status::Ok, Some(UpsertAction::Inserted)) => write!(f, "{}: inserted", self.channel),
+            (Status::Ok, Some(UpsertAction::Updated)) => {
+                write!(f, "{}: updated {}", self.channel, self.affected)
+            }
+            (Status::Ok, None) => write!(f, "{}: ok {}", self.channel, self.affected),
+        }
+    }
+}
+
+// outcome of statement executed on single channel
+pub enum ChannelOutcome {
+    // records found by onRead
+    Rows(Vec<Record>),
+    // count of records changed by mutation and records of returning clause
+    Affected(usize, Vec<Record>),
+    // onUpsert action, count of changed records and records of returning clause
+    Upserted(UpsertAction, usize, Vec<Record>),
+    Failed(String),
+}
+
+#[derive(Debug, PartialEq)]
+// result of one UnaryFuncExpr: tagged rows and one report per channel
+// explained statement has plan only
+pub struct StatementResult {
+    func_type: FuncType,
+    rows: Vec<Row>,
+    reports: Vec<ChannelReport>,
+    // names of projected fields in order of projection, record keeps fields sorted by name
+    columns: Option<Vec<String>>,
+    plan: Option<(PlanNode, ExplainFormat)>,
+}
+
+impl StatementResult {
+    // result of explained statement, it's printed in format of explain
+    pub fn from_plan(expr: &UnaryFuncExpr, plan: PlanNode) -> StatementResult {
+        StatementResult {
+            func_type: *expr.get_func_type(),
+            rows: vec![],
+            reports: vec![],
+            columns: None,
+            plan: Some((plan, expr.get_explain().unwrap_or(ExplainFormat::Text))),
+        }
+    }
+    pub fn get_func_type(&self) -> &FuncType {
+        &self.func_type
+    }
+    pub fn get_rows(&self) -> &Vec<Row> {
+        &self.rows
+    }
+    pub fn get_reports(&self) -> &Vec<ChannelReport> {
+        &self.reports
+    }
+    pub fn get_columns(&self) -> &Option<Vec<String>> {
+        &self.columns
+    }
+    // true if statement has been executed on every channel
+    pub fn is_ok(&self) -> bool {
+        self.reports.iter().all(|e| e.status == Status::Ok)
+    }
+    // total count of affected records on all channels
+    pub fn get_affected(&self) -> usize {
+        self.reports.iter().map(|e| e.affected).sum()
+    }
+    // replace rows by rows computed over union of channels (onRead pipeline),
+    // count of channel is count of rows tagged with it: a or a,b for groups
+    pub fn with_rows(mut self, rows: Vec<Row>) -> StatementResult {
+        for report in self.reports.iter_mut() {
+            if report.status == Status::Ok {
+                report.affected = rows
+                    .iter()
+                    .filter(|e| e.channel.split(',').any(|e| e == report.channel))
+                    .count();
+            }
+        }
+        self.rows = rows;
+        self
+    }
+}
+
+impl std::fmt::Display for StatementResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.plan {
+            Some((plan, ExplainFormat::Text)) => return write!(f, "{}", plan),
+            Some((plan, ExplainFormat::Json)) => return write!(f, "{}", plan.to_json()),
+            None => {}
+        }
+        for row in self.rows.iter() {
+            match &self.columns {
+                Some(columns) => writeln!(
+                    f,
+                    "{}: {}",
+                    row.get_channel(),
+                    format_columns(row.get_record(), columns)
+                )?,
+                None => writeln!(f, "{}", row)?,
+            }
+        }
+        let reports: Vec<String> = self.reports.iter().map(|e| e.to_string()).collect();
+        write!(f, "{}", reports.join("\n"))
+    }
+}
+
+// execute statement on each of its channels and combine outcomes.
+// semantics of multi-channel statement onX(a,b,c)(...):
+// - statement is executed once per channel, in order of channel list
+// - bare channel name is resolved against node path, rows and reports are tagged
+//   with qualified name (mynode.orders)
+// - channel repeated after resolution (orders, mynode.orders) is not executed again,
+//   its report is failed
+// - onRead returns union of records, every row is tagged with its source channel,
+//   fields of rows are listed in order of projection
+// - mutation with returning clause returns changed records in the same way as onRead,
+//   fields of rows are listed in order of returning
+// - mutations are applied on each channel independently, failure on one channel
+//   does not stop execution on next channels
+// - every channel gets its own report with status and affected records count
+pub fn fan_out<F>(expr: &UnaryFuncExpr, node_path: &str, mut execute: F) -> StatementResult
+where
+    F: FnMut(&ChannelName) -> ChannelOutcome,
+{
+    let mut rows: Vec<Row> = vec![];
+    let mut reports: Vec<ChannelReport> = vec![];
+    let mut executed: Vec<ChannelName> = vec![];
+
+    for channel in expr.get_channel_names() {
+        let channel = channel.resolve(node_path);
+        if executed.contains(&channel) {
+            let mes = format!("channel repeated in statement: {}", channel);
+            let report = ChannelReport::new(channel.to_string(), Status::Failed(mes), 0);
+            reports.push(report);
+            continue;
+        }
+        executed.push(channel.clone());
+        let report = match execute(&channel) {
+            ChannelOutcome::Rows(records) => {
+                let affected = records.len();
+                let tag = channel.to_string();
+                rows.extend(records.into_iter().map(|e| Row::new(tag.clone(), e)));
+                ChannelReport::new(tag, Status::Ok, affected)
+            }
+            ChannelOutcome::Affected(affected, records) => {
+                let tag = channel.to_string();
+                rows.extend(records.into_iter().map(|e| Row::new(tag.clone(), e)));
+                ChannelReport::new(tag, Status::Ok, affected)
+            }
+            ChannelOutcome::Upserted(action, affected, records) => {
+                let tag = channel.to_string();
+                rows.extend(records.into_iter().map(|e| Row::new(tag.clone(), e)));
+                ChannelReport {
+                    upsert_action: Some(action),
+                    ..ChannelReport::new(tag, Status::Ok, affected)
+                }
+            }
+            ChannelOutcome::Failed(mes) => {
+                ChannelReport::new(channel.to_string(), Status::Failed(mes), 0)
+            }
+        };
+        reports.push(report);
+    }
+
+    StatementResult {
+        func_type: *expr.get_func_type(),
+        rows,
+        reports,
+        columns: expr
+            .get_projection()
+            .as_ref()
+            .or_else(|| expr.get_returning().as_ref())
+            .map(|e| e.iter().map(|e| e.get_name()).collect()),
+        plan: None,
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::result::{fan_out, ChannelOutcome, Record, Status, UpsertAction};
+    use crate::text_processing::ast::types::{
+        ChannelName, DataType, FuncType, Projection, ScalarExpr, UnaryFuncExpr,
+    };
+
+    fn channels(names: &[&str]) -> Vec<ChannelName> {
+        names
+            .iter()
+            .map(|e| ChannelName::from_string(e).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_fan_out_read_union() -> Result<(), ()> {
+        let expr = UnaryFuncExpr::new(FuncType::OnRead, channels(&["a", "b"]), None, None);
+        let result = fan_out(&expr, "", |channel| {
+            let mut record = Record::new();
+            record.insert("from".to_string(), DataType::Text(channel.to_string()));
+            ChannelOutcome::Rows(vec![record.clone(), record])
+        });
+
+        assert_eq!(true, result.is_ok());
+        assert_eq!(4, result.get_rows().len());
+        assert_eq!("a", result.get_rows()[0].get_channel());
+        assert_eq!("b", result.get_rows()[3].get_channel());
+        assert_eq!(
+            Some(&DataType::Text("b".to_string())),
+            result.get_rows()[3].get_record().get("from")
+        );
+        assert_eq!(2, result.get_reports()[1].get_affected());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fan_out_mutation_per_channel() -> Result<(), ()> {
+        let expr = UnaryFuncExpr::new(
+            FuncType::OnUpdate,
+            channels(&["a", "mynode.b", "otherdb.c"]),
+            None,
+            None,
+        );
+        let mut visited: Vec<String> = vec![];
+        let result = fan_out(&expr, "mynode", |channel| {
+            visited.push(channel.to_string());
+            match channel.get_name().as_str() {
+                "b" => ChannelOutcome::Failed("channel not found".to_string()),
+                _ => ChannelOutcome::Affected(3, vec![]),
+            }
+        });
+
+        assert_eq!(vec!["mynode.a", "mynode.b", "otherdb.c"], visited);
+        assert_eq!(false, result.is_ok());
+        assert_eq!(true, result.get_rows().is_empty());
+        assert_eq!(6, result.get_affected());
+        assert_eq!(
+            &Status::Failed("channel not found".to_string()),
+            result.get_reports()[1].get_status()
+        );
+        assert_eq!(&Status::Ok, result.get_reports()[2].get_status());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fan_out_resolved_duplicate() -> Result<(), ()> {
+        let expr = UnaryFuncExpr::new(
+            FuncType::OnDelete,
+            channels(&["orders", "mynode.orders"]),
+            None,
+            None,
+        );
+        let mut visited: Vec<String> = vec![];
+        let result = fan_out(&expr, "mynode", |channel| {
+            visited.push(channel.to_string());
+            ChannelOutcome::Affected(1, vec![])
+        });
+
+        assert_eq!(vec!["mynode.orders"], visited);
+        assert_eq!(false, result.is_ok());
+        assert_eq!(1, result.get_affected());
+        assert_eq!(
+            &Status::Failed("channel repeated in statement: mynode.orders".to_string()),
+            result.get_reports()[1].get_status()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fan_out_columns() -> Result<(), ()> {
+        let projection = ["name", "id"]
+            .iter()
+            .map(|e| Projection::new(ScalarExpr::Value(DataType::Symbol(e.to_string())), None))
+            .collect();
+        let expr = UnaryFuncExpr::new(FuncType::OnRead, channels(&["users"]), None, None)
+            .with_projection(Some(projection));
+        let result = fan_out(&expr, "", |_| {
+            let mut record = Record::new();
+            record.insert("id".to_string(), DataType::Int(1));
+            record.insert("name".to_string(), DataType::Text("bob".to_string()));
+            ChannelOutcome::Rows(vec![record])
+        });
+        assert_eq!(
+            &Some(vec!["name".to_string(), "id".to_string()]),
+            result.get_columns()
+        );
+        // rows are printed in order of projection, not in order of record
+        assert_eq!(
+            true,
+            result
+                .to_string()
+                .starts_with("users: {name: bob, id: 1}\n")
+        );
+
+        let expr = UnaryFuncExpr::new(FuncType::OnRead, channels(&["users"]), None, None);
+        let result = fan_out(&expr, "", |_| ChannelOutcome::Rows(vec![]));
+        assert_eq!(&None, result.get_columns());
+
+        let returning = vec![Projection::new(
+            ScalarExpr::Value(DataType::Symbol("id".to_string())),
+            None,
+        )];
+        let expr = UnaryFuncExpr::new(FuncType::OnDelete, channels(&["users"]), None, None)
+            .with_returning(Some(returning));
+        let result = fan_out(&expr, "", |_| ChannelOutcome::Affected(0, vec![]));
+        assert_eq!(&Some(vec!["id".to_string()]), result.get_columns());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fan_out_upsert() -> Result<(), ()> {
+        let expr = UnaryFuncExpr::new(FuncType::OnUpsert, channels(&["a", "b"]), None, None);
+        let result = fan_out(&expr, "", |channel| match channel.get_name().as_str() {
+            "a" => ChannelOutcome::Upserted(UpsertAction::Inserted, 1, vec![]),
+            _ => ChannelOutcome::Upserted(UpsertAction::Updated, 2, vec![]),
+        });
+        assert_eq!(
+            Some(UpsertAction::Inserted),
+            result.get_reports()[0].get_upsert_action()
+        );
+        assert_eq!(
+            Some(UpsertAction::Updated),
+            result.get_reports()[1].get_upsert_action()
+        );
+        assert_eq!(3, result.get_affected());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fan_out_returning() -> Result<(), ()> {
+        let expr = UnaryFuncExpr::new(FuncType::OnCreate, channels(&["a", "b"]), None, None);
+        let result = fan_out(&expr, "", |channel| {
+            let mut record = Record::new();
+            record.insert("id".to_string(), DataType::Int(1));
+            match channel.get_name().as_str() {
+                "a" => ChannelOutcome::Affected(1, vec![record]),
+                _ => ChannelOutcome::Upserted(UpsertAction::Inserted, 1, vec![record]),
+            }
+        });
+        assert_eq!(2, result.get_rows().len());
+        assert_eq!("b", result.get_rows()[1].get_channel());
+        assert_eq!(
+            Some(&DataType::Int(1)),
+            result.get_rows()[1].get_record().get("id")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,843 @@
+use crate::environment::logger::Logger;
+use crate::execution::defaults::{now_millis, Sequences};
+use crate::execution::expiry::is_expired;
+use crate::execution::index::{choose_lookup, lookup_filter, Index};
+use crate::execution::mvcc::{ReadSnapshot, Versions};
+use crate::execution::result::Record;
+use crate::execution::wal::{Change, Wal};
+use crate::text_processing::ast::types::{BinaryExpr, DataVar, Expiry};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+// storage of channels used by executor, executor computes changes of statement
+// and storage keeps them, so executor doesn't depend on storage implementation
+// channel is full name of channel, record is found by its row id in channel,
+// row id is given by insert and kept by updates, ids of removed records are not reused
+// example: in-memory storage of tests, file storage of --uri file://, remote storage
+pub trait StorageBackend: Send {
+    fn create_channel(&mut self, channel: &str, schema: Vec<DataVar>) -> Option<()>;
+    fn drop_channel(&mut self, channel: &str) -> Option<()>;
+    fn get_channels(&self) -> Vec<String>;
+    // schema declared by first onCreate, None if channel is not created
+    fn get_schema(&self, channel: &str) -> Option<Vec<DataVar>>;
+    // count of stored records
+    fn get_row_count(&self, channel: &str) -> Option<usize>;
+    // row id of next inserted record
+    fn get_next_row(&self, channel: &str) -> Option<u64>;
+
+    fn insert(&mut self, channel: &str, row: u64, record: Record) -> Option<()>;
+    // rows and records which can be matched by filter, in order of rows
+    // filter can only narrow scan, executor checks it again on every record
+    fn scan(&self, channel: &str, filter: &Option<Vec<BinaryExpr>>) -> Option<Vec<(u64, Record)>>;
+    fn update(&mut self, channel: &str, row: u64, record: Record) -> Option<()>;
+    fn delete(&mut self, channel: &str, row: u64) -> Option<()>;
+
+    // sequences of nextval defaults, None if they're not read from storage
+    fn get_sequences(&self) -> Option<Sequences>;
+    fn set_sequence(&mut self, name: &str, value: i64) -> Option<()>;
+
+    // changes between begin and commit are kept all together or none of them
+    fn begin(&mut self);
+    fn commit(&mut self) -> Option<()>;
+    fn rollback(&mut self);
+
+    // changes which create content of storage from empty storage,
+    // indexes and deadlines of records are kept, used by backup and checkpoint
+    fn dump(&self) -> Vec<Change>;
+
+    // optional features, statement which needs unsupported feature fails
+
+    fn create_index(&mut self, _channel: &str, _field: &str) -> Option<()> {
+        unsupported("index")
+    }
+    fn has_index(&self, _channel: &str, _field: &str) -> bool {
+        false
+    }
+    // rows found by index for filter, in order of rows, None if no filter uses index
+    // records of rows are checked by filter after lookup
+    fn lookup_rows(&self, _channel: &str, _filter: &Option<Vec<BinaryExpr>>) -> Option<Vec<u64>> {
+        None
+    }
+    // filter answered by index and count of records found by it, used by explain
+    fn get_index_lookup(
+        &self,
+        _channel: &str,
+        _filter: &Option<Vec<BinaryExpr>>,
+    ) -> Option<(BinaryExpr, usize)> {
+        None
+    }
+    fn set_expiry(&mut self, _channel: &str, _expiry: Expiry) -> Option<()> {
+        unsupported("ttl")
+    }
+    fn get_expiry(&self, _channel: &str) -> Option<Expiry> {
+        None
+    }
+    fn set_deadline(&mut self, _channel: &str, _row: u64, _deadline: i64) -> Option<()> {
+        unsupported("ttl")
+    }
+    // rows and records which are expired, they're removed by sweeper
+    fn scan_expired(&self, _channel: &str, _now: i64) -> Vec<(u64, Record)> {
+        vec![]
+    }
+    // retained versions of committed records, they answer reads as of version and history
+    fn get_versions(&self) -> Option<Versions> {
+        None
+    }
+    // consistent read of committed records from other thread
+    fn read_snapshot(&self) -> Option<ReadSnapshot> {
+        self.get_versions().map(|e| e.snapshot())
+    }
+
+    // changes of one statement, storage can override it to keep them atomically
+    fn apply(&mut self, changes: &[Change]) -> Option<()> {
+        for change in changes.iter().cloned() {
+            match change {
+                Change::Create(channel, schema) => self.create_channel(&channel, schema)?,
+                Change::Drop(channel) => self.drop_channel(&channel)?,
+                Change::Insert(channel, row, record) => self.insert(&channel, row, record)?,
+                Change::Update(channel, row, record) => self.update(&channel, row, record)?,
+                Change::Delete(channel, row) => self.delete(&channel, row)?,
+                Change::Sequence(name, value) => self.set_sequence(&name, value)?,
+                Change::Index(channel, field) => self.create_index(&channel, &field)?,
+                Change::Ttl(channel, expiry) => self.set_expiry(&channel, expiry)?,
+                Change::Deadline(channel, row, deadline) => {
+                    self.set_deadline(&channel, row, deadline)?
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+fn unsupported(feature: &str) -> Option<()> {
+    Logger::error(format!("{} is not supported by storage", feature).as_str());
+    None
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+// records of channel, schema is declared by statements of first onCreate
+struct Channel {
+    schema: Vec<DataVar>,
+    // records by row id, rows are given in order of inserts
+    // records are shared with versions, so committed records are not copied
+    records: BTreeMap<u64, Arc<Record>>,
+    // row id of next inserted record
+    next_row: u64,
+    // indexes by field, they're changed with records
+    indexes: BTreeMap<String, Index>,
+    // expiry declared by first onCreate
+    expiry: Option<Expiry>,
+    // time of expiry of record of row, given by ttl of channel
+    deadlines: BTreeMap<u64, i64>,
+}
+
+impl Channel {
+    // rows of records which can be matched by filter, in order of rows
+    // expired records are skipped, they're not removed by sweeper yet
+    fn find(&self, exprs: &Option<Vec<BinaryExpr>>, now: i64) -> Vec<u64> {
+        let rows = match lookup_filter(&self.indexes, exprs) {
+            Some(rows) => rows,
+            None => self.records.keys().cloned().collect(),
+        };
+        rows.into_iter()
+            .filter(|e| !self.is_expired(*e, now))
+            .collect()
+    }
+
+    fn is_expired(&self, row: u64, now: i64) -> bool {
+        match self.records.get(&row) {
+            Some(record) => {
+                is_expired(record, self.deadlines.get(&row).cloned(), &self.expiry, now)
+            }
+            None => false,
+        }
+    }
+}
+
+// rows of channel changed by previous changes of statement, they're checked before apply
+struct ChangedRows<'a> {
+    // channel before changes, None for channel created by them
+    table: Option<&'a Channel>,
+    next_row: u64,
+    inserted: BTreeSet<u64>,
+    deleted: BTreeSet<u64>,
+}
+
+impl<'a> ChangedRows<'a> {
+    fn new(table: Option<&'a Channel>) -> ChangedRows<'a> {
+        ChangedRows {
+            table,
+            next_row: table.map_or(0, |e| e.next_row),
+            inserted: BTreeSet::new(),
+            deleted: BTreeSet::new(),
+        }
+    }
+
+    fn contains(&self, row: u64) -> bool {
+        self.inserted.contains(&row)
+            || (!self.deleted.contains(&row)
+                && matches!(self.table, Some(table) if table.records.contains_key(&row)))
+    }
+
+    // row ids are given in order, so ids of removed records are not reused
+    fn insert(&mut self, row: u64) -> bool {
+        if row < self.next_row {
+            return false;
+        }
+        self.next_row = row + 1;
+        self.inserted.insert(row);
+        true
+    }
+
+    fn delete(&mut self, row: u64) -> bool {
+        if !self.contains(row) {
+            return false;
+        }
+        self.inserted.remove(&row);
+        self.deleted.insert(row);
+        true
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+// state of storage, it's copied at begin of transaction
+struct State {
+    channels: BTreeMap<String, Channel>,
+    sequences: Sequences,
+}
+
+impl State {
+    // every change of storage is applied here, both for statements and for replay of log
+    fn apply(&mut self, change: Change) -> Option<()> {
+        let applied = match change {
+            Change::Create(channel, schema) => {
+                let table = Channel {
+                    schema,
+                    ..Channel::default()
+                };
+                self.channels.insert(channel, table).is_none()
+            }
+            Change::Drop(channel) => self.channels.remove(&channel).is_some(),
+            Change::Insert(channel, row, record) => match self.channels.get_mut(&channel) {
+                Some(table) if row >= table.next_row => {
+                    for index in table.indexes.values_mut() {
+                        index.insert(&record, row);
+                    }
+                    table.records.insert(row, Arc::new(record));
+                    table.next_row = row + 1;
+                    true
+                }
+                _ => false,
+            },
+            Change::Update(channel, row, record) => match self.channels.get_mut(&channel) {
+                Some(table) => match table.records.get_mut(&row) {
+                    Some(old) => {
+                        for index in table.indexes.values_mut() {
+                            index.update(old, &record, row);
+                        }
+                        *old = Arc::new(record);
+                        true
+                    }
+                    None => false,
+                },
+                None => false,
+            },
+            Change::Delete(channel, row) => match self.channels.get_mut(&channel) {
+                Some(table) => match table.records.remove(&row) {
+                    Some(record) => {
+                        table.deadlines.remove(&row);
+                        for index in table.indexes.values_mut() {
+                            index.delete(&record, row);
+                        }
+                        true
+                    }
+                    None => false,
+                },
+                None => false,
+            },
+            Change::Index(channel, field) => match self.channels.get_mut(&channel) {
+                Some(table) => {
+                    let records = table.records.iter().map(|(row, e)| (row, e.as_ref()));
+                    let index = Index::new(&field, records);
+                    table.indexes.insert(field, index);
+                    true
+                }
+                None => false,
+            },
+            Change::Sequence(name, value) => {
+                self.sequences.set(&name, value);
+                true
+            }
+            Change::Ttl(channel, expiry) => match self.channels.get_mut(&channel) {
+                Some(table) => {
+                    table.expiry = Some(expiry);
+                    true
+                }
+                None => false,
+            },
+            Change::Deadline(channel, row, deadline) => match self.channels.get_mut(&channel) {
+                Some(table) if table.records.contains_key(&row) => {
+                    table.deadlines.insert(row, deadline);
+                    true
+                }
+                _ => false,
+            },
+        };
+        if !applied {
+            Logger::error("change is not matched with state of storage");
+            return None;
+        }
+        Some(())
+    }
+
+    // changes are checked before they're applied or written to log,
+    // so changes which can't be applied change nothing
+    fn check(&self, changes: &[Change]) -> bool {
+        // rows of channels changed by previous changes, None for dropped channel
+        let mut changed: BTreeMap<&str, Option<ChangedRows>> = BTreeMap::new();
+        for change in changes {
+            let channel = match change {
+                Change::Sequence(_, _) => continue,
+                Change::Create(channel, _)
+                | Change::Drop(channel)
+                | Change::Insert(channel, _, _)
+                | Change::Update(channel, _, _)
+                | Change::Delete(channel, _)
+                | Change::Index(channel, _)
+                | Change::Ttl(channel, _)
+                | Change::Deadline(channel, _, _) => channel.as_str(),
+            };
+            let rows = changed.entry(channel).or_insert_with(|| {
+                let table = self.channels.get(channel);
+                table.map(|_| ChangedRows::new(table))
+            });
+            let checked = match (change, rows.as_mut()) {
+                (Change::Create(_, _), None) => true,
+                (Change::Create(_, _), Some(_)) | (_, None) => false,
+                (Change::Insert(_, row, _), Some(rows)) => rows.insert(*row),
+                (Change::Delete(_, row), Some(rows)) => rows.delete(*row),
+                (Change::Update(_, row, _), Some(rows))
+                | (Change::Deadline(_, row, _), Some(rows)) => rows.contains(*row),
+                _ => true,
+            };
+            if !checked {
+                return false;
+            }
+            match change {
+                Change::Create(_, _) => *rows = Some(ChangedRows::new(None)),
+                Change::Drop(_) => *rows = None,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    // changes which create state from empty storage, used for checkpoint
+    fn dump(&self) -> Vec<Change> {
+        let mut changes: Vec<Change> = vec![];
+        for (name, table) in self.channels.iter() {
+            changes.push(Change::Create(name.to_owned(), table.schema.clone()));
+            if let Some(expiry) = &table.expiry {
+                changes.push(Change::Ttl(name.to_owned(), expiry.clone()));
+            }
+            for (row, record) in table.records.iter() {
+                changes.push(Change::Insert(
+                    name.to_owned(),
+                    *row,
+                    record.as_ref().clone(),
+                ));
+                if let Some(deadline) = table.deadlines.get(row) {
+                    changes.push(Change::Deadline(name.to_owned(), *row, *deadline));
+                }
+            }
+            for field in table.indexes.keys() {
+                changes.push(Change::Index(name.to_owned(), field.to_owned()));
+            }
+        }
+        for (name, value) in self.sequences.get_values() {
+            changes.push(Change::Sequence(name.to_owned(), *value));
+        }
+        changes
+    }
+}
+
+#[derive(Default)]
+// storage of channels kept in memory, selected by --uri mem://
+// committed changes are versioned, so snapshots of it can be read from other threads
+pub struct MemoryStorage {
+    state: State,
+    // state before begin, it's restored by rollback
+    snapshot: Option<State>,
+    // changes of transaction are versioned on commit
+    pending_changes: Vec<Change>,
+    versions: Versions,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+
+    // storage with state created by changes
+    pub fn replay(changes: Vec<Change>) -> Option<MemoryStorage> {
+        let mut storage = MemoryStorage::new();
+        for change in changes {
+            storage.state.apply(change)?;
+        }
+        storage.commit_versions(&storage.state.dump());
+        storage.versions.set_opened();
+        Some(storage)
+    }
+
+    // versions share records of state, state is changed by changes already
+    fn commit_versions(&self, changes: &[Change]) {
+        self.versions
+            .commit_shared(changes, |channel, row| self.get_record(channel, row));
+    }
+
+    fn get_record(&self, channel: &str, row: u64) -> Option<Arc<Record>> {
+        self.get_channel(channel)?.records.get(&row).cloned()
+    }
+
+    fn check(&self, changes: &[Change]) -> Option<()> {
+        if !self.state.check(changes) {
+            Logger::error("change is not matched with state of storage");
+            return None;
+        }
+        Some(())
+    }
+
+    fn get_channel(&self, channel: &str) -> Option<&Channel> {
+        self.state.channels.get(channel)
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn create_channel(&mut self, channel: &str, schema: Vec<DataVar>) -> Option<()> {
+        self.apply(&[Change::Create(channel.to_owned(), schema)])
+    }
+    fn drop_channel(&mut self, channel: &str) -> Option<()> {
+        self.apply(&[Change::Drop(channel.to_owned())])
+    }
+    fn get_channels(&self) -> Vec<String> {
+        self.state.channels.keys().cloned().collect()
+    }
+    fn get_schema(&self, channel: &str) -> Option<Vec<DataVar>> {
+        self.get_channel(channel).map(|e| e.schema.clone())
+    }
+    fn get_row_count(&self, channel: &str) -> Option<usize> {
+        self.get_channel(channel).map(|e| e.records.len())
+    }
+    fn get_next_row(&self, channel: &str) -> Option<u64> {
+        self.get_channel(channel).map(|e| e.next_row)
+    }
+    fn insert(&mut self, channel: &str, row: u64, record: Record) -> Option<()> {
+        self.apply(&[Change::Insert(channel.to_owned(), row, record)])
+    }
+    fn scan(&self, channel: &str, filter: &Option<Vec<BinaryExpr>>) -> Option<Vec<(u64, Record)>> {
+        let table = self.get_channel(channel)?;
+        let records = table
+            .find(filter, now_millis())
+            .into_iter()
+            .filter_map(|e| Some((e, table.records.get(&e)?.as_ref().clone())));
+        Some(records.collect())
+    }
+    fn update(&mut self, channel: &str, row: u64, record: Record) -> Option<()> {
+        self.apply(&[Change::Update(channel.to_owned(), row, record)])
+    }
+    fn delete(&mut self, channel: &str, row: u64) -> Option<()> {
+        self.apply(&[Change::Delete(channel.to_owned(), row)])
+    }
+    fn get_sequences(&self) -> Option<Sequences> {
+        Some(self.state.sequences.clone())
+    }
+    fn set_sequence(&mut self, name: &str, value: i64) -> Option<()> {
+        self.apply(&[Change::Sequence(name.to_owned(), value)])
+    }
+    fn begin(&mut self) {
+        self.snapshot = Some(self.state.clone());
+    }
+    fn commit(&mut self) -> Option<()> {
+        self.snapshot = None;
+        let changes = std::mem::take(&mut self.pending_changes);
+        self.commit_versions(&changes);
+        Some(())
+    }
+    fn rollback(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            self.state = snapshot;
+        }
+        self.pending_changes.clear();
+    }
+    fn create_index(&mut self, channel: &str, field: &str) -> Option<()> {
+        self.apply(&[Change::Index(channel.to_owned(), field.to_owned())])
+    }
+    fn has_index(&self, channel: &str, field: &str) -> bool {
+        matches!(self.get_channel(channel), Some(table) if table.indexes.contains_key(field))
+    }
+    fn lookup_rows(&self, channel: &str, filter: &Option<Vec<BinaryExpr>>) -> Option<Vec<u64>> {
+        let mut rows = lookup_filter(&self.get_channel(channel)?.indexes, filter)?;
+        rows.sort_unstable();
+        Some(rows)
+    }
+    fn get_index_lookup(
+        &self,
+        channel: &str,
+        filter: &Option<Vec<BinaryExpr>>,
+    ) -> Option<(BinaryExpr, usize)> {
+        let table = self.get_channel(channel)?;
+        choose_lookup(&table.indexes, filter).map(|(expr, rows)| (expr.clone(), rows.len()))
+    }
+    fn set_expiry(&mut self, channel: &str, expiry: Expiry) -> Option<()> {
+        self.apply(&[Change::Ttl(channel.to_owned(), expiry)])
+    }
+    fn get_expiry(&self, channel: &str) -> Option<Expiry> {
+        self.get_channel(channel)?.expiry.clone()
+    }
+    fn set_deadline(&mut self, channel: &str, row: u64, deadline: i64) -> Option<()> {
+        self.apply(&[Change::Deadline(channel.to_owned(), row, deadline)])
+    }
+    fn scan_expired(&self, channel: &str, now: i64) -> Vec<(u64, Record)> {
+        match self.get_channel(channel) {
+            Some(table) => table
+                .records
+                .iter()
+                .filter(|(row, _)| table.is_expired(**row, now))
+                .map(|(row, record)| (*row, record.as_ref().clone()))
+                .collect(),
+            None => vec![],
+        }
+    }
+    fn get_versions(&self) -> Option<Versions> {
+        Some(self.versions.clone())
+    }
+    // changes of statement get one version, changes of transaction get it on commit
+    fn apply(&mut self, changes: &[Change]) -> Option<()> {
+        self.check(changes)?;
+        for change in changes.iter().cloned() {
+            self.state.apply(change)?;
+        }
+        match self.snapshot {
+            Some(_) => self.pending_changes.extend(changes.iter().cloned()),
+            None => self.commit_versions(changes),
+        }
+        Some(())
+    }
+    fn dump(&self) -> Vec<Change> {
+        self.state.dump()
+    }
+}
+
+// storage of channels in directory, selected by --uri file:///path/to/db
+// channels are kept in memory and every change is written to log before it is applied
+pub struct FileStorage {
+    memory: MemoryStorage,
+    wal: Wal,
+    // changes of transaction are written to log on commit
+    pending_changes: Vec<Change>,
+    in_transaction: bool,
+}
+
+impl FileStorage {
+    // storage of directory, state is restored from checkpoint and log
+    pub fn open<T: Into<PathBuf>>(dir: T) -> Option<FileStorage> {
+        let (wal, changes) = Wal::open(dir)?;
+        Some(FileStorage {
+            memory: MemoryStorage::replay(changes)?,
+            wal,
+            pending_changes: vec![],
+            in_transaction: false,
+        })
+    }
+
+    // checkpoint is written outside of transaction only,
+    // failed checkpoint keeps log, so it's tried again after next changes
+    fn checkpoint(&mut self) {
+        if !self.in_transaction && self.wal.needs_checkpoint() {
+            let _ = self.wal.checkpoint(&self.memory.dump());
+        }
+    }
+}
+
+impl StorageBackend for FileStorage {
+    fn create_channel(&mut self, channel: &str, schema: Vec<DataVar>) -> Option<()> {
+        self.apply(&[Change::Create(channel.to_owned(), schema)])
+    }
+    fn drop_channel(&mut self, channel: &str) -> Option<()> {
+        self.apply(&[Change::Drop(channel.to_owned())])
+    }
+    fn get_channels(&self) -> Vec<String> {
+        self.memory.get_channels()
+    }
+    fn get_schema(&self, channel: &str) -> Option<Vec<DataVar>> {
+        self.memory.get_schema(channel)
+    }
+    fn get_row_count(&self, channel: &str) -> Option<usize> {
+        self.memory.get_row_count(channel)
+    }
+    fn get_next_row(&self, channel: &str) -> Option<u64> {
+        self.memory.get_next_row(channel)
+    }
+    fn insert(&mut self, channel: &str, row: u64, record: Record) -> Option<()> {
+        self.apply(&[Change::Insert(channel.to_owned(), row, record)])
+    }
+    fn scan(&self, channel: &str, filter: &Option<Vec<BinaryExpr>>) -> Option<Vec<(u64, Record)>> {
+        self.memory.scan(channel, filter)
+    }
+    fn update(&mut self, channel: &str, row: u64, record: Record) -> Option<()> {
+        self.apply(&[Change::Update(channel.to_owned(), row, record)])
+    }
+    fn delete(&mut self, channel: &str, row: u64) -> Option<()> {
+        self.apply(&[Change::Delete(channel.to_owned(), row)])
+    }
+    fn get_sequences(&self) -> Option<Sequences> {
+        self.memory.get_sequences()
+    }
+    fn set_sequence(&mut self, name: &str, value: i64) -> Option<()> {
+        self.apply(&[Change::Sequence(name.to_owned(), value)])
+    }
+    fn begin(&mut self) {
+        self.memory.begin();
+        self.in_transaction = true;
+    }
+    // transaction is committed when its changes are written to log
+    fn commit(&mut self) -> Option<()> {
+        let changes = std::mem::take(&mut self.pending_changes);
+        if !changes.is_empty() && self.wal.append(&changes).is_none() {
+            self.rollback();
+            return None;
+        }
+        self.in_transaction = false;
+        self.memory.commit();
+        self.checkpoint();
+        Some(())
+    }
+    fn rollback(&mut self) {
+        self.memory.rollback();
+        self.pending_changes.clear();
+        self.in_transaction = false;
+    }
+    fn create_index(&mut self, channel: &str, field: &str) -> Option<()> {
+        self.apply(&[Change::Index(channel.to_owned(), field.to_owned())])
+    }
+    fn has_index(&self, channel: &str, field: &str) -> bool {
+        self.memory.has_index(channel, field)
+    }
+    fn lookup_rows(&self, channel: &str, filter: &Option<Vec<BinaryExpr>>) -> Option<Vec<u64>> {
+        self.memory.lookup_rows(channel, filter)
+    }
+    fn get_index_lookup(
+        &self,
+        channel: &str,
+        filter: &Option<Vec<BinaryExpr>>,
+    ) -> Option<(BinaryExpr, usize)> {
+        self.memory.get_index_lookup(channel, filter)
+    }
+    fn set_expiry(&mut self, channel: &str, expiry: Expiry) -> Option<()> {
+        self.apply(&[Change::Ttl(channel.to_owned(), expiry)])
+    }
+    fn get_expiry(&self, channel: &str) -> Option<Expiry> {
+        self.memory.get_expiry(channel)
+    }
+    fn set_deadline(&mut self, channel: &str, row: u64, deadline: i64) -> Option<()> {
+        self.apply(&[Change::Deadline(channel.to_owned(), row, deadline)])
+    }
+    fn scan_expired(&self, channel: &str, now: i64) -> Vec<(u64, Record)> {
+        self.memory.scan_expired(channel, now)
+    }
+    fn get_versions(&self) -> Option<Versions> {
+        self.memory.get_versions()
+    }
+    // changes are written to log before they are applied
+    fn apply(&mut self, changes: &[Change]) -> Option<()> {
+        self.memory.check(changes)?;
+        match self.in_transaction {
+            true => self.pending_changes.extend(changes.iter().cloned()),
+            false => self.wal.append(changes)?,
+        }
+        self.memory.apply(changes)?;
+        self.checkpoint();
+        Some(())
+    }
+    fn dump(&self) -> Vec<Change> {
+        self.memory.dump()
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::result::Record;
+    use crate::execution::storage::{FileStorage, MemoryStorage, StorageBackend};
+    use crate::execution::wal::Change;
+    use crate::text_processing::ast::types::{BinaryExpr, CompareOperator, DataType};
+    use std::sync::Arc;
+
+    fn record(id: i64, age: i64) -> Record {
+        let mut record = Record::new();
+        record.insert("id".to_string(), DataType::Int(id));
+        record.insert("age".to_string(), DataType::Int(age));
+        record
+    }
+
+    fn ids(records: Option<Vec<(u64, Record)>>) -> Vec<(u64, DataType)> {
+        records
+            .unwrap()
+            .into_iter()
+            .map(|(i, e)| (i, e.get("id").unwrap().clone()))
+            .collect()
+    }
+
+    // storage which implements required methods only, features are checked by executor
+    fn check_storage(storage: &mut dyn StorageBackend) {
+        storage.create_channel("users", vec![]).unwrap();
+        assert_eq!(None, storage.create_channel("users", vec![]));
+        storage.insert("users", 0, record(1, 30)).unwrap();
+        storage.insert("users", 1, record(2, 18)).unwrap();
+        storage.insert("users", 2, record(3, 25)).unwrap();
+        storage.update("users", 0, record(1, 17)).unwrap();
+        storage.delete("users", 1).unwrap();
+        storage.set_sequence("users_id", 3).unwrap();
+        assert_eq!(Some(2), storage.get_row_count("users"));
+        assert_eq!(Some(3), storage.get_next_row("users"));
+        // rows of records are kept by delete of other record
+        assert_eq!(
+            vec![(0, DataType::Int(1)), (2, DataType::Int(3))],
+            ids(storage.scan("users", &None))
+        );
+        // row id of removed record is not reused
+        assert_eq!(None, storage.insert("users", 1, record(4, 20)));
+        assert_eq!(None, storage.update("users", 1, record(4, 20)));
+
+        storage.begin();
+        storage.delete("users", 0).unwrap();
+        storage.rollback();
+        assert_eq!(Some(2), storage.get_row_count("users"));
+        assert_eq!(3, storage.get_sequences().unwrap().get("users_id"));
+
+        storage.create_channel("tmp", vec![]).unwrap();
+        storage.drop_channel("tmp").unwrap();
+        assert_eq!(vec!["users".to_string()], storage.get_channels());
+        assert_eq!(None, storage.get_schema("tmp"));
+    }
+
+    #[test]
+    fn test_memory_storage() -> Result<(), ()> {
+        let mut storage = MemoryStorage::new();
+        check_storage(&mut storage);
+
+        // index is changed with records
+        let age = DataType::Symbol("age".to_string());
+        let filter = Some(vec![BinaryExpr::new(
+            age,
+            DataType::Int(20),
+            CompareOperator::Gt,
+        )]);
+        storage.create_index("users", "age").unwrap();
+        assert_eq!(true, storage.has_index("users", "age"));
+        assert_eq!(
+            vec![(2, DataType::Int(3))],
+            ids(storage.scan("users", &filter))
+        );
+        storage.insert("users", 3, record(4, 40)).unwrap();
+        storage.delete("users", 0).unwrap();
+        assert_eq!(
+            vec![(2, DataType::Int(3)), (3, DataType::Int(4))],
+            ids(storage.scan("users", &filter))
+        );
+        assert_eq!(2, storage.get_index_lookup("users", &filter).unwrap().1);
+        assert_eq!(Some(vec![2, 3]), storage.lookup_rows("users", &filter));
+        assert_eq!(None, storage.lookup_rows("users", &None));
+
+        // committed record is shared by state and versions, it's not copied,
+        // last change of row in transaction is shared too, count includes returned record
+        let shared = storage.get_record("users", 3).unwrap();
+        assert_eq!(3, Arc::strong_count(&shared));
+        storage.begin();
+        storage.update("users", 3, record(4, 41)).unwrap();
+        storage.update("users", 3, record(4, 42)).unwrap();
+        storage.commit().unwrap();
+        let snapshot = storage.read_snapshot().unwrap();
+        assert_eq!(
+            Some(&DataType::Int(42)),
+            snapshot.get_records("users").unwrap()[1].get("age")
+        );
+        assert_eq!(
+            3,
+            Arc::strong_count(&storage.get_record("users", 3).unwrap())
+        );
+
+        let restored = MemoryStorage::replay(storage.dump()).unwrap();
+        assert_eq!(true, restored.has_index("users", "age"));
+        assert_eq!(
+            ids(storage.scan("users", &None)),
+            ids(restored.scan("users", &None))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_storage_delete() -> Result<(), ()> {
+        let mut storage = MemoryStorage::new();
+        storage.create_channel("users", vec![]).unwrap();
+        storage.create_index("users", "age").unwrap();
+        let inserts: Vec<Change> = (0..1000)
+            .map(|e| Change::Insert("users".to_string(), e, record(e as i64, e as i64 % 10)))
+            .collect();
+        storage.apply(&inserts).unwrap();
+
+        // rows of one statement are deleted in any order, other rows are kept by index
+        let deletes: Vec<Change> = (0..1000)
+            .filter(|e| e % 2 == 0)
+            .map(|e| Change::Delete("users".to_string(), e))
+            .collect();
+        storage.apply(&deletes).unwrap();
+        let filter = Some(vec![BinaryExpr::new(
+            DataType::Symbol("age".to_string()),
+            DataType::Int(3),
+            CompareOperator::Eq,
+        )]);
+        let found = ids(storage.scan("users", &filter));
+        assert_eq!(100, found.len());
+        assert_eq!((3, DataType::Int(3)), found[0]);
+        assert_eq!((993, DataType::Int(993)), found[99]);
+        // row deleted twice by one statement changes nothing
+        let deletes = vec![
+            Change::Delete("users".to_string(), 1),
+            Change::Delete("users".to_string(), 1),
+        ];
+        assert_eq!(None, storage.apply(&deletes));
+        assert_eq!(Some(500), storage.get_row_count("users"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_storage() -> Result<(), ()> {
+        let dir = std::env::temp_dir().join(format!("qdb_file_storage_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut storage = FileStorage::open(&dir).unwrap();
+        check_storage(&mut storage);
+        storage.begin();
+        storage.insert("users", 3, record(5, 50)).unwrap();
+        storage.set_deadline("users", 3, i64::MAX).unwrap();
+        storage.commit().unwrap();
+        let dump = storage.dump();
+        drop(storage);
+
+        // every kept change is read back from log
+        let storage = FileStorage::open(&dir).unwrap();
+        assert_eq!(dump, storage.dump());
+        assert_eq!(
+            true,
+            dump.contains(&Change::Insert("users".to_string(), 3, record(5, 50)))
+        );
+        assert_eq!(
+            true,
+            dump.contains(&Change::Deadline("users".to_string(), 3, i64::MAX))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+}
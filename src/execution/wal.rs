@@ -0,0 +1,717 @@
+use crate::environment::logger::Logger;
+use crate::execution::result::Record;
+use crate::text_processing::ast::functions::ScalarFunc;
+use crate::text_processing::ast::types::{
+    ArithOperator, DataType, DataVar, DefaultExpr, Expiry, ScalarExpr,
+};
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const LOG_FILE: &str = "wal.log";
+const CHECKPOINT_FILE: &str = "checkpoint";
+// checkpoint is written when log has this count of changes
+pub const CHECKPOINT_INTERVAL: usize = 1000;
+// reads of snapshot while storage is checkpointed by other process
+const SNAPSHOT_ATTEMPTS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+// change of storage state, unit of write-ahead log
+// channel is full name of channel, example: mynode.users
+pub enum Change {
+    // channel is created with schema
+    Create(String, Vec<DataVar>),
+    // channel is removed with its records
+    Drop(String),
+    // record is inserted with row id, row ids of channel are not reused
+    Insert(String, u64, Record),
+    // record of row is replaced
+    Update(String, u64, Record),
+    // record of row is removed
+    Delete(String, u64),
+    // sequence has value
+    Sequence(String, i64),
+    // field of channel is indexed
+    Index(String, String),
+    // records of channel expire
+    Ttl(String, Expiry),
+    // record of row expires at time in milliseconds since epoch
+    Deadline(String, u64, i64),
+}
+
+impl Change {
+    // line of log without checksum, None for values which can't be stored
+    // example: Insert(users, 0, { id: 1 }) -> insert users 0 1 id i:1
+    pub fn encode(&self) -> Option<String> {
+        let mut tokens: Vec<String> = vec![];
+        match self {
+            Change::Create(channel, schema) => {
+                tokens.extend(vec!["create".to_string(), channel.to_owned()]);
+                tokens.push(schema.len().to_string());
+                for var in schema {
+                    encode_var(var, &mut tokens)?;
+                }
+            }
+            Change::Drop(channel) => {
+                tokens.extend(vec!["drop".to_string(), channel.to_owned()]);
+            }
+            Change::Insert(channel, row, record) => {
+                tokens.extend(vec!["insert".to_string(), channel.to_owned()]);
+                tokens.push(row.to_string());
+                encode_record(record, &mut tokens)?;
+            }
+            Change::Update(channel, row, record) => {
+                tokens.extend(vec!["update".to_string(), channel.to_owned()]);
+                tokens.push(row.to_string());
+                encode_record(record, &mut tokens)?;
+            }
+            Change::Delete(channel, row) => {
+                tokens.extend(vec!["delete".to_string(), channel.to_owned()]);
+                tokens.push(row.to_string());
+            }
+            Change::Sequence(name, value) => {
+                tokens.extend(vec![
+                    "sequence".to_string(),
+                    name.to_owned(),
+                    value.to_string(),
+                ]);
+            }
+            Change::Index(channel, field) => {
+                tokens.extend(vec!["index".to_string(), channel.to_owned(), escape(field)]);
+            }
+            Change::Ttl(channel, expiry) => {
+                tokens.extend(vec!["ttl".to_string(), channel.to_owned()]);
+                match expiry {
+                    Expiry::Ttl(seconds) => {
+                        tokens.extend(vec!["s".to_string(), seconds.to_string()])
+                    }
+                    Expiry::Field(field) => tokens.extend(vec!["f".to_string(), escape(field)]),
+                }
+            }
+            Change::Deadline(channel, row, deadline) => {
+                tokens.extend(vec![
+                    "deadline".to_string(),
+                    channel.to_owned(),
+                    row.to_string(),
+                    deadline.to_string(),
+                ]);
+            }
+        }
+        Some(tokens.join(" "))
+    }
+
+    pub fn decode(line: &str) -> Option<Change> {
+        let mut tokens = line.split(' ');
+        let change = match tokens.next()? {
+            "create" => {
+                let channel = tokens.next()?.to_string();
+                let len: usize = tokens.next()?.parse().ok()?;
+                let schema = (0..len)
+                    .map(|_| decode_var(&mut tokens))
+                    .collect::<Option<Vec<DataVar>>>()?;
+                Change::Create(channel, schema)
+            }
+            "drop" => Change::Drop(tokens.next()?.to_string()),
+            "insert" => {
+                let channel = tokens.next()?.to_string();
+                let row = tokens.next()?.parse().ok()?;
+                Change::Insert(channel, row, decode_record(&mut tokens)?)
+            }
+            "update" => {
+                let channel = tokens.next()?.to_string();
+                let row = tokens.next()?.parse().ok()?;
+                Change::Update(channel, row, decode_record(&mut tokens)?)
+            }
+            "delete" => Change::Delete(tokens.next()?.to_string(), tokens.next()?.parse().ok()?),
+            "sequence" => {
+                Change::Sequence(tokens.next()?.to_string(), tokens.next()?.parse().ok()?)
+            }
+            "index" => Change::Index(tokens.next()?.to_string(), unescape(tokens.next()?)?),
+            "ttl" => {
+                let channel = tokens.next()?.to_string();
+                let expiry = match tokens.next()? {
+                    "s" => Expiry::Ttl(tokens.next()?.parse().ok()?),
+                    "f" => Expiry::Field(unescape(tokens.next()?)?),
+                    _ => return None,
+                };
+                Change::Ttl(channel, expiry)
+            }
+            "deadline" => {
+                let channel = tokens.next()?.to_string();
+                let row = tokens.next()?.parse().ok()?;
+                Change::Deadline(channel, row, tokens.next()?.parse().ok()?)
+            }
+            _ => return None,
+        };
+        // line is decoded completely
+        match tokens.next() {
+            Some(_) => None,
+            None => Some(change),
+        }
+    }
+}
+
+// write-ahead log of storage directory
+// changes are appended as batch closed by commit line with sequence number of batch,
+// every line has checksum, so torn or damaged tail of log is found and dropped on open
+// checkpoint is full state of storage as one batch, it's replaced atomically by rename
+// example of log:
+//   6c3d1a2b9f0e4d21 insert users 0 1 id i:1
+//   0b5e7f3c2a1d9e84 commit 12
+pub struct Wal {
+    dir: PathBuf,
+    log: File,
+    // length of log with committed batches only
+    len: u64,
+    // sequence number of last batch
+    lsn: u64,
+    // changes appended after last checkpoint
+    unchecked: usize,
+}
+
+impl Wal {
+    // open log of directory, directory is created if not exists
+    // returns changes of checkpoint and of log batches written after checkpoint
+    pub fn open<T: Into<PathBuf>>(dir: T) -> Option<(Wal, Vec<Change>)> {
+        let dir: PathBuf = dir.into();
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            Logger::error(format!("storage directory is not created: {}", e).as_str());
+            return None;
+        }
+        let content = read_checkpoint_file(&dir.join(CHECKPOINT_FILE))?;
+        let (checkpoint_lsn, mut changes) = read_checkpoint(content.as_deref())?;
+
+        let log_path = dir.join(LOG_FILE);
+        let bytes = read_file(&log_path)?.unwrap_or_default();
+        let (batches, len) = read_batches(&log_text(&bytes));
+        if len < bytes.len() {
+            Logger::warn(
+                format!(
+                    "log has not committed tail, {} bytes are dropped",
+                    bytes.len() - len
+                )
+                .as_str(),
+            );
+        }
+        let mut lsn = checkpoint_lsn;
+        let mut unchecked = 0;
+        // batches before checkpoint are in log when log is not truncated after checkpoint
+        for (batch_lsn, batch) in batches {
+            if batch_lsn <= checkpoint_lsn {
+                continue;
+            }
+            lsn = batch_lsn;
+            unchecked += batch.len();
+            changes.extend(batch);
+        }
+
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .and_then(|log| log.set_len(len as u64).map(|_| log));
+        let log = match log {
+            Ok(log) => log,
+            Err(e) => {
+                Logger::error(format!("log file is not opened: {}", e).as_str());
+                return None;
+            }
+        };
+        let wal = Wal {
+            dir,
+            log,
+            len: len as u64,
+            lsn,
+            unchecked,
+        };
+        Some((wal, changes))
+    }
+
+    // committed state of directory, directory is only read
+    // log can be appended and checkpointed by other process while it's read,
+    // so reading is repeated when checkpoint is replaced between reads of files
+    // returns sequence number of last batch and changes of checkpoint and log
+    pub fn read_snapshot<T: Into<PathBuf>>(dir: T) -> Option<(u64, Vec<Change>)> {
+        let dir: PathBuf = dir.into();
+        for _ in 0..SNAPSHOT_ATTEMPTS {
+            let checkpoint = read_checkpoint_file(&dir.join(CHECKPOINT_FILE))?;
+            let log = read_file(&dir.join(LOG_FILE))?.unwrap_or_default();
+            if read_checkpoint_file(&dir.join(CHECKPOINT_FILE))? != checkpoint {
+                continue;
+            }
+            let (mut lsn, mut changes) = read_checkpoint(checkpoint.as_deref())?;
+            // log is truncated after checkpoint, so batches after checkpoint go one by one
+            let mut is_continuous = true;
+            for (batch_lsn, batch) in read_batches(&log_text(&log)).0 {
+                if batch_lsn <= lsn {
+                    continue;
+                }
+                if batch_lsn != lsn + 1 {
+                    is_continuous = false;
+                    break;
+                }
+                lsn = batch_lsn;
+                changes.extend(batch);
+            }
+            if is_continuous {
+                return Some((lsn, changes));
+            }
+        }
+        Logger::error("snapshot is not read, storage is changed too often");
+        None
+    }
+
+    // append batch and sync it to disk, batch is committed after return only
+    pub fn append(&mut self, changes: &[Change]) -> Option<()> {
+        let content = match encode_batch(changes, self.lsn + 1) {
+            Some(content) => content,
+            None => {
+                Logger::error("change has value which can't be written to log");
+                return None;
+            }
+        };
+        let result = self
+            .log
+            .write_all(content.as_bytes())
+            .and_then(|_| self.log.sync_data());
+        if let Err(e) = result {
+            Logger::error(format!("log is not written: {}", e).as_str());
+            // partly written batch is cut, so next batches are not written after it
+            let _ = self.log.set_len(self.len);
+            return None;
+        }
+        self.len += content.len() as u64;
+        self.lsn += 1;
+        self.unchecked += changes.len();
+        Some(())
+    }
+
+    pub fn needs_checkpoint(&self) -> bool {
+        self.unchecked >= CHECKPOINT_INTERVAL
+    }
+
+    // replace checkpoint by full state, then truncate log
+    // crash before rename keeps old checkpoint with full log,
+    // crash after rename keeps batches of log which are skipped on open
+    pub fn checkpoint(&mut self, changes: &[Change]) -> Option<()> {
+        let content = encode_batch(changes, self.lsn)?;
+        let path = self.dir.join(CHECKPOINT_FILE);
+        let tmp_path = path.with_extension("tmp");
+        let result = File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(content.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&tmp_path, &path))
+            .and_then(|_| sync_dir(&self.dir));
+        if let Err(e) = result {
+            Logger::error(format!("checkpoint is not written: {}", e).as_str());
+            return None;
+        }
+        if let Err(e) = self.log.set_len(0).and_then(|_| self.log.sync_data()) {
+            Logger::error(format!("log is not truncated: {}", e).as_str());
+            return None;
+        }
+        self.len = 0;
+        self.unchecked = 0;
+        Some(())
+    }
+}
+
+// lsn and changes of checkpoint file, empty checkpoint if file not exists
+fn read_checkpoint(content: Option<&str>) -> Option<(u64, Vec<Change>)> {
+    let content = match content {
+        Some(content) => content,
+        None => return Some((0, vec![])),
+    };
+    match read_batches(content) {
+        (batches, len) if len == content.len() && batches.len() == 1 => Some(batches[0].clone()),
+        _ => {
+            Logger::error("checkpoint file is damaged");
+            None
+        }
+    }
+}
+
+// hash of line, example: FNV-1a 64
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, e| {
+        (hash ^ *e as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub fn encode_batch(changes: &[Change], lsn: u64) -> Option<String> {
+    let mut content = String::new();
+    let lines = changes
+        .iter()
+        .map(|e| e.encode())
+        .chain(std::iter::once(Some(format!("commit {}", lsn))));
+    for line in lines {
+        let line = line?;
+        content.push_str(format!("{:016x} {}\n", checksum(line.as_bytes()), line).as_str());
+    }
+    Some(content)
+}
+
+// committed batches and length of content with them
+// reading is stopped at first damaged line or at batch without commit line
+pub fn read_batches(content: &str) -> (Vec<(u64, Vec<Change>)>, usize) {
+    let mut batches: Vec<(u64, Vec<Change>)> = vec![];
+    let mut batch: Vec<Change> = vec![];
+    let mut len = 0;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        offset += line.len();
+        let line = match line.strip_suffix('\n').and_then(verify_line) {
+            Some(line) => line,
+            None => break,
+        };
+        if let Some(lsn) = line.strip_prefix("commit ") {
+            match lsn.parse::<u64>() {
+                Ok(lsn) => batches.push((lsn, std::mem::take(&mut batch))),
+                Err(_) => break,
+            }
+            len = offset;
+            continue;
+        }
+        match Change::decode(line) {
+            Some(change) => batch.push(change),
+            None => break,
+        }
+    }
+    (batches, len)
+}
+
+// line without checksum, None if checksum is not matched
+fn verify_line(line: &str) -> Option<&str> {
+    let (hash, line) = (line.get(..16)?, line.get(17..)?);
+    match u64::from_str_radix(hash, 16) {
+        Ok(hash) if hash == checksum(line.as_bytes()) => Some(line),
+        _ => None,
+    }
+}
+
+// content of file, None in option if file not exists
+fn read_file(path: &Path) -> Option<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(content) => Some(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Some(None),
+        Err(e) => {
+            Logger::error(format!("storage file is not read: {}", e).as_str());
+            None
+        }
+    }
+}
+
+// checkpoint is replaced by rename, so checkpoint with invalid utf-8 is damaged
+fn read_checkpoint_file(path: &Path) -> Option<Option<String>> {
+    match read_file(path)?.map(String::from_utf8) {
+        None => Some(None),
+        Some(Ok(content)) => Some(Some(content)),
+        Some(Err(_)) => {
+            Logger::error("checkpoint is damaged: content is not utf-8");
+            None
+        }
+    }
+}
+
+// text of log before first invalid utf-8 byte,
+// crash can leave torn bytes in tail, they are dropped like not committed batch
+fn log_text(bytes: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(bytes) {
+        Ok(content) => Cow::Borrowed(content),
+        Err(e) => String::from_utf8_lossy(&bytes[..e.valid_up_to()]),
+    }
+}
+
+// rename is durable when directory is synced
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+// text is escaped, so value is one token without spaces and line breaks
+// example: Text('a b') -> t:'a\sb'
+pub fn encode_value(value: &DataType) -> Option<String> {
+    let token = match value {
+        DataType::Null => "n".to_string(),
+        DataType::Bool(val) => format!("b:{}", val),
+        DataType::Int(val) => format!("i:{}", val),
+        DataType::Real(val) => format!("r:{:?}", val),
+        DataType::Text(val) => format!("t:{}", escape(val)),
+        DataType::Symbol(val) => format!("s:{}", escape(val)),
+        DataType::Param(_) => return None,
+    };
+    Some(token)
+}
+
+pub fn decode_value(token: &str) -> Option<DataType> {
+    if token == "n" {
+        return Some(DataType::Null);
+    }
+    let (kind, val) = (token.get(..2)?, token.get(2..)?);
+    let value = match kind {
+        "b:" => DataType::Bool(val.parse().ok()?),
+        "i:" => DataType::Int(val.parse().ok()?),
+        "r:" => DataType::Real(val.parse().ok()?),
+        "t:" => DataType::Text(unescape(val)?),
+        "s:" => DataType::Symbol(unescape(val)?),
+        _ => return None,
+    };
+    Some(value)
+}
+
+pub fn escape(val: &str) -> String {
+    let mut escaped = String::with_capacity(val.len());
+    for e in val.chars() {
+        match e {
+            '\\' => escaped.push_str("\\\\"),
+            ' ' => escaped.push_str("\\s"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            e => escaped.push(e),
+        }
+    }
+    escaped
+}
+
+pub fn unescape(val: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(val.len());
+    let mut chars = val.chars();
+    while let Some(e) = chars.next() {
+        if e != '\\' {
+            unescaped.push(e);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            's' => unescaped.push(' '),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+// count of fields, then name and value of every field
+pub fn encode_record(record: &Record, tokens: &mut Vec<String>) -> Option<()> {
+    tokens.push(record.len().to_string());
+    for (name, value) in record {
+        tokens.push(escape(name));
+        tokens.push(encode_value(value)?);
+    }
+    Some(())
+}
+
+pub fn decode_record<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Option<Record> {
+    let len: usize = tokens.next()?.parse().ok()?;
+    let mut record = Record::new();
+    for _ in 0..len {
+        let name = unescape(tokens.next()?)?;
+        record.insert(name, decode_value(tokens.next()?)?);
+    }
+    Some(record)
+}
+
+// name, value and default of statement
+// example: id: int = nextval(users_id) -> id i:0 nextval users_id
+pub fn encode_var(var: &DataVar, tokens: &mut Vec<String>) -> Option<()> {
+    tokens.push(escape(var.get_name()));
+    tokens.push(encode_value(var.get_value())?);
+    match var.get_default() {
+        None => tokens.push("-".to_string()),
+        Some(DefaultExpr::Now) => tokens.push("now".to_string()),
+        Some(DefaultExpr::Uuid) => tokens.push("uuid".to_string()),
+        Some(DefaultExpr::NextVal(sequence)) => {
+            tokens.extend(vec!["nextval".to_string(), escape(sequence)])
+        }
+        Some(DefaultExpr::Expr(expr)) => {
+            tokens.push("expr".to_string());
+            encode_scalar_expr(expr, tokens)?;
+        }
+    }
+    Some(())
+}
+
+pub fn decode_var<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Option<DataVar> {
+    let name = unescape(tokens.next()?)?;
+    let value = decode_value(tokens.next()?)?;
+    let default = match tokens.next()? {
+        "-" => None,
+        "now" => Some(DefaultExpr::Now),
+        "uuid" => Some(DefaultExpr::Uuid),
+        "nextval" => Some(DefaultExpr::NextVal(unescape(tokens.next()?)?)),
+        "expr" => Some(DefaultExpr::Expr(decode_scalar_expr(tokens)?)),
+        _ => return None,
+    };
+    Some(DataVar::new(name, value).with_default(default))
+}
+
+// expression in prefix notation, aggregates are never in schema
+// example: concat(first, ' ') -> c concat 2 v s:first v t:'\s'
+pub fn encode_scalar_expr(expr: &ScalarExpr, tokens: &mut Vec<String>) -> Option<()> {
+    match expr {
+        ScalarExpr::Value(value) => tokens.extend(vec!["v".to_string(), encode_value(value)?]),
+        ScalarExpr::Arithmetic(lterm, operator, rterm) => {
+            tokens.extend(vec!["a".to_string(), operator.to_string()]);
+            encode_scalar_expr(lterm, tokens)?;
+            encode_scalar_expr(rterm, tokens)?;
+        }
+        ScalarExpr::Call(func, args) => {
+            tokens.extend(vec![
+                "c".to_string(),
+                func.to_string(),
+                args.len().to_string(),
+            ]);
+            for arg in args {
+                encode_scalar_expr(arg, tokens)?;
+            }
+        }
+        ScalarExpr::Aggregate(_, _) => return None,
+    }
+    Some(())
+}
+
+pub fn decode_scalar_expr<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Option<ScalarExpr> {
+    let expr = match tokens.next()? {
+        "v" => ScalarExpr::Value(decode_value(tokens.next()?)?),
+        "a" => {
+            let operator = ArithOperator::from_string(tokens.next()?)?;
+            let lterm = decode_scalar_expr(tokens)?;
+            let rterm = decode_scalar_expr(tokens)?;
+            ScalarExpr::Arithmetic(Box::new(lterm), operator, Box::new(rterm))
+        }
+        "c" => {
+            let func = ScalarFunc::from_string(tokens.next()?)?;
+            let len: usize = tokens.next()?.parse().ok()?;
+            let args = (0..len)
+                .map(|_| decode_scalar_expr(tokens))
+                .collect::<Option<Vec<ScalarExpr>>>()?;
+            ScalarExpr::Call(func, args)
+        }
+        _ => return None,
+    };
+    Some(expr)
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::result::Record;
+    use crate::execution::wal::{read_batches, Change, Wal};
+    use crate::text_processing::ast::scope::Scope;
+    use crate::text_processing::ast::types::{DataType, Expiry};
+    use crate::text_processing::parser::states::{Parser, ParserDefault};
+    use std::io::Write;
+
+    fn record(id: i64, name: &str) -> Record {
+        [
+            ("id".to_string(), DataType::Int(id)),
+            ("name".to_string(), DataType::Text(name.to_string())),
+        ]
+        .iter()
+        .cloned()
+        .collect()
+    }
+
+    #[test]
+    fn test_change_encode() -> Result<(), ()> {
+        let script =
+            "onCreate(users)(id: int = nextval(users_id), full: text = concat(name, ' ', id * 2), \
+            created: int = now(), score: real = 1.5, name: text = 'bob')";
+        let expr = &ParserDefault::from_script(script, &mut Scope::new()).unwrap()[0];
+        let changes = vec![
+            Change::Create("users".to_string(), expr.get_vars().clone().unwrap()),
+            Change::Insert("users".to_string(), 0, record(1, "'a b\\ \n'")),
+            Change::Update("users".to_string(), 0, record(2, "''")),
+            Change::Delete("users".to_string(), 0),
+            Change::Sequence("users_id".to_string(), 2),
+            Change::Index("users".to_string(), "name".to_string()),
+            Change::Drop("users".to_string()),
+            Change::Ttl("users".to_string(), Expiry::Ttl(3600)),
+            Change::Ttl("users".to_string(), Expiry::Field("expires_at".to_string())),
+            Change::Deadline("users".to_string(), 0, 1700000000000),
+        ];
+        for change in changes {
+            let line = change.encode().unwrap();
+            assert_eq!(false, line.contains('\n'));
+            assert_eq!(Some(change), Change::decode(line.as_str()));
+        }
+        assert_eq!(None, Change::decode("insert users 1 id"));
+        assert_eq!(None, Change::decode("delete users 0 1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wal_recovery() -> Result<(), ()> {
+        let dir = std::env::temp_dir().join(format!("qdb_wal_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let (mut wal, changes) = Wal::open(&dir).unwrap();
+        assert_eq!(true, changes.is_empty());
+        let first = vec![Change::Insert("a".to_string(), 0, record(1, "'x'"))];
+        let second = vec![Change::Delete("a".to_string(), 0)];
+        wal.append(&first).unwrap();
+        wal.append(&second).unwrap();
+        drop(wal);
+
+        // crash in the middle of batch: change without commit line and torn line
+        let log_path = dir.join("wal.log");
+        let mut log = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&log_path)
+            .unwrap();
+        let torn = Change::Insert("a".to_string(), 1, record(2, "'y'"))
+            .encode()
+            .unwrap();
+        let line = format!("{:016x} {}\n", super::checksum(torn.as_bytes()), torn);
+        log.write_all(line.as_bytes()).unwrap();
+        log.write_all(&line.as_bytes()[..10]).unwrap();
+        drop(log);
+
+        let (wal, changes) = Wal::open(&dir).unwrap();
+        assert_eq!([first.clone(), second.clone()].concat(), changes);
+        let content = std::fs::read_to_string(&log_path).unwrap();
+        assert_eq!(content.len(), read_batches(&content).1);
+        drop(wal);
+
+        // torn tail with invalid utf-8, example: multibyte char is cut by crash
+        let mut log = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&log_path)
+            .unwrap();
+        let torn = Change::Insert("a".to_string(), 2, record(3, "'é'"))
+            .encode()
+            .unwrap();
+        let line = format!("{:016x} {}\n", super::checksum(torn.as_bytes()), torn);
+        let cut = line.find('é').unwrap() + 1;
+        log.write_all(&line.as_bytes()[..cut]).unwrap();
+        log.write_all(&[0xff, 0xfe]).unwrap();
+        drop(log);
+
+        let (mut wal, changes) = Wal::open(&dir).unwrap();
+        assert_eq!([first.clone(), second].concat(), changes);
+        let content = std::fs::read_to_string(&log_path).unwrap();
+        assert_eq!(content.len(), read_batches(&content).1);
+
+        // batches before checkpoint are skipped when log is not truncated
+        let log_before = std::fs::read_to_string(&log_path).unwrap();
+        wal.checkpoint(&first).unwrap();
+        std::fs::write(&log_path, log_before).unwrap();
+        let (_, changes) = Wal::open(&dir).unwrap();
+        assert_eq!(first, changes);
+
+        // damaged checkpoint is not replayed
+        std::fs::write(dir.join("checkpoint"), "0 damaged").unwrap();
+        assert_eq!(true, Wal::open(&dir).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+use crate::execution::filter::Filter;
+use crate::execution::result::{format_record, Record};
+use crate::text_processing::ast::types::{ChannelName, UnaryFuncExpr};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// kind of change of record
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Updated => "updated",
+            ChangeKind::Deleted => "deleted",
+        };
+        write!(f, "{}", val)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// change of one record in channel
+// old is None for created record, new is None for deleted record
+pub struct ChangeEvent {
+    channel: String,
+    kind: ChangeKind,
+    old: Option<Record>,
+    new: Option<Record>,
+}
+
+impl ChangeEvent {
+    pub fn new(
+        channel: String,
+        kind: ChangeKind,
+        old: Option<Record>,
+        new: Option<Record>,
+    ) -> ChangeEvent {
+        ChangeEvent {
+            channel,
+            kind,
+            old,
+            new,
+        }
+    }
+    pub fn get_channel(&self) -> &String {
+        &self.channel
+    }
+    pub fn get_kind(&self) -> ChangeKind {
+        self.kind
+    }
+    pub fn get_old(&self) -> &Option<Record> {
+        &self.old
+    }
+    pub fn get_new(&self) -> &Option<Record> {
+        &self.new
+    }
+}
+
+impl std::fmt::Display for ChangeEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format_record = |record: &Option<Record>| match record {
+            Some(record) => format_record(record),
+            None => "-".to_string(),
+        };
+        write!(
+            f,
+            "{} {}: {} -> {}",
+            self.channel,
+            self.kind,
+            format_record(&self.old),
+            format_record(&self.new)
+        )
+    }
+}
+
+// subscriber of onWatch statement
+struct Subscriber {
+    id: usize,
+    channels: Vec<ChannelName>,
+    // filter is compiled once at subscription
+    filter: Option<Filter>,
+    sender: Sender<ChangeEvent>,
+}
+
+impl Subscriber {
+    // update matches if record matches filter before or after change
+    fn is_matched(&self, channel: &ChannelName, event: &ChangeEvent) -> bool {
+        if !self.channels.contains(channel) {
+            return false;
+        }
+        let filter = match &self.filter {
+            Some(filter) => filter,
+            None => return true,
+        };
+        [&event.old, &event.new].iter().any(|record| match record {
+            Some(record) => filter.is_matched(record),
+            None => false,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Subscribers {
+    next_id: usize,
+    subscribers: Vec<Subscriber>,
+}
+
+#[derive(Clone, Default)]
+// feed of changes, executor publishes changes after each mutation
+// and every onWatch statement receives changes matching its channels and filter
+pub struct ChangeFeed {
+    subscribers: Arc<Mutex<Subscribers>>,
+}
+
+impl ChangeFeed {
+    pub fn new() -> ChangeFeed {
+        ChangeFeed::default()
+    }
+
+    // subscribe onWatch statement, bare channel names are resolved against node path
+    pub fn subscribe(&self, expr: &UnaryFuncExpr, node_path: &str) -> Subscription {
+        let (sender, receiver) = channel();
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let id = subscribers.next_id;
+        subscribers.next_id += 1;
+        subscribers.subscribers.push(Subscriber {
+            id,
+            channels: expr
+                .get_channel_names()
+                .iter()
+                .map(|e| e.resolve(node_path))
+                .collect(),
+            filter: expr
+                .get_binary_exprs()
+                .as_ref()
+                .map(|_| Filter::compile(expr.get_binary_exprs())),
+            sender,
+        });
+        Subscription {
+            receiver,
+            canceller: Canceller {
+                id,
+                feed: self.clone(),
+            },
+        }
+    }
+
+    // send change to matching subscribers, channel is qualified name of changed channel
+    pub fn publish(&self, channel: &ChannelName, event: ChangeEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        // subscriber with dropped subscription is removed
+        subscribers
+            .subscribers
+            .retain(|e| !e.is_matched(channel, &event) || e.sender.send(event.to_owned()).is_ok());
+    }
+
+    fn unsubscribe(&self, id: usize) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.subscribers.retain(|e| e.id != id);
+    }
+}
+
+#[derive(Clone)]
+// handle to cancel subscription from other thread, example: on Ctrl+C
+pub struct Canceller {
+    id: usize,
+    feed: ChangeFeed,
+}
+
+impl Canceller {
+    pub fn cancel(&self) {
+        self.feed.unsubscribe(self.id);
+    }
+}
+
+// stream of changes for one onWatch statement
+// iteration blocks until next change and ends when subscription is cancelled
+pub struct Subscription {
+    receiver: Receiver<ChangeEvent>,
+    canceller: Canceller,
+}
+
+impl Subscription {
+    pub fn get_canceller(&self) -> Canceller {
+        self.canceller.clone()
+    }
+    pub fn cancel(&self) {
+        self.canceller.cancel();
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = ChangeEvent;
+
+    fn next(&mut self) -> Option<ChangeEvent> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// changes of subscription are handled by other thread as they arrive,
+// thread ends when subscription is cancelled
+// example: spawn_events(subscription, |e| println!("{}", e))
+pub fn spawn_events<F>(subscription: Subscription, on_event: F) -> JoinHandle<()>
+where
+    F: Fn(ChangeEvent) + Send + 'static,
+{
+    std::thread::spawn(move || subscription.for_each(on_event))
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::execution::result::Record;
+    use crate::execution::watch::{ChangeEvent, ChangeFeed, ChangeKind};
+    use crate::text_processing::ast::types::{
+        BinaryExpr, ChannelName, CompareOperator, DataType, FuncType, UnaryFuncExpr,
+    };
+
+    fn record(age: i64) -> Record {
+        let mut record = Record::new();
+        record.insert("age".to_string(), DataType::Int(age));
+        record
+    }
+
+    #[test]
+    fn test_change_feed() -> Result<(), ()> {
+        let feed = ChangeFeed::new();
+        let expr = UnaryFuncExpr::new(
+            FuncType::OnWatch,
+            vec![ChannelName::from_string("users").unwrap()],
+            Some(vec![BinaryExpr::new(
+                DataType::Symbol("age".to_string()),
+                DataType::Int(18),
+                CompareOperator::Gt,
+            )]),
+            None,
+        );
+        let mut subscription = feed.subscribe(&expr, "mynode");
+        let users = ChannelName::from_string("mynode.users").unwrap();
+        let orders = ChannelName::from_string("mynode.orders").unwrap();
+
+        let created = ChangeEvent::new(
+            users.to_string(),
+            ChangeKind::Created,
+            None,
+            Some(record(20)),
+        );
+        feed.publish(&users, created.clone());
+        // filter does not match
+        feed.publish(
+            &users,
+            ChangeEvent::new(
+                users.to_string(),
+                ChangeKind::Created,
+                None,
+                Some(record(2)),
+            ),
+        );
+        // other channel
+        feed.publish(
+            &orders,
+            ChangeEvent::new(
+                orders.to_string(),
+                ChangeKind::Deleted,
+                Some(record(30)),
+                None,
+            ),
+        );
+        // old record matches
+        let updated = ChangeEvent::new(
+            users.to_string(),
+            ChangeKind::Updated,
+            Some(record(20)),
+            Some(record(10)),
+        );
+        feed.publish(&users, updated.clone());
+
+        subscription.get_canceller().cancel();
+        assert_eq!(Some(created), subscription.next());
+        assert_eq!(Some(updated.clone()), subscription.next());
+        assert_eq!(None, subscription.next());
+        assert_eq!(
+            "mynode.users updated: {age: 20} -> {age: 10}",
+            updated.to_string()
+        );
+        Ok(())
+    }
+}
@@ -1,15 +1,146 @@
-#[macro_use]
-extern crate lazy_static;
-#[macro_use]
-extern crate log;
+extern crate qdb_cli2;
 extern crate simple_logger;
-extern crate qdb_ast;
 
-mod environment;
-mod text_processing;
+use qdb_cli2::environment::about::{
+    get_definitions, get_params, get_retention, get_script, get_subcommand, get_uri,
+};
+use qdb_cli2::environment::logger::Logger;
+use qdb_cli2::environment::uri::Uri;
+use qdb_cli2::execution::backup::{backup, restore};
+use qdb_cli2::execution::executor::{execute_block, execute_script, BlockResult, BlockStatus};
+use qdb_cli2::execution::expiry::{Sweeper, SWEEP_INTERVAL};
+use qdb_cli2::execution::memory::{SharedExecutor, StorageExecutor};
+use qdb_cli2::execution::plan::PlanExecutor;
+use qdb_cli2::execution::remote::{listen, RemoteStorage};
+use qdb_cli2::execution::storage::{FileStorage, MemoryStorage, StorageBackend};
+use qdb_cli2::execution::watch::spawn_events;
+use qdb_cli2::text_processing::ast::scope::Scope;
 
-//use crate::text_processing::m;
+// port of remote storage when uri doesn't set it
+const DEFAULT_PORT: u16 = 6060;
 
 fn main() {
     simple_logger::init();
+
+    let uri = get_uri();
+    if let Some((command, file)) = get_subcommand() {
+        run_subcommand(&uri, command.as_str(), file.as_str());
+        return;
+    }
+    // channels of file storage are not namespaced by path of directory
+    let node_path = if uri.is_file() {
+        ""
+    } else {
+        uri.get_node_path()
+    };
+    let mut scope = Scope::from_params(get_params())
+        .with_definitions(get_definitions())
+        .with_node_path(node_path);
+    let is_remote = !uri.is_memory() && !uri.is_file();
+    let executor = match (uri.is_memory(), uri.is_file()) {
+        (true, _) => Some(StorageExecutor::new(node_path)),
+        (_, true) => StorageExecutor::open(node_path, uri.get_path()),
+        _ => {
+            let address = format!(
+                "{}:{}",
+                uri.get_host(),
+                uri.get_port().unwrap_or(DEFAULT_PORT)
+            );
+            RemoteStorage::connect(address.as_str())
+                .map(|e| StorageExecutor::with_storage(node_path, Box::new(e)))
+        }
+    };
+    let executor = match executor {
+        Some(executor) => SharedExecutor::new(executor.with_retention(get_retention())),
+        None if is_remote => {
+            // statements are explained without statistics of storage
+            Logger::error(format!("storage is not connected: {}", uri.get_host()).as_str());
+            let mut executor = PlanExecutor::new(node_path);
+            let parsed = execute_script(get_script().as_str(), &mut scope, |block| {
+                print_block(execute_block(&mut executor, block))
+            });
+            if parsed.is_none() {
+                Logger::error("script is not parsed");
+            }
+            return;
+        }
+        None => {
+            Logger::error(format!("storage is not opened: {}", uri.get_path()).as_str());
+            return;
+        }
+    };
+    // expired records are removed while script is executed and while changes are watched
+    let sweeper = Sweeper::spawn(executor.get_executor(), SWEEP_INTERVAL);
+    let mut watchers = vec![];
+    // script is parsed block by block while it's executed,
+    // so let and define of aborted block are not visible to next blocks
+    // executor is locked by statements, onRead is read after it's released
+    let mut shared = executor.clone();
+    let parsed = execute_script(get_script().as_str(), &mut scope, |block| {
+        let result = print_block(execute_block(&mut shared, block));
+        // changes caught by onWatch statements are printed as they arrive
+        for subscription in executor.lock().take_subscriptions() {
+            let canceller = subscription.get_canceller();
+            watchers.push((canceller, spawn_events(subscription, |e| println!("{}", e))));
+        }
+        result
+    });
+    if parsed.is_none() {
+        Logger::error("script is not parsed");
+    }
+    if !watchers.is_empty() {
+        // changes are watched until Ctrl+C, enter or end of input
+        println!("watching changes, press enter to stop");
+        let _ = std::io::stdin().read_line(&mut String::new());
+        for (canceller, handle) in watchers {
+            canceller.cancel();
+            let _ = handle.join();
+        }
+    }
+    drop(sweeper);
+}
+
+// results of statements are printed, status is printed when block is not applied
+fn print_block(result: BlockResult) -> BlockResult {
+    for statement_result in result.get_results() {
+        println!("{}", statement_result);
+    }
+    if result.get_status() != &BlockStatus::Applied {
+        println!("{}", result.get_status());
+    }
+    result
+}
+
+// backup and restore work with file storage only, serve works with local storage
+fn run_subcommand(uri: &Uri, command: &str, file: &str) {
+    if command == "serve" {
+        let storage: Option<Box<dyn StorageBackend>> = match (uri.is_memory(), uri.is_file()) {
+            (true, _) => Some(Box::new(MemoryStorage::new())),
+            (_, true) => FileStorage::open(uri.get_path()).map(|e| Box::new(e) as _),
+            _ => {
+                Logger::error("serve needs local storage, use --uri mem:// or file://");
+                return;
+            }
+        };
+        match storage {
+            Some(storage) => {
+                let executor = StorageExecutor::with_storage("", storage);
+                listen(executor.with_retention(get_retention()), file);
+            }
+            None => Logger::error(format!("storage is not opened: {}", uri.get_path()).as_str()),
+        }
+        return;
+    }
+    if !uri.is_file() {
+        Logger::error(format!("{} needs file storage, use --uri file://", command).as_str());
+        return;
+    }
+    let result = match command {
+        "backup" => backup(uri.get_path().as_str(), file),
+        _ => restore(file, uri.get_path().as_str()),
+    };
+    match result {
+        Some(channels) => println!("{}: {} channels", command, channels),
+        None => Logger::error(format!("{} is failed", command).as_str()),
+    }
 }
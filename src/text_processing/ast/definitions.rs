@@ -0,0 +1,234 @@
+use crate::environment::logger::Logger;
+use crate::text_processing::ast::types::{DataType, ParamKey, Placeholder, Util};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+// named stored query with typed parameters
+// body is kept as source text and parsed again on every call
+// example: define active_users($min_age: int) = onRead(users)(age >= $min_age)
+pub struct Definition {
+    name: String,
+    params: Vec<(String, String)>,
+    body: String,
+}
+
+impl Definition {
+    pub fn new(name: String, params: Vec<(String, String)>, body: String) -> Definition {
+        Definition { name, params, body }
+    }
+
+    pub fn from_string<T: ToString>(raw_value: T) -> Option<Definition> {
+        use crate::text_processing::ast::types_annotations::DEFINE;
+
+        let raw_value = raw_value.to_string();
+        let rest = Util::strip_keyword(raw_value.as_str(), DEFINE)?;
+        let (name, rest) = match rest.find('(') {
+            Some(i) => (rest[..i].trim(), &rest[i + 1..]),
+            None => return None,
+        };
+        let (raw_params, body) = match rest.find(')') {
+            Some(i) => (&rest[..i], rest[i + 1..].trim_start().strip_prefix('=')?),
+            None => return None,
+        };
+        if Placeholder::from_string(format!(":{}", name)).is_none() || body.trim().is_empty() {
+            Logger::error(format!("definition not correctly in: {}", raw_value).as_str());
+            return None;
+        }
+
+        let mut params: Vec<(String, String)> = vec![];
+        for raw_param in raw_params.split(',').filter(|e| !e.trim().is_empty()) {
+            let (param, raw_type) = match raw_param.find(':') {
+                Some(i) => (
+                    raw_param[..i].trim(),
+                    raw_param[i + 1..].trim().to_lowercase(),
+                ),
+                None => return None,
+            };
+            let param = match Placeholder::from_string(param).map(|e| e.get_key().clone()) {
+                Some(ParamKey::Name(param)) => param,
+                _ => {
+                    Logger::error(format!("parameter not correctly in: {}", raw_param).as_str());
+                    return None;
+                }
+            };
+            if DataType::from_type_default_value(&raw_type).is_none()
+                || params.iter().any(|(e, _)| e == &param)
+            {
+                Logger::error(format!("parameter not correctly in: {}", raw_param).as_str());
+                return None;
+            }
+            params.push((param, raw_type));
+        }
+        Some(Definition::new(
+            name.to_lowercase(),
+            params,
+            body.trim().to_string(),
+        ))
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+    // parameter names (without $) and their types
+    pub fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+    pub fn get_body(&self) -> &String {
+        &self.body
+    }
+}
+
+impl std::fmt::Display for Definition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::text_processing::ast::types_annotations::DEFINE;
+
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|(name, raw_type)| format!("${}: {}", name, raw_type))
+            .collect();
+        write!(
+            f,
+            "{} {}({}) = {}",
+            DEFINE,
+            self.name,
+            params.join(", "),
+            self.body
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// stored queries, saved in local definitions file: one definition per line
+pub struct Definitions {
+    path: Option<PathBuf>,
+    definitions: BTreeMap<String, Definition>,
+}
+
+impl Definitions {
+    // definitions without file, they live only in session
+    pub fn new() -> Definitions {
+        Definitions {
+            path: None,
+            definitions: BTreeMap::new(),
+        }
+    }
+
+    // load definitions from file, file is created at first define
+    pub fn open<T: Into<PathBuf>>(path: T) -> Option<Definitions> {
+        let path: PathBuf = path.into();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                Logger::error(format!("definitions file is not read: {}", e).as_str());
+                return None;
+            }
+        };
+        let mut definitions: BTreeMap<String, Definition> = BTreeMap::new();
+        for line in content.lines().filter(|e| !e.trim().is_empty()) {
+            let definition = Definition::from_string(line)?;
+            definitions.insert(definition.get_name().to_owned(), definition);
+        }
+        Some(Definitions {
+            path: Some(path),
+            definitions,
+        })
+    }
+
+    // later definition with same name replaces previous one
+    // definition is kept in memory until save, so definition of rolled back block is not saved
+    pub fn insert(&mut self, definition: Definition) {
+        self.definitions
+            .insert(definition.get_name().to_owned(), definition);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Definition> {
+        self.definitions.get(name.to_lowercase().as_str())
+    }
+
+    pub fn save(&self) -> Option<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Some(()),
+        };
+        let content: String = self
+            .definitions
+            .values()
+            .map(|e| format!("{}\n", e))
+            .collect();
+        // write next to file and rename, so file is never left half written
+        let tmp_path = path.with_extension("tmp");
+        let result =
+            std::fs::write(&tmp_path, content).and_then(|_| std::fs::rename(&tmp_path, path));
+        if let Err(e) = result {
+            Logger::error(format!("definitions file is not saved: {}", e).as_str());
+            return None;
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::text_processing::ast::definitions::{Definition, Definitions};
+
+    #[test]
+    fn test_definition_from_string() -> Result<(), ()> {
+        let definition = Definition::from_string(
+            "define Active_Users($min_age: INT, $name: text) = onRead(users)(age >= $min_age)",
+        )
+        .unwrap();
+        assert_eq!("active_users", definition.get_name());
+        assert_eq!(
+            &vec![
+                ("min_age".to_string(), "int".to_string()),
+                ("name".to_string(), "text".to_string())
+            ],
+            definition.get_params()
+        );
+        assert_eq!("onRead(users)(age >= $min_age)", definition.get_body());
+        assert_eq!(
+            Some(definition.clone()),
+            Definition::from_string(definition.to_string())
+        );
+
+        assert_eq!(
+            true,
+            Definition::from_string("define all() = onRead(users)").is_some()
+        );
+        assert_eq!(
+            None,
+            Definition::from_string("define a($1: int) = onRead(users)")
+        );
+        assert_eq!(
+            None,
+            Definition::from_string("define a($b: word) = onRead(users)")
+        );
+        assert_eq!(
+            None,
+            Definition::from_string("define a($b: int, $b: int) = onRead(x)")
+        );
+        assert_eq!(None, Definition::from_string("define a($b: int)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_definitions_file() -> Result<(), ()> {
+        let path = std::env::temp_dir().join(format!("qdb_definitions_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut definitions = Definitions::open(&path).unwrap();
+        let definition = Definition::from_string("define all() = onRead(users)").unwrap();
+        definitions.insert(definition.clone());
+        assert_eq!(None, Definitions::open(&path).unwrap().get("all"));
+        assert_eq!(Some(()), definitions.save());
+
+        let definitions = Definitions::open(&path).unwrap();
+        assert_eq!(Some(&definition), definitions.get("ALL"));
+        std::fs::remove_file(&path).unwrap();
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+use crate::environment::logger::Logger;
+use crate::text_processing::ast::types::DataType;
+use crate::text_processing::ast::types_annotations::{
+    ABS, BOOL, CEIL, COALESCE, CONCAT, FLOOR, INT, LENGTH, LOWER, NULL, NULLIF, REAL, ROUND,
+    SUBSTR, TEXT, TRIM, UPPER,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// registry of built-in scalar functions
+// every function has type signature, it's checked at parsing for known argument types
+// example: lower(name), substr(name, 1, 3), coalesce(nick, name)
+pub enum ScalarFunc {
+    Lower,
+    Upper,
+    Length,
+    Trim,
+    Substr,
+    Concat,
+    Abs,
+    Round,
+    Floor,
+    Ceil,
+    Coalesce,
+    Nullif,
+}
+
+const ANY: &[&str] = &[BOOL, INT, REAL, TEXT];
+const NUMERIC: &[&str] = &[INT, REAL];
+
+impl ScalarFunc {
+    pub fn from_string<T: ToString>(val: T) -> Option<ScalarFunc> {
+        match val.to_string().to_lowercase().as_str() {
+            LOWER => Some(ScalarFunc::Lower),
+            UPPER => Some(ScalarFunc::Upper),
+            LENGTH => Some(ScalarFunc::Length),
+            TRIM => Some(ScalarFunc::Trim),
+            SUBSTR => Some(ScalarFunc::Substr),
+            CONCAT => Some(ScalarFunc::Concat),
+            ABS => Some(ScalarFunc::Abs),
+            ROUND => Some(ScalarFunc::Round),
+            FLOOR => Some(ScalarFunc::Floor),
+            CEIL => Some(ScalarFunc::Ceil),
+            COALESCE => Some(ScalarFunc::Coalesce),
+            NULLIF => Some(ScalarFunc::Nullif),
+            _ => None,
+        }
+    }
+
+    // min and max count of arguments, None max is variadic
+    fn get_arity(&self) -> (usize, Option<usize>) {
+        match self {
+            ScalarFunc::Lower
+            | ScalarFunc::Upper
+            | ScalarFunc::Length
+            | ScalarFunc::Trim
+            | ScalarFunc::Abs
+            | ScalarFunc::Floor
+            | ScalarFunc::Ceil => (1, Some(1)),
+            ScalarFunc::Round => (1, Some(2)),
+            ScalarFunc::Substr => (2, Some(3)),
+            ScalarFunc::Nullif => (2, Some(2)),
+            ScalarFunc::Concat | ScalarFunc::Coalesce => (1, None),
+        }
+    }
+
+    // accepted types of argument at position
+    fn get_arg_types(&self, position: usize) -> &'static [&'static str] {
+        match (self, position) {
+            (ScalarFunc::Lower, _)
+            | (ScalarFunc::Upper, _)
+            | (ScalarFunc::Length, _)
+            | (ScalarFunc::Trim, _)
+            | (ScalarFunc::Substr, 0) => &[TEXT],
+            (ScalarFunc::Substr, _) | (ScalarFunc::Round, 1) => &[INT],
+            (ScalarFunc::Abs, _)
+            | (ScalarFunc::Round, _)
+            | (ScalarFunc::Floor, _)
+            | (ScalarFunc::Ceil, _) => NUMERIC,
+            (ScalarFunc::Concat, _) | (ScalarFunc::Coalesce, _) | (ScalarFunc::Nullif, _) => ANY,
+        }
+    }
+
+    // check count and types of arguments and get type of result
+    // None argument type is unknown (field), it's checked at execution
+    // example: length(text) -> int, abs(real) -> real, lower(int) -> error
+    pub fn result_type(
+        &self,
+        arg_types: &[Option<&'static str>],
+    ) -> Result<Option<&'static str>, String> {
+        let (min, max) = self.get_arity();
+        if arg_types.len() < min || matches!(max, Some(max) if arg_types.len() > max) {
+            return Err(format!(
+                "{} does not take {} arguments",
+                self,
+                arg_types.len()
+            ));
+        }
+        let known_types: Vec<&'static str> = arg_types
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|e| *e != NULL)
+            .collect();
+        for (i, arg_type) in arg_types.iter().enumerate() {
+            match arg_type {
+                Some(arg_type)
+                    if *arg_type != NULL && !self.get_arg_types(i).contains(arg_type) =>
+                {
+                    return Err(format!(
+                        "{} expects {} at argument {}, but {} is given",
+                        self,
+                        self.get_arg_types(i).join(" or "),
+                        i + 1,
+                        arg_type
+                    ));
+                }
+                _ => {}
+            }
+        }
+        let result = match self {
+            ScalarFunc::Lower
+            | ScalarFunc::Upper
+            | ScalarFunc::Trim
+            | ScalarFunc::Substr
+            | ScalarFunc::Concat => Some(TEXT),
+            ScalarFunc::Length => Some(INT),
+            ScalarFunc::Abs | ScalarFunc::Round | ScalarFunc::Floor | ScalarFunc::Ceil => {
+                arg_types[0]
+            }
+            ScalarFunc::Coalesce | ScalarFunc::Nullif => {
+                if known_types.iter().any(|e| *e != known_types[0]) {
+                    return Err(format!(
+                        "{} expects arguments of same type, but {} are given",
+                        self,
+                        known_types.join(", ")
+                    ));
+                }
+                known_types.first().copied()
+            }
+        };
+        Ok(result)
+    }
+
+    // compute function, null argument gives null except of coalesce, concat and nullif
+    pub fn apply(&self, args: Vec<DataType>) -> Option<DataType> {
+        use crate::text_processing::ast::types::DataType::*;
+
+        let arg_types: Vec<Option<&'static str>> =
+            args.iter().map(|e| Some(e.get_type_name())).collect();
+        if let Err(mes) = self.result_type(&arg_types) {
+            Logger::error(mes.as_str());
+            return None;
+        }
+        let is_null_safe = matches!(
+            self,
+            ScalarFunc::Coalesce | ScalarFunc::Concat | ScalarFunc::Nullif
+        );
+        if !is_null_safe && args.contains(&Null) {
+            return Some(Null);
+        }
+        let result = match (self, args.as_slice()) {
+            (ScalarFunc::Lower, [Text(val)]) => text(text_content(val).to_lowercase()),
+            (ScalarFunc::Upper, [Text(val)]) => text(text_content(val).to_uppercase()),
+            (ScalarFunc::Trim, [Text(val)]) => text(text_content(val).trim().to_string()),
+            (ScalarFunc::Length, [Text(val)]) => Int(text_content(val).chars().count() as i64),
+            (ScalarFunc::Substr, [Text(val), Int(start), rest @ ..]) => {
+                // start is 1-based, like in sql
+                let start = (*start).max(1) as usize - 1;
+                let len = match rest {
+                    [Int(len)] if *len >= 0 => *len as usize,
+                    [Int(_)] => 0,
+                    _ => usize::MAX,
+                };
+                text(text_content(val).chars().skip(start).take(len).collect())
+            }
+            (ScalarFunc::Concat, args) => text(
+                args.iter()
+                    .map(|e| match e {
+                        Null => "".to_string(),
+                        Text(val) => text_content(val).to_string(),
+                        e => e.to_string(),
+                    })
+                    .collect(),
+            ),
+            (ScalarFunc::Abs, [Int(val)]) => Int(val.checked_abs()?),
+            (ScalarFunc::Abs, [Real(val)]) => Real(val.abs()),
+            (ScalarFunc::Round, [Real(val)]) => Real(val.round()),
+            (ScalarFunc::Round, [Real(val), Int(digits)]) => {
+                let factor = 10f64.powi(*digits as i32);
+                Real((val * factor).round() / factor)
+            }
+            (ScalarFunc::Floor, [Real(val)]) => Real(val.floor()),
+            (ScalarFunc::Ceil, [Real(val)]) => Real(val.ceil()),
+            (ScalarFunc::Round, [Int(val), ..])
+            | (ScalarFunc::Floor, [Int(val)])
+            | (ScalarFunc::Ceil, [Int(val)]) => Int(*val),
+            (ScalarFunc::Coalesce, args) => {
+                args.iter().find(|e| **e != Null).cloned().unwrap_or(Null)
+            }
+            (ScalarFunc::Nullif, [l, r]) if l == r => Null,
+            (ScalarFunc::Nullif, [l, _]) => l.clone(),
+            _ => return None,
+        };
+        Some(result)
+    }
+}
+
+// content of text value without quotes of literal
+fn text_content(val: &str) -> &str {
+    if val.len() > 1 && val.starts_with('\'') && val.ends_with('\'') {
+        return &val[1..val.len() - 1];
+    }
+    val
+}
+
+// text value stored like text literal
+fn text(content: String) -> DataType {
+    DataType::Text(format!("'{}'", content))
+}
+
+impl std::fmt::Display for ScalarFunc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            ScalarFunc::Lower => LOWER,
+            ScalarFunc::Upper => UPPER,
+            ScalarFunc::Length => LENGTH,
+            ScalarFunc::Trim => TRIM,
+            ScalarFunc::Substr => SUBSTR,
+            ScalarFunc::Concat => CONCAT,
+            ScalarFunc::Abs => ABS,
+            ScalarFunc::Round => ROUND,
+            ScalarFunc::Floor => FLOOR,
+            ScalarFunc::Ceil => CEIL,
+            ScalarFunc::Coalesce => COALESCE,
+            ScalarFunc::Nullif => NULLIF,
+        };
+        write!(f, "{}", val)
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::text_processing::ast::functions::ScalarFunc;
+    use crate::text_processing::ast::types::DataType::*;
+    use crate::text_processing::ast::types_annotations::{INT, REAL, TEXT};
+
+    fn text(val: &str) -> crate::text_processing::ast::types::DataType {
+        Text(format!("'{}'", val))
+    }
+
+    #[test]
+    fn test_scalar_func_result_type() -> Result<(), ()> {
+        assert_eq!(Ok(Some(INT)), ScalarFunc::Length.result_type(&[Some(TEXT)]));
+        assert_eq!(Ok(Some(TEXT)), ScalarFunc::Lower.result_type(&[None]));
+        assert_eq!(Ok(Some(REAL)), ScalarFunc::Abs.result_type(&[Some(REAL)]));
+        assert_eq!(
+            Ok(Some(INT)),
+            ScalarFunc::Coalesce.result_type(&[None, Some(INT)])
+        );
+        assert_eq!(true, ScalarFunc::Lower.result_type(&[Some(INT)]).is_err());
+        assert_eq!(true, ScalarFunc::Substr.result_type(&[Some(TEXT)]).is_err());
+        assert_eq!(
+            true,
+            ScalarFunc::Nullif
+                .result_type(&[Some(INT), Some(TEXT)])
+                .is_err()
+        );
+        assert_eq!(None, ScalarFunc::from_string("unknown"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scalar_func_apply() -> Result<(), ()> {
+        assert_eq!(
+            Some(text("BOB")),
+            ScalarFunc::Upper.apply(vec![text("bob")])
+        );
+        assert_eq!(Some(Int(3)), ScalarFunc::Length.apply(vec![text("bob")]));
+        assert_eq!(Some(text("b")), ScalarFunc::Trim.apply(vec![text(" b ")]));
+        assert_eq!(
+            Some(text("ob")),
+            ScalarFunc::Substr.apply(vec![text("bob"), Int(2)])
+        );
+        assert_eq!(
+            Some(text("bo")),
+            ScalarFunc::Substr.apply(vec![text("bob"), Int(1), Int(2)])
+        );
+        assert_eq!(
+            Some(text("id-5")),
+            ScalarFunc::Concat.apply(vec![text("id-"), Int(5), Null])
+        );
+        assert_eq!(Some(Int(2)), ScalarFunc::Abs.apply(vec![Int(-2)]));
+        assert_eq!(
+            Some(Real(2.35)),
+            ScalarFunc::Round.apply(vec![Real(2.345), Int(2)])
+        );
+        assert_eq!(Some(Real(2.0)), ScalarFunc::Floor.apply(vec![Real(2.5)]));
+        assert_eq!(Some(Real(3.0)), ScalarFunc::Ceil.apply(vec![Real(2.5)]));
+        assert_eq!(Some(Int(1)), ScalarFunc::Coalesce.apply(vec![Null, Int(1)]));
+        assert_eq!(Some(Null), ScalarFunc::Nullif.apply(vec![Int(1), Int(1)]));
+        assert_eq!(Some(Int(1)), ScalarFunc::Nullif.apply(vec![Int(1), Int(2)]));
+        assert_eq!(Some(Null), ScalarFunc::Lower.apply(vec![Null]));
+        assert_eq!(None, ScalarFunc::Lower.apply(vec![Int(1)]));
+        Ok(())
+    }
+}
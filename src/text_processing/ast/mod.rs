@@ -1,2 +1,6 @@
+pub mod definitions;
+pub mod functions;
+pub mod params;
+pub mod scope;
 pub mod types;
-mod types_annotations;
+pub mod types_annotations;
@@ -0,0 +1,384 @@
+use crate::environment::logger::Logger;
+use crate::text_processing::ast::types::{
+    BinaryExpr, DataType, DataVar, DefaultExpr, ParamKey, Placeholder, ScalarExpr, UnaryFuncExpr,
+};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+// values for placeholders of parameterised query
+// values are bound to parsed expression, so they are never parsed as DSL
+// example: onRead(users)(age > $1, name == :name)
+pub struct Params {
+    values: BTreeMap<ParamKey, DataType>,
+    // count of values bound by push, positions of them are not mixed with explicit ones
+    pushed: usize,
+}
+
+impl Params {
+    pub fn new() -> Params {
+        Params {
+            values: BTreeMap::new(),
+            pushed: 0,
+        }
+    }
+
+    // bind value to next position: $1, $2, ...
+    // None if some position is bound explicitly, example: --param 1=21:int
+    pub fn push(&mut self, value: DataType) -> Option<()> {
+        if self.get_position_count() != self.pushed {
+            Logger::error("positional value is mixed with value bound to position");
+            return None;
+        }
+        self.pushed += 1;
+        self.values.insert(ParamKey::Position(self.pushed), value);
+        Some(())
+    }
+
+    fn get_position_count(&self) -> usize {
+        self.values
+            .keys()
+            .filter(|e| matches!(e, ParamKey::Position(_)))
+            .count()
+    }
+
+    // bind value to name: :name
+    pub fn insert<T: ToString>(&mut self, name: T, value: DataType) {
+        let name = name.to_string().to_lowercase();
+        self.values.insert(ParamKey::Name(name), value);
+    }
+
+    pub fn get(&self, key: &ParamKey) -> Option<&DataType> {
+        self.values.get(key)
+    }
+    pub fn get_by_name(&self, name: &str) -> Option<&DataType> {
+        self.values.get(&ParamKey::Name(name.to_lowercase()))
+    }
+
+    // names of values bound to name
+    pub fn get_names(&self) -> Vec<String> {
+        self.values
+            .keys()
+            .filter_map(|e| match e {
+                ParamKey::Name(name) => Some(name.to_owned()),
+                ParamKey::Position(_) => None,
+            })
+            .collect()
+    }
+
+    // remove value bound to name
+    pub fn remove(&mut self, name: &str) -> Option<DataType> {
+        self.values.remove(&ParamKey::Name(name.to_lowercase()))
+    }
+
+    // bind value from cli flag, number as name is position
+    // example: name=bob:text, 1=21:int
+    pub fn insert_from_string<T: ToString>(&mut self, raw_param: T) -> Option<()> {
+        use crate::text_processing::ast::types_annotations::{SYMBOL, TEXT};
+
+        let raw_param = raw_param.to_string();
+        let (name, rest) = match raw_param.find('=') {
+            Some(i) => (&raw_param[..i], &raw_param[i + 1..]),
+            None => return None,
+        };
+        let (value, raw_type) = match rest.rfind(':') {
+            Some(i) => (&rest[..i], rest[i + 1..].to_lowercase()),
+            None => return None,
+        };
+        let key = match name.parse::<usize>() {
+            Ok(position) => Placeholder::from_string(format!("${}", position))?,
+            Err(_) => Placeholder::from_string(format!(":{}", name))?,
+        };
+        if raw_type == SYMBOL {
+            return None;
+        }
+        if matches!(key.get_key(), ParamKey::Position(_)) && self.pushed > 0 {
+            Logger::error("value bound to position is mixed with positional values");
+            return None;
+        }
+        // text is taken as is, with its case, and stored like text literal
+        let value = match raw_type.as_str() {
+            TEXT if value.len() > 1 && value.starts_with('\'') && value.ends_with('\'') => {
+                DataType::Text(value.to_string())
+            }
+            TEXT => DataType::Text(format!("'{}'", value)),
+            _ => DataType::from_string(value.to_string(), raw_type)?,
+        };
+        self.values.insert(key.get_key().clone(), value);
+        Some(())
+    }
+
+    // replace placeholders in filter, having and statements by bound values
+    // type of value is checked against declared type of statement
+    // and against type of literal on other side of comparison
+    pub fn bind(&self, expr: &UnaryFuncExpr) -> Option<UnaryFuncExpr> {
+        let bind_exprs = |exprs: &Option<Vec<BinaryExpr>>| -> Option<Option<Vec<BinaryExpr>>> {
+            match exprs {
+                Some(exprs) => Some(Some(
+                    exprs
+                        .iter()
+                        .map(|e| self.bind_binary_expr(e))
+                        .collect::<Option<Vec<BinaryExpr>>>()?,
+                )),
+                None => Some(None),
+            }
+        };
+        let binary_exprs = bind_exprs(expr.get_binary_exprs())?;
+        let having = bind_exprs(expr.get_having())?;
+        let vars = match expr.get_vars() {
+            Some(vars) => Some(
+                vars.iter()
+                    .map(|e| {
+                        let value = self.bind_term(e.get_value(), None)?;
+                        let default = match e.get_default() {
+                            Some(DefaultExpr::Expr(expr)) => {
+                                Some(DefaultExpr::Expr(self.bind_scalar_expr(expr, None)?))
+                            }
+                            default => default.to_owned(),
+                        };
+                        let var = DataVar::new(e.get_name().to_owned(), value);
+                        Some(var.with_default(default))
+                    })
+                    .collect::<Option<Vec<DataVar>>>()?,
+            ),
+            None => None,
+        };
+        Some(
+            expr.clone()
+                .with_binary_exprs(binary_exprs)
+                .with_having(having)
+                .with_vars(vars),
+        )
+    }
+
+    fn bind_binary_expr(&self, expr: &BinaryExpr) -> Option<BinaryExpr> {
+        // type of literal, fields and nulls can be compared with any type
+        fn literal_type(term: &ScalarExpr) -> Option<&'static str> {
+            use crate::text_processing::ast::types_annotations::NULL;
+
+            term.get_type_name().filter(|e| *e != NULL)
+        }
+        let lterm = self.bind_scalar_expr(expr.get_lterm(), literal_type(expr.get_rterm()))?;
+        let rterm = self.bind_scalar_expr(expr.get_rterm(), literal_type(&lterm))?;
+        Some(BinaryExpr::from_scalar_exprs(
+            lterm,
+            rterm,
+            *expr.get_operator(),
+        ))
+    }
+
+    // bind placeholders inside of expression, arguments of functions are checked after binding
+    // example: lower($1) with $1 = 18 -> error
+    fn bind_scalar_expr(
+        &self,
+        expr: &ScalarExpr,
+        expected_type: Option<&str>,
+    ) -> Option<ScalarExpr> {
+        let bound = match expr {
+            ScalarExpr::Value(term) => ScalarExpr::Value(self.bind_term(term, expected_type)?),
+            ScalarExpr::Arithmetic(lterm, operator, rterm) => ScalarExpr::Arithmetic(
+                Box::new(self.bind_scalar_expr(lterm, None)?),
+                *operator,
+                Box::new(self.bind_scalar_expr(rterm, None)?),
+            ),
+            ScalarExpr::Aggregate(func, arg) => match arg {
+                Some(arg) => {
+                    ScalarExpr::Aggregate(*func, Some(Box::new(self.bind_scalar_expr(arg, None)?)))
+                }
+                None => ScalarExpr::Aggregate(*func, None),
+            },
+            ScalarExpr::Call(func, args) => ScalarExpr::Call(
+                *func,
+                args.iter()
+                    .map(|e| self.bind_scalar_expr(e, None))
+                    .collect::<Option<Vec<ScalarExpr>>>()?,
+            ),
+        };
+        bound.check()?;
+        Some(bound)
+    }
+
+    fn bind_term(&self, term: &DataType, expected_type: Option<&str>) -> Option<DataType> {
+        let placeholder = match term {
+            DataType::Param(placeholder) => placeholder,
+            term => return Some(term.clone()),
+        };
+        let value = match self.get(placeholder.get_key()) {
+            Some(value) => value,
+            None => {
+                Logger::error(format!("param is not bound: {}", placeholder).as_str());
+                return None;
+            }
+        };
+        if let DataType::Symbol(_) | DataType::Param(_) = value {
+            Logger::error(format!("param can not be bound to field: {}", placeholder).as_str());
+            return None;
+        }
+        let expected_type = placeholder
+            .get_type_name()
+            .as_ref()
+            .map(|e| e.as_str())
+            .or(expected_type);
+        match expected_type {
+            Some(expected_type)
+                if *value != DataType::Null && value.get_type_name() != expected_type =>
+            {
+                Logger::error(
+                    format!(
+                        "param {} expects {} but {} is bound",
+                        placeholder,
+                        expected_type,
+                        value.get_type_name()
+                    )
+                    .as_str(),
+                );
+                None
+            }
+            _ => Some(value.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::text_processing::ast::params::Params;
+    use crate::text_processing::ast::types::{
+        BinaryExpr, ChannelName, CompareOperator, DataType, DataVar, FuncType, ParamKey,
+        Placeholder, ScalarExpr, UnaryFuncExpr,
+    };
+
+    fn param(raw_value: &str) -> DataType {
+        DataType::Param(Placeholder::from_string(raw_value).unwrap())
+    }
+
+    #[test]
+    fn test_insert_from_string() -> Result<(), ()> {
+        let mut params = Params::new();
+        assert_eq!(Some(()), params.insert_from_string("1=21:int"));
+        assert_eq!(
+            Some(()),
+            params.insert_from_string("name=x'); onDelete(users:text")
+        );
+        assert_eq!(Some(()), params.insert_from_string("quoted='Bob':text"));
+        assert_eq!(None, params.insert_from_string("age=abc:int"));
+        assert_eq!(None, params.insert_from_string("field=age:symbol"));
+        assert_eq!(None, params.insert_from_string("age"));
+
+        assert_eq!(Some(&DataType::Int(21)), params.get(&ParamKey::Position(1)));
+        assert_eq!(
+            Some(&DataType::Text("'x'); onDelete(users'".to_string())),
+            params.get(&ParamKey::Name("name".to_string()))
+        );
+        assert_eq!(
+            Some(&DataType::Text("'Bob'".to_string())),
+            params.get(&ParamKey::Name("quoted".to_string()))
+        );
+
+        // explicit positions are not mixed with pushed values
+        assert_eq!(None, params.push(DataType::Int(1)));
+        let mut params = Params::new();
+        assert_eq!(Some(()), params.push(DataType::Int(1)));
+        assert_eq!(None, params.insert_from_string("2=21:int"));
+        assert_eq!(Some(()), params.push(DataType::Int(2)));
+        assert_eq!(Some(&DataType::Int(2)), params.get(&ParamKey::Position(2)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind() -> Result<(), ()> {
+        let expr = UnaryFuncExpr::new(
+            FuncType::OnUpdate,
+            vec![ChannelName::from_string("users").unwrap()],
+            Some(vec![BinaryExpr::new(
+                DataType::Symbol("id".to_string()),
+                param("$1"),
+                CompareOperator::Eq,
+            )]),
+            Some(vec![DataVar::new(
+                "name".to_string(),
+                DataType::Param(Placeholder::from_string(":name").unwrap().with_type("text")),
+            )]),
+        );
+
+        let mut params = Params::new();
+        params.push(DataType::Int(7));
+        params.insert("name", DataType::Text("'bob'".to_string()));
+        let bound = params.bind(&expr).unwrap();
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Int(7)),
+            bound.get_binary_exprs().as_ref().unwrap()[0].get_rterm()
+        );
+        assert_eq!(
+            &DataType::Text("'bob'".to_string()),
+            bound.get_vars().as_ref().unwrap()[0].get_value()
+        );
+
+        // statement declares text
+        let mut params = Params::new();
+        params.push(DataType::Int(7));
+        params.insert("name", DataType::Int(1));
+        assert_eq!(true, params.bind(&expr).is_none());
+
+        // not bound
+        assert_eq!(true, Params::new().bind(&expr).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_literal_type() -> Result<(), ()> {
+        let expr = UnaryFuncExpr::new(
+            FuncType::OnRead,
+            vec![ChannelName::from_string("users").unwrap()],
+            Some(vec![BinaryExpr::new(
+                DataType::Int(2),
+                param("$1"),
+                CompareOperator::Lt,
+            )]),
+            None,
+        );
+        let mut params = Params::new();
+        params.push(DataType::Real(2.5));
+        assert_eq!(true, params.bind(&expr).is_none());
+
+        let mut params = Params::new();
+        params.push(DataType::Symbol("age".to_string()));
+        assert_eq!(true, params.bind(&expr).is_none());
+
+        let mut params = Params::new();
+        params.push(DataType::Int(3));
+        assert_eq!(true, params.bind(&expr).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_function_args() -> Result<(), ()> {
+        use crate::text_processing::ast::functions::ScalarFunc;
+
+        let expr = UnaryFuncExpr::new(
+            FuncType::OnRead,
+            vec![ChannelName::from_string("users").unwrap()],
+            Some(vec![BinaryExpr::from_scalar_exprs(
+                ScalarExpr::Value(DataType::Symbol("name".to_string())),
+                ScalarExpr::Call(ScalarFunc::Lower, vec![ScalarExpr::Value(param("$1"))]),
+                CompareOperator::Eq,
+            )]),
+            None,
+        );
+        let mut params = Params::new();
+        params.push(DataType::Int(18));
+        assert_eq!(true, params.bind(&expr).is_none());
+
+        let mut params = Params::new();
+        params.push(DataType::Text("'BOB'".to_string()));
+        let bound = params.bind(&expr).unwrap();
+        let record = [("name".to_string(), DataType::Text("'bob'".to_string()))]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(
+            Some(true),
+            bound.get_binary_exprs().as_ref().unwrap()[0].eval(&record)
+        );
+        Ok(())
+    }
+}
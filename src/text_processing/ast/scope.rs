@@ -0,0 +1,145 @@
+use crate::environment::logger::Logger;
+use crate::text_processing::ast::definitions::{Definition, Definitions};
+use crate::text_processing::ast::params::Params;
+use crate::text_processing::ast::types::{ChannelName, DataType};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, PartialEq)]
+// value of let binding: literal for filters and statements or list of channels
+// example: let min_age: int = 18; let targets = (users, mynode.orders);
+pub enum LetValue {
+    Value(DataType),
+    Channels(Vec<ChannelName>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// let bindings of one script or REPL session and stored queries
+// literals are bound as named params, so they are used as :name
+// example: let min_age = 18; onRead(users)(age >= :min_age)
+pub struct Scope {
+    params: Params,
+    // names of params from cli, let can't redefine them
+    fixed: BTreeSet<String>,
+    channels: BTreeMap<String, Vec<ChannelName>>,
+    // node path of uri, bare channel names are resolved against it
+    node_path: String,
+    definitions: Definitions,
+}
+
+impl Scope {
+    pub fn new() -> Scope {
+        Scope::from_params(Params::new())
+    }
+
+    // scope with params from cli
+    pub fn from_params(params: Params) -> Scope {
+        Scope {
+            fixed: params.get_names().into_iter().collect(),
+            params,
+            channels: BTreeMap::new(),
+            node_path: "".to_string(),
+            definitions: Definitions::new(),
+        }
+    }
+
+    pub fn with_node_path<T: ToString>(mut self, node_path: T) -> Scope {
+        self.node_path = node_path.to_string();
+        self
+    }
+
+    // set stored queries, usually loaded from definitions file
+    pub fn with_definitions(mut self, definitions: Definitions) -> Scope {
+        self.definitions = definitions;
+        self
+    }
+
+    // later let with same name replaces previous value,
+    // param from cli is not replaced, so script can't change value given by user
+    pub fn insert<T: ToString>(&mut self, name: T, value: LetValue) -> Option<()> {
+        let name = name.to_string().to_lowercase();
+        if self.fixed.contains(&name) {
+            Logger::error(format!("let can't redefine param :{} given by --param", name).as_str());
+            return None;
+        }
+        self.params.remove(&name);
+        self.channels.remove(&name);
+        match value {
+            LetValue::Value(value) => self.params.insert(name, value),
+            LetValue::Channels(channels) => {
+                self.channels.insert(name, channels);
+            }
+        }
+        Some(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<LetValue> {
+        if let Some(channels) = self.channels.get(name) {
+            return Some(LetValue::Channels(channels.to_owned()));
+        }
+        self.params
+            .get_by_name(name)
+            .map(|e| LetValue::Value(e.to_owned()))
+    }
+    pub fn get_params(&self) -> &Params {
+        &self.params
+    }
+    pub fn get_channels(&self, name: &str) -> Option<&Vec<ChannelName>> {
+        self.channels.get(name)
+    }
+    pub fn get_node_path(&self) -> &str {
+        &self.node_path
+    }
+
+    // store query, it's saved to definitions file by save
+    pub fn define(&mut self, definition: Definition) {
+        self.definitions.insert(definition)
+    }
+    // save stored queries to definitions file if scope has one
+    pub fn save(&self) -> Option<()> {
+        self.definitions.save()
+    }
+    pub fn get_definition(&self, name: &str) -> Option<&Definition> {
+        self.definitions.get(name)
+    }
+}
+
+#[cfg(test)]
+// test module
+mod test {
+    use crate::text_processing::ast::params::Params;
+    use crate::text_processing::ast::scope::{LetValue, Scope};
+    use crate::text_processing::ast::types::{ChannelName, DataType};
+
+    #[test]
+    fn test_scope_insert() -> Result<(), ()> {
+        let mut scope = Scope::new();
+        scope.insert("Target", LetValue::Value(DataType::Int(1)));
+        assert_eq!(Some(LetValue::Value(DataType::Int(1))), scope.get("target"));
+
+        let channels = vec![ChannelName::from_string("users").unwrap()];
+        scope.insert("target", LetValue::Channels(channels.clone()));
+        assert_eq!(Some(&channels), scope.get_channels("target"));
+        assert_eq!(None, scope.get_params().get_by_name("target"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scope_insert_param() -> Result<(), ()> {
+        let mut params = Params::new();
+        params.insert_from_string("min_age=18:int");
+        let mut scope = Scope::from_params(params);
+        assert_eq!(
+            None,
+            scope.insert("MIN_AGE", LetValue::Value(DataType::Int(1)))
+        );
+        assert_eq!(
+            Some(LetValue::Value(DataType::Int(18))),
+            scope.get("min_age")
+        );
+        assert_eq!(
+            Some(()),
+            scope.insert("age", LetValue::Value(DataType::Int(1)))
+        );
+        Ok(())
+    }
+}
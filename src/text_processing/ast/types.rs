@@ -1,8 +1,16 @@
 use crate::environment::logger::Logger;
-use crate::text_processing::ast::types::FuncType::{OnCreate, OnDelete, OnRead, OnUpdate};
+use crate::text_processing::ast::functions::ScalarFunc;
+use crate::text_processing::ast::types::FuncType::{
+    OnCreate, OnDelete, OnIndex, OnRead, OnUpdate, OnUpsert, OnWatch,
+};
 use regex::{Match, Regex};
+use std::collections::BTreeMap;
 
-#[derive(Debug, PartialEq, PartialOrd)]
+// record of channel: field name -> value
+// example: { a: Int(2), b: Text('x') }
+pub type Record = BTreeMap<String, DataType>;
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 // data types
 // example: 23 : int
 pub enum DataType {
@@ -18,6 +26,8 @@ pub enum DataType {
     Text(String),
     // shadow value
     Symbol(String),
+    // placeholder, value is bound before execution
+    Param(Placeholder),
 }
 
 impl DataType {
@@ -42,7 +52,7 @@ impl DataType {
 
     pub fn from_string<T: ToString>(raw_value: T, raw_type: T) -> Option<DataType> {
         use super::types::DataType::*;
-        use super::types_annotations::{BOOL, INT, NULL, REAL, SYMBOL, TEXT};
+        use super::types_annotations::{BOOL, INT, NULL, PARAM, REAL, SYMBOL, TEXT};
 
         let raw_value = raw_value.to_string().to_lowercase();
         let raw_type = raw_type.to_string().to_lowercase();
@@ -54,6 +64,7 @@ impl DataType {
             REAL => Some(Real(Self::from::<f64>(raw_value)?)),
             TEXT => Some(Text(raw_value)),
             SYMBOL => Some(Symbol(raw_value)),
+            PARAM => Some(Param(Placeholder::from_string(raw_value)?)),
             _ => None,
         }
     }
@@ -75,14 +86,193 @@ impl DataType {
     }
 }
 
-#[derive(Debug)]
+impl DataType {
+    // type annotation of value
+    // example: Int(2) -> int
+    pub fn get_type_name(&self) -> &'static str {
+        use super::types::DataType::*;
+        use super::types_annotations::{BOOL, INT, NULL, PARAM, REAL, SYMBOL, TEXT};
+
+        match self {
+            Null => NULL,
+            Bool(_) => BOOL,
+            Int(_) => INT,
+            Real(_) => REAL,
+            Text(_) => TEXT,
+            Symbol(_) => SYMBOL,
+            Param(_) => PARAM,
+        }
+    }
+}
+
+impl std::fmt::Display for DataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use super::types::DataType::*;
+
+        match self {
+            Null => write!(f, "null"),
+            Bool(val) => write!(f, "{}", val),
+            Int(val) => write!(f, "{}", val),
+            Real(val) => write!(f, "{:?}", val),
+            Text(val) => write!(f, "{}", val),
+            Symbol(val) => write!(f, "{}", val),
+            Param(val) => write!(f, "{}", val),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+// key of placeholder: position or name
+// example: $1 -> Position(1), :name -> Name(name)
+pub enum ParamKey {
+    Position(usize),
+    Name(String),
+}
+
+impl std::fmt::Display for ParamKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamKey::Position(position) => write!(f, "${}", position),
+            ParamKey::Name(name) => write!(f, ":{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+// placeholder of literal in filters and statements
+// type is declared when placeholder is used as value of statement
+// example: age == $1, name: text = :name, age >= $min_age
+pub struct Placeholder {
+    key: ParamKey,
+    type_name: Option<String>,
+}
+
+impl Placeholder {
+    pub fn new(key: ParamKey, type_name: Option<String>) -> Placeholder {
+        Placeholder { key, type_name }
+    }
+
+    pub fn from_string<T: ToString>(raw_value: T) -> Option<Placeholder> {
+        let raw_value = raw_value.to_string();
+        let key = if let Some(position) = raw_value.strip_prefix('$') {
+            match position.parse::<usize>() {
+                Ok(position) if position > 0 => ParamKey::Position(position),
+                // parameter of stored query: $min_age
+                Err(_) if Placeholder::from_string(format!(":{}", position)).is_some() => {
+                    ParamKey::Name(position.to_lowercase())
+                }
+                _ => return None,
+            }
+        } else if let Some(name) = raw_value.strip_prefix(':') {
+            if !Util::is_single_word(name.to_string())
+                || !name.chars().all(|e| e.is_alphanumeric() || e == '_')
+            {
+                return None;
+            }
+            ParamKey::Name(name.to_lowercase())
+        } else {
+            return None;
+        };
+        Some(Placeholder::new(key, None))
+    }
+
+    // set type declared by statement
+    pub fn with_type<T: ToString>(mut self, type_name: T) -> Placeholder {
+        self.type_name = Some(type_name.to_string().to_lowercase());
+        self
+    }
+    pub fn get_key(&self) -> &ParamKey {
+        &self.key
+    }
+    pub fn get_type_name(&self) -> &Option<String> {
+        &self.type_name
+    }
+}
+
+impl std::fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// value computed at execution, used for defaults of schema and for statements
+// example: created: int = now(), key: text = uuid(), id: int = nextval(users_id)
+pub enum DefaultExpr {
+    // current time as epoch milliseconds
+    Now,
+    // random uuid v4
+    Uuid,
+    // next value of named sequence, sequence starts from 1
+    NextVal(String),
+    // expression over fields of record, example: concat(first, ' ', last)
+    Expr(ScalarExpr),
+}
+
+impl DefaultExpr {
+    // name of function and arguments
+    // example: (nextval, [users_id]) -> NextVal(users_id)
+    pub fn from_string<T: ToString>(name: T, args: &[String]) -> Option<DefaultExpr> {
+        use super::types_annotations::{NEXTVAL, NOW, UUID};
+
+        match (name.to_string().to_lowercase().as_str(), args) {
+            (NOW, []) => Some(DefaultExpr::Now),
+            (UUID, []) => Some(DefaultExpr::Uuid),
+            (NEXTVAL, [sequence]) if Util::is_single_word(sequence.to_owned()) => {
+                Some(DefaultExpr::NextVal(sequence.to_lowercase()))
+            }
+            _ => None,
+        }
+    }
+
+    // type of computed value, None if it is known only at execution
+    pub fn get_type_name(&self) -> Option<&'static str> {
+        use super::types_annotations::{INT, TEXT};
+
+        match self {
+            DefaultExpr::Now | DefaultExpr::NextVal(_) => Some(INT),
+            DefaultExpr::Uuid => Some(TEXT),
+            DefaultExpr::Expr(expr) => expr.get_type_name(),
+        }
+    }
+}
+
+impl std::fmt::Display for DefaultExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use super::types_annotations::{NEXTVAL, NOW, UUID};
+
+        match self {
+            DefaultExpr::Now => write!(f, "{}()", NOW),
+            DefaultExpr::Uuid => write!(f, "{}()", UUID),
+            DefaultExpr::NextVal(sequence) => write!(f, "{}({})", NEXTVAL, sequence),
+            DefaultExpr::Expr(expr) => write!(f, "{}", expr),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 // data variable - composition from data types
+// value is default value of type when variable has computed default
 // example: <variable name> = 23 : int
-pub struct DataVar(String, DataType);
+pub struct DataVar(String, DataType, Option<DefaultExpr>);
 
 impl DataVar {
     pub fn new(var_name: String, data_type: DataType) -> DataVar {
-        DataVar(var_name, data_type)
+        DataVar(var_name, data_type, None)
+    }
+    // set value computed at execution
+    pub fn with_default(mut self, default: Option<DefaultExpr>) -> DataVar {
+        self.2 = default;
+        self
+    }
+    pub fn get_name(&self) -> &String {
+        &self.0
+    }
+    pub fn get_value(&self) -> &DataType {
+        &self.1
+    }
+    pub fn get_default(&self) -> &Option<DefaultExpr> {
+        &self.2
     }
 }
 
@@ -92,7 +282,66 @@ impl std::fmt::Display for DataVar {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+// channel name, can be qualified by namespace (node path)
+// example: orders, mynode.orders
+pub struct ChannelName {
+    namespace: Option<String>,
+    name: String,
+}
+
+impl ChannelName {
+    pub fn new(namespace: Option<String>, name: String) -> ChannelName {
+        ChannelName { namespace, name }
+    }
+
+    pub fn from_string<T: ToString>(raw_value: T) -> Option<ChannelName> {
+        let raw_value = raw_value.to_string().to_lowercase();
+        let parts: Vec<&str> = raw_value.split('.').collect();
+        let is_name = |e: &&str| {
+            Util::is_single_word(e.to_string())
+                && e.chars().all(|c| c.is_alphanumeric() || c == '_')
+        };
+        if !parts.iter().all(is_name) {
+            return None;
+        }
+        match parts[..] {
+            [name] => Some(ChannelName::new(None, name.to_string())),
+            [namespace, name] => Some(ChannelName::new(
+                Some(namespace.to_string()),
+                name.to_string(),
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn get_namespace(&self) -> &Option<String> {
+        &self.namespace
+    }
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    // bare name resolves against node path, qualified name stays as is
+    // example: orders + mynode -> mynode.orders
+    pub fn resolve(&self, node_path: &str) -> ChannelName {
+        if self.namespace.is_some() || node_path.is_empty() {
+            return self.clone();
+        }
+        ChannelName::new(Some(node_path.to_lowercase()), self.name.to_owned())
+    }
+}
+
+impl std::fmt::Display for ChannelName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{}.{}", namespace, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 // function types, it's can use for Expr struct
 // example: onCreate
 pub enum FuncType {
@@ -104,12 +353,18 @@ pub enum FuncType {
     OnUpdate,
     // onDelete
     OnDelete,
+    // onUpsert: onUpdate which creates record if filter matches nothing
+    OnUpsert,
+    // onWatch: stream of changes of channel
+    OnWatch,
+    // onIndex: ordered index over one field of channel
+    OnIndex,
 }
 
 impl FuncType {
     pub fn from_string(func_type: String) -> Option<FuncType> {
         use crate::text_processing::ast::types_annotations::{
-            ONCREATE, ONDELETE, ONREAD, ONUPDATE,
+            ONCREATE, ONDELETE, ONINDEX, ONREAD, ONUPDATE, ONUPSERT, ONWATCH,
         };
         let raw_type = func_type.to_string().to_lowercase();
 
@@ -122,27 +377,45 @@ impl FuncType {
             ONUPDATE => Some(OnUpdate),
             // for delete node from channel
             ONDELETE => Some(OnDelete),
+            // for update node in channel or create it
+            ONUPSERT => Some(OnUpsert),
+            // for subscribe on changes of channel
+            ONWATCH => Some(OnWatch),
+            // for index field of channel
+            ONINDEX => Some(OnIndex),
             _ => None,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 // expressions for execution operation.
 // It's composition from function types and data variable
 // (look enum FuncType and struct DataVar )
 // example: Expr equal to onCreate(a : int, b : bool)
 pub struct UnaryFuncExpr {
     func_type: FuncType,
-    channel_names: Vec<DataType>,
+    channel_names: Vec<ChannelName>,
     binary_exprs: Option<Vec<BinaryExpr>>,
     vars: Option<Vec<DataVar>>,
+    projection: Option<Vec<Projection>>,
+    joins: Option<Vec<Join>>,
+    group_by: Option<Vec<ScalarExpr>>,
+    having: Option<Vec<BinaryExpr>>,
+    order_by: Option<Vec<OrderBy>>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    returning: Option<Vec<Projection>>,
+    explain: Option<ExplainFormat>,
+    expiry: Option<Expiry>,
+    as_of: Option<AsOf>,
+    history: bool,
 }
 
 impl UnaryFuncExpr {
     pub fn new(
         func_type: FuncType,
-        channel_names: Vec<DataType>,
+        channel_names: Vec<ChannelName>,
         binary_exprs: Option<Vec<BinaryExpr>>,
         vars: Option<Vec<DataVar>>,
     ) -> UnaryFuncExpr {
@@ -151,12 +424,88 @@ impl UnaryFuncExpr {
             channel_names,
             binary_exprs,
             vars,
+            projection: None,
+            joins: None,
+            group_by: None,
+            having: None,
+            order_by: None,
+            limit: None,
+            offset: None,
+            returning: None,
+            explain: None,
+            expiry: None,
+            as_of: None,
+            history: false,
         }
     }
+    // set filter, used to replace placeholders by bound values
+    pub fn with_binary_exprs(mut self, binary_exprs: Option<Vec<BinaryExpr>>) -> UnaryFuncExpr {
+        self.binary_exprs = binary_exprs;
+        self
+    }
+    // set statements, used to replace placeholders by bound values
+    pub fn with_vars(mut self, vars: Option<Vec<DataVar>>) -> UnaryFuncExpr {
+        self.vars = vars;
+        self
+    }
+    // set fields selected by onRead, None selects every field
+    pub fn with_projection(mut self, projection: Option<Vec<Projection>>) -> UnaryFuncExpr {
+        self.projection = projection;
+        self
+    }
+    pub fn with_joins(mut self, joins: Option<Vec<Join>>) -> UnaryFuncExpr {
+        self.joins = joins;
+        self
+    }
+    pub fn with_group_by(mut self, group_by: Option<Vec<ScalarExpr>>) -> UnaryFuncExpr {
+        self.group_by = group_by;
+        self
+    }
+    pub fn with_having(mut self, having: Option<Vec<BinaryExpr>>) -> UnaryFuncExpr {
+        self.having = having;
+        self
+    }
+    pub fn with_order_by(mut self, order_by: Option<Vec<OrderBy>>) -> UnaryFuncExpr {
+        self.order_by = order_by;
+        self
+    }
+    pub fn with_limit(mut self, limit: Option<usize>) -> UnaryFuncExpr {
+        self.limit = limit;
+        self
+    }
+    pub fn with_offset(mut self, offset: Option<usize>) -> UnaryFuncExpr {
+        self.offset = offset;
+        self
+    }
+    // set fields of changed records returned by mutation
+    pub fn with_returning(mut self, returning: Option<Vec<Projection>>) -> UnaryFuncExpr {
+        self.returning = returning;
+        self
+    }
+    // set expiry of records of channel created by onCreate
+    pub fn with_expiry(mut self, expiry: Option<Expiry>) -> UnaryFuncExpr {
+        self.expiry = expiry;
+        self
+    }
+    // set version of records read by onRead, None reads current records
+    pub fn with_as_of(mut self, as_of: Option<AsOf>) -> UnaryFuncExpr {
+        self.as_of = as_of;
+        self
+    }
+    // onRead lists every version of records instead of records
+    pub fn with_history(mut self, history: bool) -> UnaryFuncExpr {
+        self.history = history;
+        self
+    }
+    // statement is explained instead of executed
+    pub fn with_explain(mut self, explain: Option<ExplainFormat>) -> UnaryFuncExpr {
+        self.explain = explain;
+        self
+    }
     pub fn get_func_type(&self) -> &FuncType {
         &self.func_type
     }
-    pub fn get_channel_names(&self) -> &Vec<DataType> {
+    pub fn get_channel_names(&self) -> &Vec<ChannelName> {
         &self.channel_names
     }
     pub fn get_binary_exprs(&self) -> &Option<Vec<BinaryExpr>> {
@@ -165,15 +514,134 @@ impl UnaryFuncExpr {
     pub fn get_vars(&self) -> &Option<Vec<DataVar>> {
         &self.vars
     }
+    pub fn get_projection(&self) -> &Option<Vec<Projection>> {
+        &self.projection
+    }
+    pub fn get_joins(&self) -> &Option<Vec<Join>> {
+        &self.joins
+    }
+    pub fn get_group_by(&self) -> &Option<Vec<ScalarExpr>> {
+        &self.group_by
+    }
+    pub fn get_having(&self) -> &Option<Vec<BinaryExpr>> {
+        &self.having
+    }
+    // true if onRead result is computed over groups of records
+    pub fn is_aggregate(&self) -> bool {
+        let has_aggregate = match &self.projection {
+            Some(projection) => projection.iter().any(|e| e.get_expr().has_aggregate()),
+            None => false,
+        };
+        has_aggregate || self.group_by.is_some()
+    }
+    pub fn get_order_by(&self) -> &Option<Vec<OrderBy>> {
+        &self.order_by
+    }
+    pub fn get_limit(&self) -> Option<usize> {
+        self.limit
+    }
+    pub fn get_offset(&self) -> Option<usize> {
+        self.offset
+    }
+    pub fn get_returning(&self) -> &Option<Vec<Projection>> {
+        &self.returning
+    }
+    pub fn get_explain(&self) -> Option<ExplainFormat> {
+        self.explain
+    }
+    pub fn get_expiry(&self) -> &Option<Expiry> {
+        &self.expiry
+    }
+    pub fn get_as_of(&self) -> &Option<AsOf> {
+        &self.as_of
+    }
+    pub fn is_history(&self) -> bool {
+        self.history
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// version of records read by onRead
+// example: as of version 12 -> Version(12), as of timestamp 1718000000000 -> Timestamp(1718000000000)
+pub enum AsOf {
+    // version of committed changes, every statement outside of transaction commits one
+    Version(u64),
+    // milliseconds since epoch like now(), last version committed before it is read
+    Timestamp(i64),
 }
 
-#[derive(Debug, PartialOrd, PartialEq)]
+impl std::fmt::Display for AsOf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsOf::Version(version) => write!(f, "version {}", version),
+            AsOf::Timestamp(timestamp) => write!(f, "timestamp {}", timestamp),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// expiry of records declared by first onCreate of channel
+// example: ttl(3600) -> Ttl(3600), ttl(expires_at) -> Field(expires_at)
+pub enum Expiry {
+    // record expires in seconds after it's created
+    Ttl(u64),
+    // record expires at time of field, milliseconds since epoch like now()
+    Field(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// output of plan of explained statement
+// example: explain onRead(users) -> Text, explain json onRead(users) -> Json
+pub enum ExplainFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// how statements of block are applied
+pub enum BlockKind {
+    // statements outside of transaction, each one is applied on its own
+    AutoCommit,
+    // begin ... commit: all statements are applied or none of them
+    Commit,
+    // begin ... rollback: statements are executed and then discarded
+    Rollback,
+}
+
+#[derive(Debug, Clone)]
+// statements of script executed together
+// example: begin; onUpdate(a)(x == 1)(y: int = 2); onDelete(b); commit
+pub struct Block {
+    kind: BlockKind,
+    exprs: Vec<UnaryFuncExpr>,
+}
+
+impl Block {
+    pub fn new(kind: BlockKind, exprs: Vec<UnaryFuncExpr>) -> Block {
+        Block { kind, exprs }
+    }
+    pub fn get_kind(&self) -> BlockKind {
+        self.kind
+    }
+    pub fn get_exprs(&self) -> &Vec<UnaryFuncExpr> {
+        &self.exprs
+    }
+    pub fn into_exprs(self) -> Vec<UnaryFuncExpr> {
+        self.exprs
+    }
+}
+
+#[derive(Debug, Clone, PartialOrd, PartialEq)]
 pub enum ArgumentGroup {
     FuncGroup(String),
     /* unused: */
     //ChannelsGroup(String),
     //ExpressionsGroup(String),
     //StatementsGroup(String),
+    // clause name after function groups, example: limit(10)
+    ClauseGroup(String),
+    // word of read modifier at end of statement, example: as of version 12
+    ModifierGroup(String),
     OtherGroup(String),
     None,
 }
@@ -181,12 +649,22 @@ pub enum ArgumentGroup {
 impl ArgumentGroup {
     pub fn from_string(val: &String) -> ArgumentGroup {
         use crate::text_processing::ast::types_annotations::{
-            ONCREATE, ONDELETE, ONREAD, ONUPDATE,
+            AS, GROUPBY, HAVING, HISTORY, JOIN, LEFTJOIN, LIMIT, OF, OFFSET, ONCREATE, ONDELETE,
+            ONINDEX, ONREAD, ONUPDATE, ONUPSERT, ONWATCH, ORDERBY, RETURNING, TIMESTAMP, TTL,
+            VERSION,
         };
         let val = val.to_lowercase();
         let val = val.as_str();
         match val {
-            ONCREATE | ONREAD | ONUPDATE | ONDELETE => ArgumentGroup::FuncGroup(val.to_string()),
+            ONCREATE | ONREAD | ONUPDATE | ONDELETE | ONUPSERT | ONWATCH | ONINDEX => {
+                ArgumentGroup::FuncGroup(val.to_string())
+            }
+            ORDERBY | LIMIT | OFFSET | GROUPBY | HAVING | JOIN | LEFTJOIN | RETURNING | TTL => {
+                ArgumentGroup::ClauseGroup(val.to_string())
+            }
+            AS | OF | VERSION | TIMESTAMP | HISTORY => {
+                ArgumentGroup::ModifierGroup(val.to_string())
+            }
             _ => ArgumentGroup::OtherGroup(val.to_string()),
         }
     }
@@ -194,10 +672,14 @@ impl ArgumentGroup {
 
 impl ToString for ArgumentGroup {
     fn to_string(&self) -> String {
-        use crate::text_processing::ast::types::ArgumentGroup::{FuncGroup, OtherGroup};
+        use crate::text_processing::ast::types::ArgumentGroup::{
+            ClauseGroup, FuncGroup, ModifierGroup, OtherGroup,
+        };
 
         match self {
             FuncGroup(val) => val.to_owned(),
+            ClauseGroup(val) => val.to_owned(),
+            ModifierGroup(val) => val.to_owned(),
             OtherGroup(val) => val.to_owned(),
             _ => "".to_owned(),
         }
@@ -209,47 +691,636 @@ impl std::fmt::Display for UnaryFuncExpr {
         write!(f, "function type: {:?}", &self.func_type);
         write!(f, "channel names: {:?}", &self.channel_names);
         write!(f, "binary expressions: {:?}", &self.binary_exprs);
-        write!(f, "vars: {:?}", &self.vars)
+        write!(f, "vars: {:?}", &self.vars);
+        write!(f, "projection: {:?}", &self.projection);
+        write!(f, "joins: {:?}", &self.joins);
+        write!(f, "group by: {:?}", &self.group_by);
+        write!(f, "having: {:?}", &self.having);
+        write!(f, "order by: {:?}", &self.order_by);
+        write!(f, "limit: {:?}, offset: {:?}", &self.limit, &self.offset);
+        write!(f, "returning: {:?}", &self.returning)
     }
 }
 
-#[derive(Debug)]
-// expressions for left-hand and right-hand data types
-pub struct BinaryExpr(DataType, DataType, String);
+#[derive(Debug, Clone, PartialEq)]
+// expressions for left-hand and right-hand terms, term is scalar expression
+// example: age >= 18, lower(name) == 'bob'
+pub struct BinaryExpr(ScalarExpr, ScalarExpr, CompareOperator);
 
 impl BinaryExpr {
-    pub fn new(lterm: DataType, rterm: DataType, operator: String) -> BinaryExpr {
+    pub fn new(lterm: DataType, rterm: DataType, operator: CompareOperator) -> BinaryExpr {
+        BinaryExpr(ScalarExpr::Value(lterm), ScalarExpr::Value(rterm), operator)
+    }
+    pub fn from_scalar_exprs(
+        lterm: ScalarExpr,
+        rterm: ScalarExpr,
+        operator: CompareOperator,
+    ) -> BinaryExpr {
         BinaryExpr(lterm, rterm, operator)
     }
+    pub fn get_lterm(&self) -> &ScalarExpr {
+        &self.0
+    }
+    pub fn get_rterm(&self) -> &ScalarExpr {
+        &self.1
+    }
+    pub fn get_operator(&self) -> &CompareOperator {
+        &self.2
+    }
 
-    fn eq(&self) -> bool {
-        self.0 == self.1
+    // compare with fields (Symbol terms) taken from record
+    pub fn eval(&self, record: &Record) -> Option<bool> {
+        let lterm = self.0.eval(record)?;
+        let rterm = self.1.eval(record)?;
+        Some(self.2.apply(&lterm, &rterm))
     }
-    fn neq(&self) -> bool {
-        self.0 != self.1
+
+    pub fn compare(&self) -> Option<bool> {
+        self.eval(&Record::new())
     }
-    fn ge(&self) -> bool {
-        self.0 >= self.1
+    // todo: add AND and OR operators
+}
+
+impl std::fmt::Display for BinaryExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.0, self.2, self.1)
     }
-    fn gt(&self) -> bool {
-        self.0 > self.1
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// comparison operators of binary expressions
+pub enum CompareOperator {
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+    Le,
+    Lt,
+}
+
+impl CompareOperator {
+    pub fn from_string<T: ToString>(val: T) -> Option<CompareOperator> {
+        match val.to_string().as_str() {
+            "==" => Some(CompareOperator::Eq),
+            "!=" => Some(CompareOperator::Ne),
+            ">=" => Some(CompareOperator::Ge),
+            ">" => Some(CompareOperator::Gt),
+            "<=" => Some(CompareOperator::Le),
+            "<" => Some(CompareOperator::Lt),
+            _ => None,
+        }
     }
-    fn le(&self) -> bool {
-        self.0 <= self.1
+
+    pub fn apply(&self, lterm: &DataType, rterm: &DataType) -> bool {
+        match self {
+            CompareOperator::Eq => lterm == rterm,
+            CompareOperator::Ne => lterm != rterm,
+            CompareOperator::Ge => lterm >= rterm,
+            CompareOperator::Gt => lterm > rterm,
+            CompareOperator::Le => lterm <= rterm,
+            CompareOperator::Lt => lterm < rterm,
+        }
     }
-    fn lt(&self) -> bool {
-        self.0 < self.1
+}
+
+impl std::fmt::Display for CompareOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            CompareOperator::Eq => "==",
+            CompareOperator::Ne => "!=",
+            CompareOperator::Ge => ">=",
+            CompareOperator::Gt => ">",
+            CompareOperator::Le => "<=",
+            CompareOperator::Lt => "<",
+        };
+        write!(f, "{}", val)
     }
-    // todo: add AND and OR operators
+}
 
-    pub fn compare(&self) -> Option<bool> {
-        match self.2.as_str() {
-            "==" => Some(self.eq()),
-            "!=" => Some(self.neq()),
-            ">=" => Some(self.ge()),
-            ">" => Some(self.gt()),
-            "<=" => Some(self.le()),
-            "<" => Some(self.lt()),
+#[derive(Debug, Copy, Clone, PartialEq)]
+// arithmetic operators for scalar expressions
+pub enum ArithOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl ArithOperator {
+    pub fn from_string<T: ToString>(val: T) -> Option<ArithOperator> {
+        match val.to_string().as_str() {
+            "+" => Some(ArithOperator::Add),
+            "-" => Some(ArithOperator::Sub),
+            "*" => Some(ArithOperator::Mul),
+            "/" => Some(ArithOperator::Div),
+            "%" => Some(ArithOperator::Rem),
+            _ => None,
+        }
+    }
+
+    // operator with greater priority is applied first
+    pub fn get_priority(&self) -> u8 {
+        match self {
+            ArithOperator::Add | ArithOperator::Sub => 1,
+            ArithOperator::Mul | ArithOperator::Div | ArithOperator::Rem => 2,
+        }
+    }
+
+    fn apply_int(&self, l: i64, r: i64) -> Option<i64> {
+        match self {
+            ArithOperator::Add => l.checked_add(r),
+            ArithOperator::Sub => l.checked_sub(r),
+            ArithOperator::Mul => l.checked_mul(r),
+            ArithOperator::Div => l.checked_div(r),
+            ArithOperator::Rem => l.checked_rem(r),
+        }
+    }
+
+    fn apply_real(&self, l: f64, r: f64) -> f64 {
+        match self {
+            ArithOperator::Add => l + r,
+            ArithOperator::Sub => l - r,
+            ArithOperator::Mul => l * r,
+            ArithOperator::Div => l / r,
+            ArithOperator::Rem => l % r,
+        }
+    }
+}
+
+impl std::fmt::Display for ArithOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val = match self {
+            ArithOperator::Add => "+",
+            ArithOperator::Sub => "-",
+            ArithOperator::Mul => "*",
+            ArithOperator::Div => "/",
+            ArithOperator::Rem => "%",
+        };
+        write!(f, "{}", val)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// aggregate functions for onRead projection
+// example: count(*), count(distinct a), sum(a), avg(a), min(a), max(a)
+pub enum AggregateFunc {
+    Count,
+    CountDistinct,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunc {
+    pub fn from_string<T: ToString>(val: T) -> Option<AggregateFunc> {
+        use crate::text_processing::ast::types_annotations::{AVG, COUNT, MAX, MIN, SUM};
+
+        match val.to_string().to_lowercase().as_str() {
+            COUNT => Some(AggregateFunc::Count),
+            SUM => Some(AggregateFunc::Sum),
+            AVG => Some(AggregateFunc::Avg),
+            MIN => Some(AggregateFunc::Min),
+            MAX => Some(AggregateFunc::Max),
+            _ => None,
+        }
+    }
+
+    // type of aggregate result for type of argument
+    // example: avg of int -> real, count of any -> int, sum of text -> not defined
+    pub fn result_type(&self, arg_type: &str) -> Option<&'static str> {
+        use crate::text_processing::ast::types_annotations::{BOOL, INT, NULL, REAL, TEXT};
+
+        match (self, arg_type) {
+            (AggregateFunc::Count, _) | (AggregateFunc::CountDistinct, _) => Some(INT),
+            (_, NULL) => Some(NULL),
+            (AggregateFunc::Sum, INT) => Some(INT),
+            (AggregateFunc::Sum, REAL) | (AggregateFunc::Avg, INT) | (AggregateFunc::Avg, REAL) => {
+                Some(REAL)
+            }
+            (AggregateFunc::Min, val) | (AggregateFunc::Max, val) => {
+                [BOOL, INT, REAL, TEXT].iter().find(|e| **e == val).copied()
+            }
+            _ => None,
+        }
+    }
+
+    // compute aggregate over values of group, null values are skipped
+    // count(*) gets one value per record
+    pub fn apply(&self, values: Vec<DataType>) -> Option<DataType> {
+        use super::types::DataType::*;
+
+        let values: Vec<DataType> = values.into_iter().filter(|e| *e != Null).collect();
+        for e in values.iter() {
+            if self.result_type(e.get_type_name()).is_none() {
+                Logger::error(format!("{} is not defined for {:?}", self, e).as_str());
+                return None;
+            }
+        }
+        let result = match self {
+            AggregateFunc::Count => Int(values.len() as i64),
+            AggregateFunc::CountDistinct => {
+                let mut distinct: Vec<&DataType> = vec![];
+                for e in values.iter() {
+                    if !distinct.contains(&e) {
+                        distinct.push(e);
+                    }
+                }
+                Int(distinct.len() as i64)
+            }
+            _ if values.is_empty() => Null,
+            AggregateFunc::Sum | AggregateFunc::Avg => {
+                let is_int = values.iter().all(|e| matches!(e, Int(_)));
+                let sum = values.iter().fold(0.0, |acc, e| match e {
+                    Int(val) => acc + *val as f64,
+                    Real(val) => acc + val,
+                    _ => acc,
+                });
+                match self {
+                    // overflow gives null, like arithmetic of int values
+                    AggregateFunc::Sum if is_int => values
+                        .iter()
+                        .try_fold(0i64, |acc, e| match e {
+                            Int(val) => acc.checked_add(*val),
+                            _ => Some(acc),
+                        })
+                        .map_or(Null, Int),
+                    AggregateFunc::Sum => Real(sum),
+                    _ => Real(sum / values.len() as f64),
+                }
+            }
+            AggregateFunc::Min | AggregateFunc::Max => {
+                let mut result = values[0].clone();
+                for e in values.into_iter().skip(1) {
+                    let is_less = match (&e, &result) {
+                        (Int(l), Real(r)) => (*l as f64) < *r,
+                        (Real(l), Int(r)) => *l < (*r as f64),
+                        _ => e < result,
+                    };
+                    if is_less == (*self == AggregateFunc::Min) {
+                        result = e;
+                    }
+                }
+                result
+            }
+        };
+        Some(result)
+    }
+}
+
+impl std::fmt::Display for AggregateFunc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::text_processing::ast::types_annotations::{AVG, COUNT, MAX, MIN, SUM};
+
+        let val = match self {
+            AggregateFunc::Count | AggregateFunc::CountDistinct => COUNT,
+            AggregateFunc::Sum => SUM,
+            AggregateFunc::Avg => AVG,
+            AggregateFunc::Min => MIN,
+            AggregateFunc::Max => MAX,
+        };
+        write!(f, "{}", val)
+    }
+}
+
+// apply arithmetic operator to computed values
+pub fn apply_arithmetic(
+    lterm: DataType,
+    operator: &ArithOperator,
+    rterm: DataType,
+) -> Option<DataType> {
+    use super::types::DataType::*;
+
+    let result = match (&lterm, &rterm) {
+        (Null, _) | (_, Null) => Some(Null),
+        (Int(l), Int(r)) => Some(operator.apply_int(*l, *r).map_or(Null, Int)),
+        (Int(l), Real(r)) => Some(Real(operator.apply_real(*l as f64, *r))),
+        (Real(l), Int(r)) => Some(Real(operator.apply_real(*l, *r as f64))),
+        (Real(l), Real(r)) => Some(Real(operator.apply_real(*l, *r))),
+        (Text(l), Text(r)) if *operator == ArithOperator::Add => Some(Text(format!("{}{}", l, r))),
+        _ => None,
+    };
+    if result.is_none() {
+        Logger::error(
+            format!(
+                "operator {} is not defined for {:?} and {:?}",
+                operator, lterm, rterm
+            )
+            .as_str(),
+        );
+    }
+    result
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// scalar expression, computed for every record from fields and literals
+// example: price * qty + 1
+pub enum ScalarExpr {
+    // literal value or field (Symbol)
+    Value(DataType),
+    // left-hand expression, operator, right-hand expression
+    Arithmetic(Box<ScalarExpr>, ArithOperator, Box<ScalarExpr>),
+    // aggregate over group of records, None argument is count(*)
+    Aggregate(AggregateFunc, Option<Box<ScalarExpr>>),
+    // built-in scalar function, example: lower(name)
+    Call(ScalarFunc, Vec<ScalarExpr>),
+}
+
+impl ScalarExpr {
+    // compute value for record, unknown field is null
+    pub fn eval(&self, record: &Record) -> Option<DataType> {
+        use super::types::DataType::*;
+
+        match self {
+            ScalarExpr::Value(Symbol(name)) => Some(record.get(name).cloned().unwrap_or(Null)),
+            ScalarExpr::Value(val) => Some(val.clone()),
+            ScalarExpr::Arithmetic(lterm, operator, rterm) => {
+                apply_arithmetic(lterm.eval(record)?, operator, rterm.eval(record)?)
+            }
+            ScalarExpr::Aggregate(_, _) => {
+                Logger::error(format!("aggregate {} is used without group", self).as_str());
+                None
+            }
+            ScalarExpr::Call(func, args) => {
+                let values: Option<Vec<DataType>> = args.iter().map(|e| e.eval(record)).collect();
+                func.apply(values?)
+            }
+        }
+    }
+
+    // compute value for group of records:
+    // aggregates are computed over group, fields are taken from first record
+    pub fn eval_group(&self, records: &[Record]) -> Option<DataType> {
+        match self {
+            ScalarExpr::Aggregate(func, None) => {
+                func.apply(records.iter().map(|_| DataType::Bool(true)).collect())
+            }
+            ScalarExpr::Aggregate(func, Some(arg)) => {
+                let values: Option<Vec<DataType>> = records.iter().map(|e| arg.eval(e)).collect();
+                func.apply(values?)
+            }
+            ScalarExpr::Arithmetic(lterm, operator, rterm) => apply_arithmetic(
+                lterm.eval_group(records)?,
+                operator,
+                rterm.eval_group(records)?,
+            ),
+            ScalarExpr::Call(func, args) => {
+                let values: Option<Vec<DataType>> =
+                    args.iter().map(|e| e.eval_group(records)).collect();
+                func.apply(values?)
+            }
+            ScalarExpr::Value(_) => match records.first() {
+                Some(record) => self.eval(record),
+                None => Some(DataType::Null),
+            },
+        }
+    }
+
+    // true if expression contains aggregate function
+    pub fn has_aggregate(&self) -> bool {
+        match self {
+            ScalarExpr::Aggregate(_, _) => true,
+            ScalarExpr::Arithmetic(lterm, _, rterm) => {
+                lterm.has_aggregate() || rterm.has_aggregate()
+            }
+            ScalarExpr::Call(_, args) => args.iter().any(ScalarExpr::has_aggregate),
+            ScalarExpr::Value(_) => false,
+        }
+    }
+
+    // true if expression reads fields of record
+    pub fn has_field(&self) -> bool {
+        self.has_value(&|e| matches!(e, DataType::Symbol(_)))
+    }
+
+    // true if expression contains placeholder
+    pub fn has_param(&self) -> bool {
+        self.has_value(&|e| matches!(e, DataType::Param(_)))
+    }
+
+    fn has_value(&self, predicate: &dyn Fn(&DataType) -> bool) -> bool {
+        match self {
+            ScalarExpr::Value(val) => predicate(val),
+            ScalarExpr::Arithmetic(lterm, _, rterm) => {
+                lterm.has_value(predicate) || rterm.has_value(predicate)
+            }
+            ScalarExpr::Aggregate(_, arg) => arg.iter().any(|e| e.has_value(predicate)),
+            ScalarExpr::Call(_, args) => args.iter().any(|e| e.has_value(predicate)),
+        }
+    }
+
+    // type of result known before execution, None if it depends on fields or placeholders
+    // example: length(name) -> int, price * 2 -> None
+    pub fn get_type_name(&self) -> Option<&'static str> {
+        use crate::text_processing::ast::types_annotations::{INT, NULL, REAL, TEXT};
+
+        match self {
+            ScalarExpr::Value(DataType::Symbol(_)) | ScalarExpr::Value(DataType::Param(_)) => None,
+            ScalarExpr::Value(val) => Some(val.get_type_name()),
+            ScalarExpr::Arithmetic(lterm, operator, rterm) => {
+                match (lterm.get_type_name()?, rterm.get_type_name()?) {
+                    (NULL, _) | (_, NULL) => Some(NULL),
+                    (INT, INT) => Some(INT),
+                    (INT, REAL) | (REAL, INT) | (REAL, REAL) => Some(REAL),
+                    (TEXT, TEXT) if *operator == ArithOperator::Add => Some(TEXT),
+                    _ => None,
+                }
+            }
+            ScalarExpr::Aggregate(func, None) => func.result_type(NULL),
+            ScalarExpr::Aggregate(func, Some(arg)) => func.result_type(arg.get_type_name()?),
+            ScalarExpr::Call(func, args) => {
+                let arg_types: Vec<Option<&'static str>> =
+                    args.iter().map(ScalarExpr::get_type_name).collect();
+                func.result_type(&arg_types).ok().flatten()
+            }
+        }
+    }
+
+    // check types of function arguments which are known before execution
+    // example: lower(18) -> error
+    pub fn check(&self) -> Option<()> {
+        match self {
+            ScalarExpr::Value(_) => Some(()),
+            ScalarExpr::Arithmetic(lterm, _, rterm) => {
+                lterm.check()?;
+                rterm.check()
+            }
+            ScalarExpr::Aggregate(_, arg) => arg.as_ref().map_or(Some(()), |e| e.check()),
+            ScalarExpr::Call(func, args) => {
+                for arg in args {
+                    arg.check()?;
+                }
+                let arg_types: Vec<Option<&'static str>> =
+                    args.iter().map(ScalarExpr::get_type_name).collect();
+                match func.result_type(&arg_types) {
+                    Ok(_) => Some(()),
+                    Err(mes) => {
+                        Logger::error(format!("{} in: {}", mes, self).as_str());
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ScalarExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::text_processing::ast::types_annotations::DISTINCT;
+
+        match self {
+            ScalarExpr::Value(val) => write!(f, "{}", val),
+            // nested arithmetic is parenthesized when its operator binds weaker
+            // example: a*(b+1), a-(b-c), a*b+1
+            ScalarExpr::Arithmetic(lterm, operator, rterm) => {
+                let priority = operator.get_priority();
+                match lterm.as_ref() {
+                    ScalarExpr::Arithmetic(_, e, _) if e.get_priority() < priority => {
+                        write!(f, "({})", lterm)?
+                    }
+                    _ => write!(f, "{}", lterm)?,
+                }
+                write!(f, "{}", operator)?;
+                match rterm.as_ref() {
+                    ScalarExpr::Arithmetic(_, e, _) if e.get_priority() <= priority => {
+                        write!(f, "({})", rterm)
+                    }
+                    _ => write!(f, "{}", rterm),
+                }
+            }
+            ScalarExpr::Aggregate(func, None) => write!(f, "{}(*)", func),
+            ScalarExpr::Aggregate(AggregateFunc::CountDistinct, Some(arg)) => {
+                write!(f, "{}({} {})", AggregateFunc::CountDistinct, DISTINCT, arg)
+            }
+            ScalarExpr::Aggregate(func, Some(arg)) => write!(f, "{}({})", func, arg),
+            ScalarExpr::Call(func, args) => {
+                let args: Vec<String> = args.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}({})", func, args.join(","))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// field of onRead result: expression with optional alias
+// example: name, name as n, price * qty as total
+pub struct Projection {
+    expr: ScalarExpr,
+    alias: Option<String>,
+}
+
+impl Projection {
+    pub fn new(expr: ScalarExpr, alias: Option<String>) -> Projection {
+        Projection { expr, alias }
+    }
+    pub fn get_expr(&self) -> &ScalarExpr {
+        &self.expr
+    }
+    pub fn get_alias(&self) -> &Option<String> {
+        &self.alias
+    }
+    // name of field in result: alias or text of expression
+    pub fn get_name(&self) -> String {
+        match &self.alias {
+            Some(alias) => alias.to_owned(),
+            None => self.expr.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// sort direction of orderBy item
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// place of null values in sorted result
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// item of orderBy clause
+// by default nulls are last for asc and first for desc
+// example: created desc nulls last
+pub struct OrderBy {
+    expr: ScalarExpr,
+    direction: SortDirection,
+    nulls: NullsOrder,
+}
+
+impl OrderBy {
+    pub fn new(expr: ScalarExpr, direction: SortDirection, nulls: Option<NullsOrder>) -> OrderBy {
+        let nulls = nulls.unwrap_or(match direction {
+            SortDirection::Asc => NullsOrder::Last,
+            SortDirection::Desc => NullsOrder::First,
+        });
+        OrderBy {
+            expr,
+            direction,
+            nulls,
+        }
+    }
+    pub fn get_expr(&self) -> &ScalarExpr {
+        &self.expr
+    }
+    pub fn get_direction(&self) -> SortDirection {
+        self.direction
+    }
+    pub fn get_nulls(&self) -> NullsOrder {
+        self.nulls
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+// kind of join: inner keeps only matched records, left keeps every record of channel
+pub enum JoinKind {
+    Inner,
+    Left,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// join clause of onRead: channel and equality of fields qualified by channel
+// example: join(users)(orders.user_id == users.id)
+pub struct Join {
+    kind: JoinKind,
+    channel: ChannelName,
+    on: BinaryExpr,
+}
+
+impl Join {
+    pub fn new(kind: JoinKind, channel: ChannelName, on: BinaryExpr) -> Join {
+        Join { kind, channel, on }
+    }
+    pub fn get_kind(&self) -> JoinKind {
+        self.kind
+    }
+    pub fn get_channel(&self) -> &ChannelName {
+        &self.channel
+    }
+    pub fn get_on(&self) -> &BinaryExpr {
+        &self.on
+    }
+    // fields of condition as (field of joined records, field of joined channel),
+    // only one of fields belongs to joined channel
+    // example: orders.user_id == users.id -> (orders.user_id, users.id)
+    pub fn get_keys(&self) -> Option<(&String, &String)> {
+        let prefix = format!("{}.", self.channel.get_name());
+        match (&self.on.0, &self.on.1) {
+            (ScalarExpr::Value(DataType::Symbol(l)), ScalarExpr::Value(DataType::Symbol(r)))
+                if r.starts_with(&prefix) && !l.starts_with(&prefix) =>
+            {
+                Some((l, r))
+            }
+            (ScalarExpr::Value(DataType::Symbol(l)), ScalarExpr::Value(DataType::Symbol(r)))
+                if l.starts_with(&prefix) && !r.starts_with(&prefix) =>
+            {
+                Some((r, l))
+            }
             _ => None,
         }
     }
@@ -275,10 +1346,27 @@ impl Util {
         }
     }
 
+    // strip keyword followed by whitespace at start of statement
+    // example: ("let a = 2", "let") -> a = 2
+    pub fn strip_keyword<'a>(raw_value: &'a str, keyword: &str) -> Option<&'a str> {
+        let raw_value = raw_value.trim_start();
+        let rest = raw_value.get(keyword.len()..)?;
+        let is_keyword = raw_value[..keyword.len()].to_lowercase() == keyword;
+        if is_keyword && rest.starts_with(char::is_whitespace) {
+            return Some(rest.trim_start());
+        }
+        None
+    }
+
     // identify type from string value
     pub fn identify_type(term: &String) -> String {
-        use crate::text_processing::ast::types_annotations::{BOOL, INT, NULL, REAL, SYMBOL, TEXT};
+        use crate::text_processing::ast::types_annotations::{
+            BOOL, INT, NULL, PARAM, REAL, SYMBOL, TEXT,
+        };
 
+        if Placeholder::from_string(term).is_some() {
+            return PARAM.to_string();
+        }
         let term = term.chars().collect::<Vec<char>>();
 
         if term.iter().fold(true, |acc, e| acc && (e.is_numeric())) {
@@ -303,7 +1391,9 @@ impl Util {
 #[cfg(test)]
 // test module
 mod test {
-    use crate::text_processing::ast::types::{BinaryExpr, DataType, Util};
+    use crate::text_processing::ast::types::{
+        ArithOperator, BinaryExpr, ChannelName, CompareOperator, DataType, Record, ScalarExpr, Util,
+    };
 
     #[test]
     fn test_data_type_from_string() -> Result<(), ()> {
@@ -336,6 +1426,110 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_channel_name_from_string() -> Result<(), ()> {
+        let channel = ChannelName::from_string("Orders").unwrap();
+        assert_eq!(&None, channel.get_namespace());
+        assert_eq!("orders", channel.get_name());
+
+        let channel = ChannelName::from_string("otherdb.orders").unwrap();
+        assert_eq!(&Some("otherdb".to_string()), channel.get_namespace());
+        assert_eq!("otherdb.orders", channel.to_string());
+
+        assert_eq!(None, ChannelName::from_string("a.b.c"));
+        assert_eq!(None, ChannelName::from_string("a."));
+        assert_eq!(None, ChannelName::from_string("2orders"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_name_resolve() -> Result<(), ()> {
+        let bare = ChannelName::from_string("orders").unwrap();
+        assert_eq!("mynode.orders", bare.resolve("mynode").to_string());
+        assert_eq!("orders", bare.resolve("").to_string());
+
+        let qualified = ChannelName::from_string("otherdb.orders").unwrap();
+        assert_eq!("otherdb.orders", qualified.resolve("mynode").to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scalar_expr_eval() -> Result<(), ()> {
+        let arithmetic = |l: DataType, operator: ArithOperator, r: DataType| {
+            ScalarExpr::Arithmetic(
+                Box::new(ScalarExpr::Value(l)),
+                operator,
+                Box::new(ScalarExpr::Value(r)),
+            )
+        };
+        let mut record = Record::new();
+        record.insert("a".to_string(), DataType::Int(7));
+
+        let expr = arithmetic(
+            DataType::Symbol("a".to_string()),
+            ArithOperator::Rem,
+            DataType::Int(4),
+        );
+        assert_eq!(Some(DataType::Int(3)), expr.eval(&record));
+        let expr = arithmetic(
+            DataType::Symbol("a".to_string()),
+            ArithOperator::Div,
+            DataType::Real(2.0),
+        );
+        assert_eq!(Some(DataType::Real(3.5)), expr.eval(&record));
+        let expr = arithmetic(
+            DataType::Symbol("a".to_string()),
+            ArithOperator::Div,
+            DataType::Int(0),
+        );
+        assert_eq!(Some(DataType::Null), expr.eval(&record));
+        let expr = arithmetic(
+            DataType::Symbol("b".to_string()),
+            ArithOperator::Add,
+            DataType::Int(1),
+        );
+        assert_eq!(Some(DataType::Null), expr.eval(&record));
+        let expr = arithmetic(DataType::Bool(true), ArithOperator::Add, DataType::Int(1));
+        assert_eq!(None, expr.eval(&record));
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_func() -> Result<(), ()> {
+        use crate::text_processing::ast::types::AggregateFunc;
+
+        assert_eq!(Some("real"), AggregateFunc::Avg.result_type("int"));
+        assert_eq!(Some("int"), AggregateFunc::Sum.result_type("int"));
+        assert_eq!(Some("int"), AggregateFunc::Count.result_type("text"));
+        assert_eq!(Some("text"), AggregateFunc::Max.result_type("text"));
+        assert_eq!(None, AggregateFunc::Sum.result_type("text"));
+
+        let values = vec![DataType::Int(3), DataType::Real(2.5), DataType::Null];
+        assert_eq!(
+            Some(DataType::Real(5.5)),
+            AggregateFunc::Sum.apply(values.clone())
+        );
+        assert_eq!(
+            Some(DataType::Real(2.5)),
+            AggregateFunc::Min.apply(values.clone())
+        );
+        assert_eq!(
+            Some(DataType::Int(3)),
+            AggregateFunc::Max.apply(values.clone())
+        );
+        assert_eq!(Some(DataType::Int(2)), AggregateFunc::Count.apply(values));
+        assert_eq!(
+            Some(DataType::Int(5)),
+            AggregateFunc::Sum.apply(vec![DataType::Int(2), DataType::Int(3)])
+        );
+        // overflow of int sum is null
+        assert_eq!(
+            Some(DataType::Null),
+            AggregateFunc::Sum.apply(vec![DataType::Int(i64::MAX), DataType::Int(1)])
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_is_single_word() -> Result<(), ()> {
         assert_eq!(true, Util::is_single_word("myvarexample".to_string()));
@@ -366,10 +1560,10 @@ mod test {
     fn test_binary_expr_compare() -> Result<(), ()> {
         assert_eq!(
             true,
-            BinaryExpr(
+            BinaryExpr::new(
                 DataType::Text("my text".to_string()),
                 DataType::Text("my text".to_string()),
-                "==".to_string()
+                CompareOperator::Eq
             )
             .compare()
             .unwrap()
@@ -377,10 +1571,10 @@ mod test {
 
         assert_eq!(
             true,
-            BinaryExpr(
+            BinaryExpr::new(
                 DataType::Text("my text double".to_string()),
                 DataType::Text("my text".to_string()),
-                ">=".to_string()
+                CompareOperator::Ge
             )
             .compare()
             .unwrap()
@@ -388,10 +1582,10 @@ mod test {
 
         assert_eq!(
             true,
-            BinaryExpr(
+            BinaryExpr::new(
                 DataType::Text("my text".to_string()),
                 DataType::Text("my text double".to_string()),
-                "<=".to_string()
+                CompareOperator::Le
             )
             .compare()
             .unwrap()
@@ -399,28 +1593,28 @@ mod test {
 
         assert_eq!(
             true,
-            BinaryExpr(DataType::Int(32), DataType::Real(32.0), "!=".to_string())
+            BinaryExpr::new(DataType::Int(32), DataType::Real(32.0), CompareOperator::Ne)
                 .compare()
                 .unwrap()
         );
 
         assert_eq!(
             true,
-            BinaryExpr(DataType::Null, DataType::Null, "==".to_string())
+            BinaryExpr::new(DataType::Null, DataType::Null, CompareOperator::Eq)
                 .compare()
                 .unwrap()
         );
 
         assert_eq!(
             false,
-            BinaryExpr(DataType::Bool(true), DataType::Null, "==".to_string())
+            BinaryExpr::new(DataType::Bool(true), DataType::Null, CompareOperator::Eq)
                 .compare()
                 .unwrap()
         );
 
         assert_eq!(
             false,
-            BinaryExpr(DataType::Int(32), DataType::Real(32.0), "==".to_string())
+            BinaryExpr::new(DataType::Int(32), DataType::Real(32.0), CompareOperator::Eq)
                 .compare()
                 .unwrap()
         );
@@ -3,6 +3,70 @@ pub const ONCREATE: &str = "oncreate";
 pub const ONREAD: &str = "onread";
 pub const ONUPDATE: &str = "onupdate";
 pub const ONDELETE: &str = "ondelete";
+pub const ONUPSERT: &str = "onupsert";
+pub const ONWATCH: &str = "onwatch";
+pub const ONINDEX: &str = "onindex";
+
+// script keywords definition
+pub const LET: &str = "let";
+pub const DEFINE: &str = "define";
+pub const CALL: &str = "call";
+pub const BEGIN: &str = "begin";
+pub const COMMIT: &str = "commit";
+pub const ROLLBACK: &str = "rollback";
+pub const EXPLAIN: &str = "explain";
+pub const JSON: &str = "json";
+
+// clause names definition
+pub const ORDERBY: &str = "orderby";
+pub const LIMIT: &str = "limit";
+pub const OFFSET: &str = "offset";
+pub const GROUPBY: &str = "groupby";
+pub const HAVING: &str = "having";
+pub const JOIN: &str = "join";
+pub const LEFTJOIN: &str = "leftjoin";
+pub const RETURNING: &str = "returning";
+pub const TTL: &str = "ttl";
+
+// read modifiers definition
+pub const AS: &str = "as";
+pub const OF: &str = "of";
+pub const VERSION: &str = "version";
+pub const TIMESTAMP: &str = "timestamp";
+pub const HISTORY: &str = "history";
+
+// aggregate functions names definition
+pub const COUNT: &str = "count";
+pub const SUM: &str = "sum";
+pub const AVG: &str = "avg";
+pub const MIN: &str = "min";
+pub const MAX: &str = "max";
+pub const DISTINCT: &str = "distinct";
+
+// scalar functions names definition
+pub const LOWER: &str = "lower";
+pub const UPPER: &str = "upper";
+pub const LENGTH: &str = "length";
+pub const TRIM: &str = "trim";
+pub const SUBSTR: &str = "substr";
+pub const CONCAT: &str = "concat";
+pub const ABS: &str = "abs";
+pub const ROUND: &str = "round";
+pub const FLOOR: &str = "floor";
+pub const CEIL: &str = "ceil";
+pub const COALESCE: &str = "coalesce";
+pub const NULLIF: &str = "nullif";
+
+// computed defaults names definition
+pub const NOW: &str = "now";
+pub const UUID: &str = "uuid";
+pub const NEXTVAL: &str = "nextval";
+
+// ordering keywords definition
+pub const ASC: &str = "asc";
+pub const DESC: &str = "desc";
+pub const NULLS_FIRST: &str = "nulls first";
+pub const NULLS_LAST: &str = "nulls last";
 
 // data types names definition
 pub const NULL: &str = "null";
@@ -11,3 +75,4 @@ pub const INT: &str = "int";
 pub const REAL: &str = "real";
 pub const TEXT: &str = "text";
 pub const SYMBOL: &str = "symbol";
+pub const PARAM: &str = "param";
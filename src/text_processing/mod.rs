@@ -1,5 +1,5 @@
-mod ast;
-mod parser;
+pub mod ast;
+pub mod parser;
 
 //use ast::types::*;
 
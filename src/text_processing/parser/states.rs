@@ -1,8 +1,15 @@
 use crate::environment::logger::Logger;
+use crate::execution::result::Record;
+use crate::text_processing::ast::definitions::Definition;
+use crate::text_processing::ast::functions::ScalarFunc;
+use crate::text_processing::ast::params::Params;
+use crate::text_processing::ast::scope::{LetValue, Scope};
 use crate::text_processing::ast::types::{
-    ArgumentGroup, BinaryExpr, DataType, DataVar, FuncType, UnaryFuncExpr, Util,
+    AggregateFunc, ArgumentGroup, ArithOperator, AsOf, BinaryExpr, Block, BlockKind, ChannelName,
+    CompareOperator, DataType, DataVar, DefaultExpr, Expiry, ExplainFormat, FuncType, Join,
+    JoinKind, NullsOrder, OrderBy, ParamKey, Placeholder, Projection, ScalarExpr, SortDirection,
+    UnaryFuncExpr, Util,
 };
-use std::str::from_utf8;
 
 // rule for parse  DSL-line from string
 // it's struct promotes pipeline logic for create UnaryFuncExpression
@@ -13,46 +20,106 @@ pub struct Rule;
 impl Rule {
     // helping to split line on string group for next processing
     fn split_on_raw_group<T: Into<String>>(line: T) -> Vec<String> {
-        let mut line: String = line.into();
-        let mut capture_mode: bool = true;
-        line.retain(|e: char| {
-            if e == '\'' {
-                capture_mode = !capture_mode
+        Rule::split_on_typed_group(line)
+            .into_iter()
+            .map(|(e, _)| e)
+            .filter(|e| !e.is_empty())
+            .collect()
+    }
+    // helping to split line on words and top-level groups in parentheses
+    // returns (value, is_group), empty group is kept for positional arguments
+    // example: onRead(a)()(b as c) -> [(onRead, false), (a, true), ("", true), (b as c, true)]
+    fn split_on_typed_group<T: Into<String>>(line: T) -> Vec<(String, bool)> {
+        fn is_word_char(e: char) -> bool {
+            e.is_alphanumeric() || e == '_' || e == '\'' || e == '.' || e == '$'
+        }
+        // whitespace is kept only before word: "name as n", "created desc", "count(*) as n"
+        fn push_char(buffer: &mut String, pending_space: &mut bool, e: char) {
+            let is_word_end = buffer.ends_with(is_word_char) || buffer.ends_with(')');
+            if *pending_space && is_word_end && is_word_char(e) {
+                buffer.push(' ');
             }
+            *pending_space = false;
+            buffer.push(e);
+        }
+        let line: String = line.into();
+        let mut groups: Vec<(String, bool)> = vec![];
+        let mut buffer = String::new();
+        let mut pending_space = false;
+        let mut depth: usize = 0;
+        let mut capture_mode = false;
+
+        for e in line.chars() {
             if capture_mode {
-                !e.is_whitespace()
-            } else {
-                true
+                buffer.push(e);
+                capture_mode = e != '\'';
+                continue;
             }
-        });
-        let lines: Vec<&[u8]> = line
-            .as_bytes()
-            .split(|elem| *elem == b'\n' || *elem == b';')
-            .collect();
-
-        let mut accum: Vec<Vec<&[u8]>> = vec![vec![]];
-        for line in lines {
-            let mut capture_mode = true;
-            let a: Vec<&[u8]> = line
-                .split(|elem| {
-                    if *elem == b'\'' {
-                        capture_mode = !capture_mode
+            match e {
+                '\'' => {
+                    push_char(&mut buffer, &mut pending_space, e);
+                    capture_mode = true;
+                }
+                '(' if depth == 0 => {
+                    if !buffer.is_empty() {
+                        groups.push((buffer.clone(), false));
                     }
-                    (*elem == b'(' || *elem == b')') && capture_mode == true
-                })
-                .collect();
-            if !a.is_empty() {
-                accum.push(a);
+                    buffer.clear();
+                    pending_space = false;
+                    depth = 1;
+                }
+                ')' if depth == 1 => {
+                    groups.push((buffer.clone(), true));
+                    buffer.clear();
+                    pending_space = false;
+                    depth = 0;
+                }
+                '(' => {
+                    push_char(&mut buffer, &mut pending_space, e);
+                    depth += 1;
+                }
+                ')' if depth > 1 => {
+                    push_char(&mut buffer, &mut pending_space, e);
+                    depth -= 1;
+                }
+                _ if depth == 0 && (e.is_whitespace() || e == ';') => {
+                    if !buffer.is_empty() {
+                        groups.push((buffer.clone(), false));
+                    }
+                    buffer.clear();
+                }
+                _ if e.is_whitespace() => pending_space = true,
+                _ => push_char(&mut buffer, &mut pending_space, e),
             }
         }
-        let accum: Vec<String> = accum
-            .iter()
-            .flatten()
-            .map(|e| from_utf8(e).unwrap().to_owned())
-            .filter(|e| !e.is_empty())
-            .collect();
-
-        accum
+        if !buffer.is_empty() {
+            groups.push((buffer, depth > 0));
+        }
+        groups
+    }
+    // helping to split group on items by top-level commas
+    // example: a, concat(b, c), 'd,e' -> [a, concat(b, c), 'd,e']
+    fn split_on_items(val: &str) -> Vec<String> {
+        let mut items: Vec<String> = vec![];
+        let mut buffer = String::new();
+        let mut depth: usize = 0;
+        let mut capture_mode = false;
+        for e in val.chars() {
+            match e {
+                '\'' => capture_mode = !capture_mode,
+                '(' if !capture_mode => depth += 1,
+                ')' if !capture_mode => depth = depth.saturating_sub(1),
+                ',' if !capture_mode && depth == 0 => {
+                    items.push(buffer.clone());
+                    buffer.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            buffer.push(e);
+        }
+        items.push(buffer);
+        items
     }
     // helping to split line on binary expression (used in get_expressions)
     fn split_expression(raw_expression: &str) -> Option<BinaryExpr> {
@@ -66,22 +133,19 @@ impl Rule {
             }
             None
         }
-        fn create_data_type(term: &String) -> Option<DataType> {
-            DataType::from_string(term, &Util::identify_type(term))
-        }
         let operators = vec!["==", "!=", ">=", "<=", ">", "<"];
         let mut binary_expression: Option<BinaryExpr> = None;
         'a: for operator in operators {
             match split_on_data_type_and_operator(raw_expression, operator) {
-                Some(ref val) => {
-                    binary_expression = Some(BinaryExpr::new(
-                        create_data_type(&val[0]).unwrap(),
-                        create_data_type(&val[1]).unwrap(),
-                        operator.to_string(),
+                Some(ref val) if val.len() == 2 => {
+                    binary_expression = Some(BinaryExpr::from_scalar_exprs(
+                        Rule::split_scalar_expr(&val[0])?,
+                        Rule::split_scalar_expr(&val[1])?,
+                        CompareOperator::from_string(operator)?,
                     ));
                     break 'a;
                 }
-                None => binary_expression = None,
+                _ => binary_expression = None,
             };
         }
         if binary_expression.is_none() {
@@ -91,10 +155,249 @@ impl Rule {
         }
         binary_expression
     }
+    // helping to split scalar expression on tokens: words, literals and operators
+    fn split_on_scalar_tokens(raw_expression: &str) -> Vec<String> {
+        let mut tokens: Vec<String> = vec![];
+        let mut buffer = String::new();
+        let mut capture_mode = false;
+        for e in raw_expression.chars() {
+            if capture_mode || e == '\'' {
+                buffer.push(e);
+                capture_mode = (e == '\'') != capture_mode;
+                continue;
+            }
+            if e.is_alphanumeric() || e == '_' || e == '.' || e == '$' || e == ':' {
+                buffer.push(e);
+                continue;
+            }
+            if !buffer.is_empty() {
+                tokens.push(buffer.clone());
+                buffer.clear();
+            }
+            if !e.is_whitespace() {
+                tokens.push(e.to_string());
+            }
+        }
+        if !buffer.is_empty() {
+            tokens.push(buffer);
+        }
+        tokens
+    }
+    // helping to parse scalar expression (used in projection, filter and statements)
+    // priority: function call, unary minus, then * / %, then + -
+    // example: price*(qty+1), lower(name)
+    fn split_scalar_expr(raw_expression: &str) -> Option<ScalarExpr> {
+        fn parse_sum(tokens: &[String], pos: &mut usize) -> Option<ScalarExpr> {
+            let mut lterm = parse_product(tokens, pos)?;
+            while let Some(operator) = tokens.get(*pos).and_then(ArithOperator::from_string) {
+                if operator != ArithOperator::Add && operator != ArithOperator::Sub {
+                    break;
+                }
+                *pos += 1;
+                let rterm = parse_product(tokens, pos)?;
+                lterm = ScalarExpr::Arithmetic(Box::new(lterm), operator, Box::new(rterm));
+            }
+            Some(lterm)
+        }
+        fn parse_product(tokens: &[String], pos: &mut usize) -> Option<ScalarExpr> {
+            let mut lterm = parse_unary(tokens, pos)?;
+            while let Some(operator) = tokens.get(*pos).and_then(ArithOperator::from_string) {
+                if operator == ArithOperator::Add || operator == ArithOperator::Sub {
+                    break;
+                }
+                *pos += 1;
+                let rterm = parse_unary(tokens, pos)?;
+                lterm = ScalarExpr::Arithmetic(Box::new(lterm), operator, Box::new(rterm));
+            }
+            Some(lterm)
+        }
+        fn parse_unary(tokens: &[String], pos: &mut usize) -> Option<ScalarExpr> {
+            let token = tokens.get(*pos)?;
+            *pos += 1;
+            match token.as_str() {
+                "-" => {
+                    let rterm = parse_unary(tokens, pos)?;
+                    Some(match rterm {
+                        ScalarExpr::Value(DataType::Int(val)) => {
+                            ScalarExpr::Value(DataType::Int(-val))
+                        }
+                        ScalarExpr::Value(DataType::Real(val)) => {
+                            ScalarExpr::Value(DataType::Real(-val))
+                        }
+                        rterm => ScalarExpr::Arithmetic(
+                            Box::new(ScalarExpr::Value(DataType::Int(0))),
+                            ArithOperator::Sub,
+                            Box::new(rterm),
+                        ),
+                    })
+                }
+                "(" => {
+                    let expr = parse_sum(tokens, pos)?;
+                    if tokens.get(*pos)? != ")" {
+                        return None;
+                    }
+                    *pos += 1;
+                    Some(expr)
+                }
+                _ if tokens.get(*pos).map(|e| e.as_str()) == Some("(") => {
+                    *pos += 1;
+                    let expr = parse_call(token, tokens, pos)?;
+                    if tokens.get(*pos)? != ")" {
+                        return None;
+                    }
+                    *pos += 1;
+                    Some(expr)
+                }
+                _ => parse_atom(token),
+            }
+        }
+        // function call after name and opening parenthesis
+        // example: count(*), count(distinct a), sum(a), substr(name, 1, 3)
+        fn parse_call(name: &str, tokens: &[String], pos: &mut usize) -> Option<ScalarExpr> {
+            use crate::text_processing::ast::types_annotations::DISTINCT;
+
+            if let Some(func) = ScalarFunc::from_string(name) {
+                return parse_scalar_call(func, tokens, pos);
+            }
+            let func = match AggregateFunc::from_string(name) {
+                Some(func) => func,
+                None => {
+                    Logger::error(format!("function not found: {}", name).as_str());
+                    return None;
+                }
+            };
+            match tokens.get(*pos)?.as_str() {
+                "*" if func == AggregateFunc::Count => {
+                    *pos += 1;
+                    Some(ScalarExpr::Aggregate(func, None))
+                }
+                DISTINCT if func == AggregateFunc::Count => {
+                    *pos += 1;
+                    let arg = parse_sum(tokens, pos)?;
+                    Some(ScalarExpr::Aggregate(
+                        AggregateFunc::CountDistinct,
+                        Some(Box::new(arg)),
+                    ))
+                }
+                _ => {
+                    let arg = parse_sum(tokens, pos)?;
+                    if arg.has_aggregate() {
+                        Logger::error(format!("aggregate inside of aggregate: {}", name).as_str());
+                        return None;
+                    }
+                    Some(ScalarExpr::Aggregate(func, Some(Box::new(arg))))
+                }
+            }
+        }
+        // arguments of scalar function separated by commas, types are checked by signature
+        fn parse_scalar_call(
+            func: ScalarFunc,
+            tokens: &[String],
+            pos: &mut usize,
+        ) -> Option<ScalarExpr> {
+            let mut args: Vec<ScalarExpr> = vec![];
+            if tokens.get(*pos)? != ")" {
+                args.push(parse_sum(tokens, pos)?);
+                while tokens.get(*pos)? == "," {
+                    *pos += 1;
+                    args.push(parse_sum(tokens, pos)?);
+                }
+            }
+            let call = ScalarExpr::Call(func, args);
+            call.check()?;
+            Some(call)
+        }
+        fn parse_atom(token: &String) -> Option<ScalarExpr> {
+            if token.is_empty() || ArithOperator::from_string(token).is_some() {
+                return None;
+            }
+            if token == ")" || token == "(" || token == "," {
+                return None;
+            }
+            let data_type = DataType::from_string(token, &Util::identify_type(token))?;
+            Some(ScalarExpr::Value(data_type))
+        }
+
+        let tokens = Rule::split_on_scalar_tokens(raw_expression);
+        let mut pos: usize = 0;
+        let expr = parse_sum(&tokens, &mut pos).filter(|_| pos == tokens.len());
+        if expr.is_none() {
+            Logger::error(
+                format!("error parse in scalar expression at: {}", raw_expression).as_str(),
+            )
+        }
+        expr
+    }
+    // helping to parse scalar expression where placeholders are not bound (projection, orderBy)
+    fn split_scalar_expr_without_params(raw_expression: &str) -> Option<ScalarExpr> {
+        let expr = Rule::split_scalar_expr(raw_expression)?;
+        if expr.has_param() {
+            Logger::error(format!("placeholder is not allowed here: {}", raw_expression).as_str());
+            return None;
+        }
+        Some(expr)
+    }
+    // helping to split projection item on expression and alias
+    // example: price*qty as total
+    fn split_projection(raw_projection: &str) -> Option<Projection> {
+        let (raw_expression, alias) = match raw_projection.rfind(" as ") {
+            Some(i) if !raw_projection[i..].contains('\'') => {
+                let alias = raw_projection[i + 4..].trim().to_string();
+                if !Util::is_single_word(alias.to_owned()) || alias.contains(' ') {
+                    Logger::error(format!("alias not correctly in: {}", raw_projection).as_str());
+                    return None;
+                }
+                (&raw_projection[..i], Some(alias))
+            }
+            _ => (raw_projection, None),
+        };
+        let expr = Rule::split_scalar_expr_without_params(raw_expression)?;
+        Some(Projection::new(expr, alias))
+    }
+    // helping to split orderBy item on expression, direction and nulls order
+    // example: created desc nulls last
+    fn split_order_by(raw_order_by: &str) -> Option<OrderBy> {
+        use crate::text_processing::ast::types_annotations::{ASC, DESC, NULLS_FIRST, NULLS_LAST};
+
+        let mut raw_expression = raw_order_by;
+        let mut nulls: Option<NullsOrder> = None;
+        for (keyword, val) in [
+            (NULLS_FIRST, NullsOrder::First),
+            (NULLS_LAST, NullsOrder::Last),
+        ] {
+            if let Some(rest) = raw_expression.strip_suffix(keyword) {
+                raw_expression = rest.trim_end();
+                nulls = Some(val);
+            }
+        }
+        let mut direction = SortDirection::Asc;
+        for (keyword, val) in [(ASC, SortDirection::Asc), (DESC, SortDirection::Desc)] {
+            if let Some(rest) = raw_expression.strip_suffix(keyword) {
+                if rest.ends_with(' ') {
+                    raw_expression = rest.trim_end();
+                    direction = val;
+                }
+            }
+        }
+        let expr = Rule::split_scalar_expr_without_params(raw_expression)?;
+        Some(OrderBy::new(expr, direction, nulls))
+    }
+    // helping to parse count of records for limit and offset
+    fn split_count(raw_count: &str) -> Option<usize> {
+        match raw_count.trim().parse::<usize>() {
+            Ok(count) => Some(count),
+            Err(_e) => {
+                Logger::error(format!("count of records not correctly in: {}", raw_count).as_str());
+                None
+            }
+        }
+    }
     // help to split string value to data var
     fn split_statement(raw_statement: &str) -> Option<DataVar> {
+        use crate::text_processing::ast::types_annotations::NULL;
+
         let val: Vec<&str> = raw_statement.splitn(2, ":").collect();
-        let type_and_value: Vec<&str> = val[1].split("=").collect();
+        let type_and_value: Vec<&str> = val.get(1)?.split("=").collect();
 
         let symbol = val.get(0);
         let raw_type = type_and_value.get(0);
@@ -102,39 +405,251 @@ impl Rule {
 
         if symbol.is_some() && raw_type.is_some() && value.is_some() {
             let (symbol, raw_type, value) = (symbol.unwrap(), raw_type.unwrap(), value.unwrap());
-            let data_type = DataType::from_string(value, raw_type).expect(
-                format!(
-                    "DataType creation has been failed at {}.\n  value: {} type: {}",
-                    symbol,
-                    type_and_value.get(0).unwrap(),
-                    type_and_value.get(1).unwrap()
-                )
-                .as_str(),
-            );
-            return Some(DataVar::new(symbol.to_string(), data_type));
+            // placeholder keeps declared type for type check at binding
+            if let Some(placeholder) = Placeholder::from_string(value) {
+                if DataType::from_type_default_value(raw_type).is_none() {
+                    Logger::error(format!("type not correctly in: {}", raw_statement).as_str());
+                    return None;
+                }
+                let data_type = DataType::Param(placeholder.with_type(raw_type));
+                return Some(DataVar::new(symbol.to_string(), data_type));
+            }
+            // computed default: now(), uuid(), nextval(seq)
+            if let Some(default) = Rule::split_default(value) {
+                if default.get_type_name() != Some(raw_type.to_lowercase().as_str()) {
+                    Logger::error(
+                        format!("{} is not {} in: {}", default, raw_type, raw_statement).as_str(),
+                    );
+                    return None;
+                }
+                let data_type = DataType::from_type_default_value(raw_type)?;
+                let var = DataVar::new(symbol.to_string(), data_type);
+                return Some(var.with_default(Some(default)));
+            }
+            // constant expression is evaluated at parsing,
+            // expression over fields is computed at execution for every record
+            // example: (full: text = concat(first, ' ', last))
+            if Rule::split_on_scalar_tokens(value).len() > 1 {
+                let expr = Rule::split_scalar_expr(value)?;
+                if (expr.has_field() || expr.has_param()) && !expr.has_aggregate() {
+                    let type_name = expr.get_type_name();
+                    let data_type = DataType::from_type_default_value(raw_type).filter(
+                        |e| !matches!(type_name, Some(t) if t != NULL && t != e.get_type_name()),
+                    );
+                    if data_type.is_none() {
+                        Logger::error(
+                            format!("value is not {} in: {}", raw_type, raw_statement).as_str(),
+                        );
+                    }
+                    let var = DataVar::new(symbol.to_string(), data_type?);
+                    return Some(var.with_default(Some(DefaultExpr::Expr(expr))));
+                }
+                let data_type = Rule::split_constant_expr(value).filter(|e| {
+                    e == &DataType::Null || e.get_type_name() == raw_type.to_lowercase()
+                });
+                if data_type.is_none() {
+                    Logger::error(
+                        format!("value is not {} in: {}", raw_type, raw_statement).as_str(),
+                    );
+                }
+                return Some(DataVar::new(symbol.to_string(), data_type?));
+            }
+            let data_type = DataType::from_string(value, raw_type);
+            if data_type.is_none() {
+                Logger::error(
+                    format!(
+                        "DataType creation has been failed at {}.\n  value: {} type: {}",
+                        symbol, value, raw_type
+                    )
+                    .as_str(),
+                );
+            }
+            return Some(DataVar::new(symbol.to_string(), data_type?));
         }
 
         if symbol.is_some() && raw_type.is_some() {
             let (symbol, raw_type) = (symbol.unwrap(), raw_type.unwrap());
-            let data_type = DataType::from_type_default_value(raw_type).expect(
-                format!(
-                    "DataType creation has been failed at {}.\n value: {} type: {}",
-                    symbol, type_and_value[0], type_and_value[0]
-                )
-                .as_str(),
-            );
-            return Some(DataVar::new(symbol.to_string(), data_type));
+            let data_type = DataType::from_type_default_value(raw_type);
+            if data_type.is_none() {
+                Logger::error(
+                    format!(
+                        "DataType creation has been failed at {}.\n type: {}",
+                        symbol, raw_type
+                    )
+                    .as_str(),
+                );
+            }
+            return Some(DataVar::new(symbol.to_string(), data_type?));
         };
 
         None
     }
 
+    // helping to split script on top-level statements by semicolons
+    // example: let a = 'x;y'; onRead(b) -> [let a = 'x;y', onRead(b)]
+    fn split_on_statements(script: &str) -> Vec<String> {
+        let mut statements: Vec<String> = vec![];
+        let mut buffer = String::new();
+        let mut depth: usize = 0;
+        let mut capture_mode = false;
+        for e in script.chars() {
+            if capture_mode || e == '\'' {
+                buffer.push(e);
+                capture_mode = (e == '\'') != capture_mode;
+                continue;
+            }
+            match e {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                ';' if depth == 0 => {
+                    statements.push(buffer.trim().to_string());
+                    buffer.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            buffer.push(e);
+        }
+        statements.push(buffer.trim().to_string());
+        statements.into_iter().filter(|e| !e.is_empty()).collect()
+    }
+    // helping to split let statement on name, declared type and raw value
+    // example: let min_age: int = 18 -> (min_age, Some(int), 18)
+    fn split_let(raw_let: &str) -> Option<(String, Option<String>, String)> {
+        use crate::text_processing::ast::types_annotations::LET;
+
+        let rest = Util::strip_keyword(raw_let, LET)?;
+        let (name, raw_value) = match rest.find('=') {
+            Some(i) => (&rest[..i], rest[i + 1..].trim()),
+            None => return None,
+        };
+        let (name, raw_type) = match name.find(':') {
+            Some(i) => (name[..i].trim(), Some(name[i + 1..].trim().to_lowercase())),
+            None => (name.trim(), None),
+        };
+        if !Util::is_single_word(name.to_string())
+            || !name.chars().all(|e| e.is_alphanumeric() || e == '_')
+            || raw_value.is_empty()
+        {
+            Logger::error(format!("let not correctly in: {}", raw_let).as_str());
+            return None;
+        }
+        Some((name.to_lowercase(), raw_type, raw_value.to_string()))
+    }
+
+    // helping to evaluate constant expression, field names are not allowed
+    // example: 60 * 24 -> Int(1440)
+    fn split_constant_expr(raw_expression: &str) -> Option<DataType> {
+        let raw_expression = raw_expression.to_lowercase();
+        let expr = Rule::split_scalar_expr(raw_expression.as_str())?;
+        if expr.has_field() || expr.has_param() {
+            Logger::error(format!("unknown name in constant: {}", raw_expression).as_str());
+            return None;
+        }
+        Some(expr)
+            .filter(|e| !e.has_aggregate())
+            .and_then(|e| e.eval(&Record::new()))
+    }
+    // helping to split computed default on function and arguments
+    // example: nextval(users_id) -> NextVal(users_id)
+    fn split_default(raw_default: &str) -> Option<DefaultExpr> {
+        let tokens = Rule::split_on_scalar_tokens(raw_default);
+        match tokens.as_slice() {
+            [name, open, args @ .., close] if open == "(" && close == ")" => {
+                let args: Vec<String> = args.iter().filter(|e| *e != ",").cloned().collect();
+                DefaultExpr::from_string(name, &args)
+            }
+            _ => None,
+        }
+    }
+    // helping to split call of stored query on name and arguments
+    // example: call active_users(21, :name) -> (active_users, [21, :name])
+    fn split_call(raw_call: &str) -> Option<(String, Vec<String>)> {
+        use crate::text_processing::ast::types_annotations::CALL;
+
+        let rest = Util::strip_keyword(raw_call, CALL)?;
+        let (name, raw_args) = match (rest.find('('), rest.strip_suffix(')')) {
+            (Some(i), Some(rest)) => (rest[..i].trim(), &rest[i + 1..]),
+            _ => {
+                Logger::error(format!("call not correctly in: {}", raw_call).as_str());
+                return None;
+            }
+        };
+        let args = Rule::split_on_items(raw_args)
+            .iter()
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .collect();
+        Some((name.to_lowercase(), args))
+    }
+
+    // value of let binding: constant expression, channel list or other let binding
+    // example: 18, 'bob', 60 * 24, users, (users, mynode.orders), :min_age
+    pub fn get_let_value(
+        raw_value: &str,
+        raw_type: &Option<String>,
+        scope: &Scope,
+    ) -> Option<LetValue> {
+        use crate::text_processing::ast::types_annotations::SYMBOL;
+
+        let raw_value = raw_value.trim();
+        let placeholder = Placeholder::from_string(raw_value);
+        let value = if let Some(ParamKey::Name(name)) = placeholder.as_ref().map(|e| e.get_key()) {
+            scope.get(name.as_str())
+        } else {
+            let raw_items = match raw_value.strip_prefix('(') {
+                Some(rest) if rest.ends_with(')') => &rest[..rest.len() - 1],
+                _ => raw_value,
+            };
+            let items: Vec<String> = Rule::split_on_items(raw_items.to_lowercase().as_str())
+                .iter()
+                .map(|e| e.trim().to_string())
+                .collect();
+            let is_channels = items
+                .iter()
+                .all(|e| Util::identify_type(e) == SYMBOL && ChannelName::from_string(e).is_some());
+            if is_channels {
+                Rule::get_channels(items.join(",")).map(LetValue::Channels)
+            } else {
+                Rule::split_constant_expr(raw_value).map(LetValue::Value)
+            }
+        };
+        match (value, raw_type) {
+            (None, _) => {
+                Logger::error(format!("let value not correctly in: {}", raw_value).as_str());
+                None
+            }
+            (Some(LetValue::Value(value)), Some(raw_type))
+                if value != DataType::Null && value.get_type_name() != raw_type =>
+            {
+                Logger::error(
+                    format!(
+                        "let expects {} but {} is given: {}",
+                        raw_type,
+                        value.get_type_name(),
+                        raw_value
+                    )
+                    .as_str(),
+                );
+                None
+            }
+            (Some(LetValue::Channels(_)), Some(raw_type)) => {
+                Logger::error(format!("channels can not be typed as {}", raw_type).as_str());
+                None
+            }
+            (value, _) => value,
+        }
+    }
+
     pub fn get_argument_groups<T: Into<String>>(line: T) -> Vec<ArgumentGroup> {
-        let collection = Rule::split_on_raw_group(line);
+        let collection = Rule::split_on_typed_group(line);
 
         collection
             .iter()
-            .map(|e| ArgumentGroup::from_string(e))
+            .map(|(e, is_group)| match is_group {
+                true => ArgumentGroup::OtherGroup(e.to_lowercase()),
+                false => ArgumentGroup::from_string(e),
+            })
             .fold(vec![], |mut acc, e| {
                 if matches!(e, ArgumentGroup::FuncGroup(ref _x)) {
                     acc.push(ArgumentGroup::None);
@@ -148,26 +663,95 @@ impl Rule {
         FuncType::from_string(val.to_string())
     }
 
-    pub fn get_channels<T: ToString>(val: T) -> Option<Vec<DataType>> {
+    // channel may be qualified by namespace: mynode.orders
+    pub fn get_channels<T: ToString>(val: T) -> Option<Vec<ChannelName>> {
+        Rule::get_channels_in_scope(val, &Scope::new())
+    }
+
+    // channel list may use channels of let binding: onRead(:targets, audit)
+    pub fn get_channels_in_scope<T: ToString>(val: T, scope: &Scope) -> Option<Vec<ChannelName>> {
         let val: String = val.to_string();
-        let types: Vec<DataType> = val
-            .split(',')
-            .map(|e: &str| {
-                DataType::from_string(e.to_owned(), Util::identify_type(&e.to_owned())).unwrap()
-            })
+        let mut channels: Vec<ChannelName> = vec![];
+        for e in Rule::split_on_items(val.as_str()) {
+            let let_channels = e.strip_prefix(':').and_then(|e| scope.get_channels(e));
+            match ChannelName::from_string(&e) {
+                _ if let_channels.is_some() => channels.extend(let_channels.unwrap().to_owned()),
+                Some(channel) => channels.push(channel),
+                None => {
+                    Logger::error(format!("channel values not correctly in: {:?}", e).as_str());
+                    return None;
+                }
+            }
+        }
+        // every channel is executed once, so repeated channel is a script error
+        // bare name is compared by its resolved name: orders is mynode.orders
+        let resolved: Vec<ChannelName> = channels
+            .iter()
+            .map(|e| e.resolve(scope.get_node_path()))
             .collect();
+        let duplicate = resolved
+            .iter()
+            .enumerate()
+            .find(|(i, e)| resolved[..*i].contains(e));
+        if let Some((_, duplicate)) = duplicate {
+            Logger::error(format!("channel repeated in: {}", duplicate).as_str());
+            return None;
+        }
+        Some(channels)
+    }
 
-        let imbalance = types.iter().find(|e| match e {
-            DataType::Symbol(ref _val) => false,
-            _ => true,
-        });
-        if imbalance.is_none() {
-            Some(types)
-        } else {
-            Logger::error(
-                format!("channel values not correctly in: {:?}", imbalance.unwrap()).as_str(),
-            );
-            None
+    // items of group split by commas, error of item is logged and group is not parsed
+    fn split_items<T, F>(val: &str, kind: &str, split: F) -> Option<Vec<T>>
+    where
+        F: Fn(&str) -> Option<T>,
+    {
+        Rule::split_on_items(val)
+            .iter()
+            .map(|e| {
+                let item = split(e);
+                if item.is_none() {
+                    Logger::error(format!("{} error in: {:?}", kind, e).as_str());
+                }
+                item
+            })
+            .collect()
+    }
+
+    // part of statement which is required, error is logged when it's not parsed
+    fn required<T>(val: Option<T>, mes: &str) -> Option<T> {
+        if val.is_none() {
+            Logger::error(mes);
+        }
+        val
+    }
+
+    // group of statement which may be empty: None when it's empty, error when it's not parsed
+    // example: filter of onRead(users)() is empty
+    fn get_optional<T, F>(val: String, get: F) -> Option<Option<T>>
+    where
+        F: Fn(String) -> Option<T>,
+    {
+        if val.is_empty() {
+            return Some(None);
+        }
+        get(val).map(Some)
+    }
+
+    // clause of statement: None when it's not used, error when it's not parsed
+    fn get_parsed_clause<T, F>(
+        clauses: &[(String, Vec<String>)],
+        name: &str,
+        get: F,
+    ) -> Option<Option<T>>
+    where
+        F: Fn(String) -> Option<T>,
+    {
+        match Rule::get_clause(clauses, name) {
+            Some(val) => {
+                let parsed = get(val.to_owned());
+                Rule::required(parsed, format!("{} parsing error", name).as_str()).map(Some)
+            }
+            None => Some(None),
         }
     }
 
@@ -176,14 +760,7 @@ impl Rule {
         if val.is_empty() {
             return None;
         }
-        Some(
-            val.split(',')
-                .map(|e| {
-                    Rule::split_expression(e)
-                        .expect(format!("Expression error in: {:?}", e).as_str())
-                })
-                .collect(),
-        )
+        Rule::split_items(val.as_str(), "Expression", Rule::split_expression)
     }
 
     pub fn get_statements<T: ToString>(val: T) -> Option<Vec<DataVar>> {
@@ -191,87 +768,705 @@ impl Rule {
         if val.is_empty() {
             return None;
         }
-        Some(
-            val.split(',')
-                .map(|e| {
-                    Rule::split_statement(e).expect(format!("Statement error in: {:?}", e).as_str())
-                })
-                .collect(),
-        )
+        Rule::split_items(val.as_str(), "Statement", Rule::split_statement)
     }
-}
 
-trait Parser {
-    fn from_unary_func_expr<T: Into<String>>(line: T) -> Option<Vec<UnaryFuncExpr>> {
-        let argument_super_group = Rule::get_argument_groups(line);
-        let argument_groups: Vec<&[ArgumentGroup]> = argument_super_group
-            .split(|e| matches!(e, ArgumentGroup::None))
-            .filter(|e| !e.is_empty())
-            .collect();
-        let mut unary_func_expressions: Vec<UnaryFuncExpr> = vec![];
+    // projection of onRead: fields, computed expressions and aliases
+    // example: name, age + 1 as next_age
+    pub fn get_projection<T: ToString>(val: T) -> Option<Vec<Projection>> {
+        let val: String = val.to_string();
+        if val.is_empty() {
+            return None;
+        }
+        let projection = Rule::split_items(val.as_str(), "Projection", Rule::split_projection)?;
+        // fields of result are named by projection, so repeated name is a script error
+        let names: Vec<String> = projection.iter().map(|e| e.get_name()).collect();
+        let duplicate = names
+            .iter()
+            .enumerate()
+            .find(|(i, e)| names[..*i].contains(e));
+        if let Some((_, duplicate)) = duplicate {
+            Logger::error(format!("field repeated in projection: {}", duplicate).as_str());
+            return None;
+        }
+        Some(projection)
+    }
 
-        for argument_subgroups in argument_groups {
-            let func_type = Rule::get_func_type(&argument_subgroups[0].to_string())
-                .expect("function type not found");
-            let channels = argument_subgroups
-                .get(1)
-                .expect("channel not found")
-                .to_string();
-            let channels = Rule::get_channels(channels).expect("channels parsing error");
-
-            match func_type {
-                FuncType::OnCreate => {
-                    // func_type : Y, channels: Y, expressions: N, statements: Y
-                    let statements = argument_subgroups
-                        .get(2)
-                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
-                        .to_string();
-                    let statements = Rule::get_statements(statements);
-                    let unary_func_expr = UnaryFuncExpr::new(func_type, channels, None, statements);
-                    unary_func_expressions.push(unary_func_expr);
+    // split statement groups on positional groups and clauses
+    // example: onRead(a)(x>2) orderBy(x) limit(10) -> [onRead, a, x>2], [(orderby, [x]), (limit, [10])]
+    pub fn get_clauses(
+        argument_subgroups: &[ArgumentGroup],
+    ) -> Option<(&[ArgumentGroup], Vec<(String, Vec<String>)>)> {
+        use crate::text_processing::ast::types_annotations::{JOIN, LEFTJOIN};
+
+        let position = argument_subgroups
+            .iter()
+            .position(|e| matches!(e, ArgumentGroup::ClauseGroup(ref _x)))
+            .unwrap_or(argument_subgroups.len());
+        let (positional, rest) = argument_subgroups.split_at(position);
+
+        let mut clauses: Vec<(String, Vec<String>)> = vec![];
+        for group in rest {
+            match group {
+                ArgumentGroup::ClauseGroup(name) => {
+                    let is_repeatable = name == JOIN || name == LEFTJOIN;
+                    if !is_repeatable && clauses.iter().any(|(e, _)| e == name) {
+                        Logger::error(format!("clause repeated: {}", name).as_str());
+                        return None;
+                    }
+                    clauses.push((name.to_owned(), vec![]));
                 }
-                FuncType::OnRead => {
-                    // func_type : Y, channels: Y, expressions: Y, statements: N
-                    let expressions = argument_subgroups
-                        .get(2)
-                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
-                        .to_string();
-                    let expressions = Rule::get_expressions(expressions);
-                    let unary_func_expr =
-                        UnaryFuncExpr::new(func_type, channels, expressions, None);
-                    unary_func_expressions.push(unary_func_expr);
+                ArgumentGroup::OtherGroup(val) => clauses.last_mut()?.1.push(val.to_owned()),
+                _ => return None,
+            }
+        }
+        if let Some((name, _)) = clauses.iter().find(|(_, groups)| groups.is_empty()) {
+            Logger::error(format!("clause without arguments: {}", name).as_str());
+            return None;
+        }
+        Some((positional, clauses))
+    }
+
+    // split statement groups on other groups and read modifiers, modifiers are put after groups
+    // example: onRead(a) history as of version 12 limit(1) -> [onRead, a, limit, 1], Some(Version(12)), true
+    pub fn get_read_modifiers(
+        argument_subgroups: &[ArgumentGroup],
+    ) -> Option<(Vec<ArgumentGroup>, Option<AsOf>, bool)> {
+        use crate::text_processing::ast::types_annotations::{AS, HISTORY, OF, TIMESTAMP, VERSION};
+
+        let mut rest: Vec<ArgumentGroup> = vec![];
+        let (mut as_of, mut history) = (None, false);
+        let mut position = 0;
+        while position < argument_subgroups.len() {
+            if !matches!(
+                argument_subgroups[position],
+                ArgumentGroup::ModifierGroup(ref _x)
+            ) {
+                rest.push(argument_subgroups[position].clone());
+                position += 1;
+                continue;
+            }
+            let words: Vec<String> = argument_subgroups[position..]
+                .iter()
+                .take(4)
+                .map(|e| e.to_string())
+                .collect();
+            let words: Vec<&str> = words.iter().map(|e| e.as_str()).collect();
+            // count of groups of modifier
+            position += match words.as_slice() {
+                [HISTORY, ..] if !history => {
+                    history = true;
+                    1
                 }
-                FuncType::OnUpdate => {
-                    // func_type : Y, channels: Y, expressions: Y, statements: Y
-                    let expressions = argument_subgroups
-                        .get(2)
-                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
-                        .to_string();
-                    let expressions = Rule::get_expressions(expressions);
-                    let statements = argument_subgroups
-                        .get(3)
-                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
-                        .to_string();
-                    let statements = Rule::get_statements(statements);
-                    let unary_func_expr =
-                        UnaryFuncExpr::new(func_type, channels, expressions, statements);
-                    unary_func_expressions.push(unary_func_expr);
+                [AS, OF, VERSION, value] if as_of.is_none() => {
+                    as_of = Some(AsOf::Version(value.parse().ok()?));
+                    4
                 }
-                FuncType::OnDelete => {
-                    // func_type : Y, channels: Y, expressions: N, statements: N
-                    let unary_func_expr = UnaryFuncExpr::new(func_type, channels, None, None);
-                    unary_func_expressions.push(unary_func_expr);
+                [AS, OF, TIMESTAMP, value] if as_of.is_none() => {
+                    as_of = Some(AsOf::Timestamp(value.parse().ok()?));
+                    4
+                }
+                _ => {
+                    let mes = format!("read modifier is not correctly: {}", words.join(" "));
+                    Logger::error(mes.as_str());
+                    return None;
+                }
+            };
+        }
+        Some((rest, as_of, history))
+    }
+
+    // first group of clause
+    fn get_clause<'a>(clauses: &'a [(String, Vec<String>)], name: &str) -> Option<&'a String> {
+        clauses
+            .iter()
+            .find(|(e, _)| e == name)
+            .and_then(|(_, groups)| groups.first())
+    }
+
+    // join and leftJoin clauses of onRead, in order of script
+    // condition compares field of joined channel with field of read channels or channel joined before
+    // example: join(users)(orders.user_id == users.id) leftJoin(items)(items.order_id == orders.id)
+    pub fn get_joins(
+        channels: &[ChannelName],
+        clauses: &[(String, Vec<String>)],
+    ) -> Option<Vec<Join>> {
+        use crate::text_processing::ast::types_annotations::{JOIN, LEFTJOIN};
+
+        let mut joined: Vec<String> = channels.iter().map(|e| e.get_name().to_owned()).collect();
+        let mut joins: Vec<Join> = vec![];
+        for (name, groups) in clauses {
+            let kind = match name.as_str() {
+                JOIN => JoinKind::Inner,
+                LEFTJOIN => JoinKind::Left,
+                _ => continue,
+            };
+            let (channel, on) = match groups.as_slice() {
+                [channel, on] => (channel, on),
+                _ => {
+                    Logger::error(format!("{} needs channel and condition", name).as_str());
+                    return None;
                 }
+            };
+            let mut channels = Rule::get_channels(channel)?;
+            let on = Rule::split_expression(on)?;
+            if channels.len() != 1 || *on.get_operator() != CompareOperator::Eq {
+                Logger::error(format!("{} supports one channel and == condition", name).as_str());
+                return None;
+            }
+            let join = Join::new(kind, channels.remove(0), on);
+            let is_joined =
+                |key: &String| joined.iter().any(|e| key.starts_with(&format!("{}.", e)));
+            if !matches!(join.get_keys(), Some((key, _)) if is_joined(key)) {
+                Logger::error(
+                    format!(
+                        "condition of {} must compare field of joined channel with field of read channel: {:?}",
+                        name,
+                        join.get_on()
+                    )
+                    .as_str(),
+                );
+                return None;
             }
+            joined.push(join.get_channel().get_name().to_owned());
+            joins.push(join);
         }
-        if !unary_func_expressions.is_empty() {
-            return Some(unary_func_expressions);
+        if joins.is_empty() {
+            return None;
         }
-        None
+        Some(joins)
     }
-}
 
-struct ParserDefault;
+    // orderBy clause of onRead
+    // example: orderBy(created desc nulls first, name)
+    pub fn get_order_by<T: ToString>(val: T) -> Option<Vec<OrderBy>> {
+        let val: String = val.to_string();
+        if val.is_empty() {
+            return None;
+        }
+        Rule::split_items(val.as_str(), "OrderBy", Rule::split_order_by)
+    }
+
+    // groupBy clause of onRead
+    // example: groupBy(country, city)
+    pub fn get_group_by<T: ToString>(val: T) -> Option<Vec<ScalarExpr>> {
+        let val: String = val.to_string();
+        if val.is_empty() {
+            return None;
+        }
+        let group_by = Rule::split_items(
+            val.as_str(),
+            "GroupBy",
+            Rule::split_scalar_expr_without_params,
+        )?;
+        if let Some(aggregate) = group_by.iter().find(|e| e.has_aggregate()) {
+            Logger::error(format!("aggregate in groupBy: {}", aggregate).as_str());
+            return None;
+        }
+        Some(group_by)
+    }
+
+    // having clause of onRead, filter for groups
+    // terms are fields of result: alias or text of expression
+    // example: having(count(*) > 5, total >= 100)
+    pub fn get_having<T: ToString>(val: T) -> Option<Vec<BinaryExpr>> {
+        // aggregate term is field of result named by text of expression
+        let as_field = |term: &ScalarExpr| match term.has_aggregate() {
+            true => ScalarExpr::Value(DataType::Symbol(term.to_string())),
+            false => term.clone(),
+        };
+        let having = Rule::get_expressions(val)?
+            .iter()
+            .map(|e| {
+                BinaryExpr::from_scalar_exprs(
+                    as_field(e.get_lterm()),
+                    as_field(e.get_rterm()),
+                    *e.get_operator(),
+                )
+            })
+            .collect();
+        Some(having)
+    }
+
+    // check projection of grouped onRead: every field without aggregate must be in groupBy
+    // example: (country, count(*)) groupBy(country)
+    pub fn check_group_projection(
+        projection: &Option<Vec<Projection>>,
+        group_by: &Option<Vec<ScalarExpr>>,
+    ) -> bool {
+        // function of grouped fields is grouped too: lower(country) groupBy(country)
+        fn is_grouped(expr: &ScalarExpr, group_by: &[ScalarExpr]) -> bool {
+            match expr {
+                _ if group_by.contains(expr) => true,
+                ScalarExpr::Call(_, args) => args.iter().all(|e| is_grouped(e, group_by)),
+                _ => !expr.has_field(),
+            }
+        }
+        let empty: Vec<ScalarExpr> = vec![];
+        let group_by = group_by.as_ref().unwrap_or(&empty);
+        let ungrouped = projection
+            .iter()
+            .flatten()
+            .find(|e| !e.get_expr().has_aggregate() && !is_grouped(e.get_expr(), group_by));
+        if let Some(ungrouped) = ungrouped {
+            Logger::error(
+                format!(
+                    "field {} must be in groupBy or aggregate",
+                    ungrouped.get_expr()
+                )
+                .as_str(),
+            );
+            return false;
+        }
+        true
+    }
+
+    // returning clause of mutations: fields of changed records
+    // example: onCreate(users)(name: text = 'bob') returning(id, name)
+    pub fn get_returning<T: ToString>(val: T) -> Option<Vec<Projection>> {
+        let returning = Rule::get_projection(val)?;
+        if let Some(aggregated) = returning.iter().find(|e| e.get_expr().has_aggregate()) {
+            Logger::error(format!("aggregate in returning: {}", aggregated.get_expr()).as_str());
+            return None;
+        }
+        Some(returning)
+    }
+
+    // ttl clause of onCreate: seconds of life of record or field with time of expiry
+    // example: ttl(3600), ttl(expires_at)
+    pub fn get_expiry<T: ToString>(val: T) -> Option<Expiry> {
+        let val = val.to_string();
+        let val = val.trim();
+        if let Ok(seconds) = val.parse::<u64>() {
+            return Some(Expiry::Ttl(seconds));
+        }
+        let is_field = val.starts_with(|e: char| e.is_alphabetic() || e == '_')
+            && val.chars().all(|e| e.is_alphanumeric() || e == '_');
+        match is_field {
+            true => Some(Expiry::Field(val.to_string())),
+            false => {
+                Logger::error(format!("ttl is not correctly: {}", val).as_str());
+                None
+            }
+        }
+    }
+
+    // limit clause of onRead, example: limit(10)
+    pub fn get_limit<T: ToString>(val: T) -> Option<usize> {
+        Rule::split_count(val.to_string().as_str())
+    }
+
+    // offset clause of onRead, example: offset(20)
+    pub fn get_offset<T: ToString>(val: T) -> Option<usize> {
+        Rule::split_count(val.to_string().as_str())
+    }
+}
+
+pub trait Parser {
+    // parse script: let bindings, stored queries and statements separated by semicolons
+    // let binding is visible in next statements of script and in next scripts with same scope
+    // call of stored query is expanded into statements of its body
+    // placeholders of statements are bound by let bindings and params of scope
+    // example: let adult = 18; onRead(users)(age >= :adult)
+    fn from_script<T: Into<String>>(script: T, scope: &mut Scope) -> Option<Vec<UnaryFuncExpr>> {
+        let blocks = Self::from_script_blocks(script, scope)?;
+        Some(blocks.into_iter().flat_map(Block::into_exprs).collect())
+    }
+
+    // text of blocks of script, transaction block is kept with its begin and commit (or rollback)
+    // example: a; b; begin; c; commit; d -> [a; b, begin; c; commit, d]
+    fn split_on_blocks<T: Into<String>>(script: T) -> Vec<String> {
+        use crate::text_processing::ast::types_annotations::{BEGIN, COMMIT, ROLLBACK};
+
+        let script: String = script.into();
+        let mut blocks: Vec<String> = vec![];
+        let mut statements: Vec<String> = vec![];
+        for statement in Rule::split_on_statements(script.as_str()) {
+            let keyword = statement.to_lowercase();
+            if keyword == BEGIN && !statements.is_empty() {
+                blocks.push(statements.join("; "));
+                statements.clear();
+            }
+            statements.push(statement);
+            if keyword == COMMIT || keyword == ROLLBACK {
+                blocks.push(statements.join("; "));
+                statements.clear();
+            }
+        }
+        if !statements.is_empty() {
+            blocks.push(statements.join("; "));
+        }
+        blocks
+    }
+
+    // parse script on blocks of statements
+    // statements between begin and commit (or rollback) are one transaction block,
+    // statements outside of transaction are applied one by one
+    // let and define of block ended by rollback are discarded with block
+    // example: begin; onUpdate(a)()(x: int = 1); onUpdate(b)()(x: int = 1); commit
+    fn from_script_blocks<T: Into<String>>(script: T, scope: &mut Scope) -> Option<Vec<Block>> {
+        use crate::text_processing::ast::types_annotations::{
+            BEGIN, COMMIT, DEFINE, EXPLAIN, JSON, ROLLBACK,
+        };
+
+        let script: String = script.into();
+        let mut blocks: Vec<Block> = vec![];
+        let mut unary_func_expressions: Vec<UnaryFuncExpr> = vec![];
+        let mut is_transaction = false;
+        // scope before transaction, it's restored by rollback
+        let mut saved_scope = scope.clone();
+        for statement in Rule::split_on_statements(script.as_str()) {
+            let keyword = statement.to_lowercase();
+            match keyword.as_str() {
+                BEGIN if is_transaction => {
+                    Logger::error("transaction is already started");
+                    return None;
+                }
+                BEGIN => {
+                    if !unary_func_expressions.is_empty() {
+                        blocks.push(Block::new(BlockKind::AutoCommit, unary_func_expressions));
+                    }
+                    unary_func_expressions = vec![];
+                    is_transaction = true;
+                    saved_scope = scope.clone();
+                    continue;
+                }
+                COMMIT | ROLLBACK if !is_transaction => {
+                    Logger::error(format!("{} without begin", keyword).as_str());
+                    return None;
+                }
+                COMMIT | ROLLBACK => {
+                    let kind = match keyword.as_str() {
+                        COMMIT => BlockKind::Commit,
+                        _ => {
+                            *scope = saved_scope.clone();
+                            BlockKind::Rollback
+                        }
+                    };
+                    blocks.push(Block::new(kind, unary_func_expressions));
+                    unary_func_expressions = vec![];
+                    is_transaction = false;
+                    continue;
+                }
+                _ => {}
+            }
+            // plan is shown instead of result
+            // example: explain onRead(users)(age > 18), explain json onRead(users)
+            if let Some(rest) = Util::strip_keyword(statement.as_str(), EXPLAIN) {
+                let (format, rest) = match Util::strip_keyword(rest, JSON) {
+                    Some(rest) => (ExplainFormat::Json, rest),
+                    None => (ExplainFormat::Text, rest),
+                };
+                for unary_func_expr in Self::from_unary_func_expr_in_scope(rest, scope)? {
+                    let unary_func_expr = scope.get_params().bind(&unary_func_expr)?;
+                    unary_func_expressions.push(unary_func_expr.with_explain(Some(format)));
+                }
+                continue;
+            }
+            if let Some((name, raw_type, raw_value)) = Rule::split_let(statement.as_str()) {
+                let value = Rule::get_let_value(raw_value.as_str(), &raw_type, scope)?;
+                scope.insert(name, value)?;
+                continue;
+            }
+            if Util::strip_keyword(statement.as_str(), DEFINE).is_some() {
+                let definition = Definition::from_string(statement.as_str())?;
+                Self::from_definition(&definition, &[], scope, true)?;
+                scope.define(definition);
+                continue;
+            }
+            if let Some((name, args)) = Rule::split_call(statement.as_str()) {
+                let definition = match scope.get_definition(name.as_str()) {
+                    Some(definition) => definition.to_owned(),
+                    None => {
+                        Logger::error(format!("definition not found: {}", name).as_str());
+                        return None;
+                    }
+                };
+                let expanded = Self::from_definition(&definition, &args, scope, false)?;
+                unary_func_expressions.extend(expanded);
+                continue;
+            }
+            for unary_func_expr in Self::from_unary_func_expr_in_scope(statement, scope)? {
+                unary_func_expressions.push(scope.get_params().bind(&unary_func_expr)?);
+            }
+        }
+        if is_transaction {
+            Logger::error("transaction is not closed by commit or rollback");
+            return None;
+        }
+        if !unary_func_expressions.is_empty() {
+            blocks.push(Block::new(BlockKind::AutoCommit, unary_func_expressions));
+        }
+        Some(blocks)
+    }
+
+    // expand stored query into statements, arguments are type-checked by parameter types
+    // placeholders of body are bound only by parameters, not by params and lets of caller
+    // on define body is checked with default values of parameters
+    fn from_definition(
+        definition: &Definition,
+        args: &[String],
+        scope: &Scope,
+        is_check: bool,
+    ) -> Option<Vec<UnaryFuncExpr>> {
+        if !is_check && args.len() != definition.get_params().len() {
+            Logger::error(
+                format!(
+                    "{} expects {} arguments but {} are given",
+                    definition.get_name(),
+                    definition.get_params().len(),
+                    args.len()
+                )
+                .as_str(),
+            );
+            return None;
+        }
+        let mut params = Params::new();
+        for (i, (name, raw_type)) in definition.get_params().iter().enumerate() {
+            let value = match args.get(i) {
+                Some(arg) => {
+                    match Rule::get_let_value(arg.as_str(), &Some(raw_type.to_owned()), scope)? {
+                        LetValue::Value(value) => value,
+                        LetValue::Channels(_) => return None,
+                    }
+                }
+                None => DataType::from_type_default_value(raw_type)?,
+            };
+            params.insert(name, value);
+        }
+        // error of body is error of script
+        let body = definition.get_body();
+        match Self::from_unary_func_expr_in_scope(body, scope) {
+            Some(exprs) => exprs.iter().map(|e| params.bind(e)).collect(),
+            None => {
+                Logger::error(
+                    format!(
+                        "body of {} is not correctly: {}",
+                        definition.get_name(),
+                        body
+                    )
+                    .as_str(),
+                );
+                None
+            }
+        }
+    }
+
+    fn from_unary_func_expr<T: Into<String>>(line: T) -> Option<Vec<UnaryFuncExpr>> {
+        Self::from_unary_func_expr_in_scope(line, &Scope::new())
+    }
+
+    fn from_unary_func_expr_in_scope<T: Into<String>>(
+        line: T,
+        scope: &Scope,
+    ) -> Option<Vec<UnaryFuncExpr>> {
+        use crate::text_processing::ast::types_annotations::{
+            GROUPBY, HAVING, JOIN, LEFTJOIN, LIMIT, OFFSET, ORDERBY, RETURNING, TTL,
+        };
+
+        let argument_super_group = Rule::get_argument_groups(line);
+        let argument_groups: Vec<&[ArgumentGroup]> = argument_super_group
+            .split(|e| matches!(e, ArgumentGroup::None))
+            .filter(|e| !e.is_empty())
+            .collect();
+        let mut unary_func_expressions: Vec<UnaryFuncExpr> = vec![];
+
+        for argument_subgroups in argument_groups {
+            let (argument_subgroups, as_of, history) = Rule::required(
+                Rule::get_read_modifiers(argument_subgroups),
+                "read modifiers parsing error",
+            )?;
+            let (argument_subgroups, clauses) = Rule::required(
+                Rule::get_clauses(&argument_subgroups),
+                "clauses parsing error",
+            )?;
+            let func_type = Rule::required(
+                Rule::get_func_type(&argument_subgroups[0].to_string()),
+                "function type not found",
+            )?;
+            if (as_of.is_some() || history) && func_type != FuncType::OnRead {
+                let mes = format!("read modifier is not allowed for {:?}", func_type);
+                Logger::error(mes.as_str());
+                return None;
+            }
+            let allowed_clauses: &[&str] = match func_type {
+                FuncType::OnRead => &[JOIN, LEFTJOIN, GROUPBY, HAVING, ORDERBY, LIMIT, OFFSET],
+                FuncType::OnWatch | FuncType::OnIndex => &[],
+                FuncType::OnCreate => &[RETURNING, TTL],
+                _ => &[RETURNING],
+            };
+            if let Some((name, _)) = clauses
+                .iter()
+                .find(|(e, _)| !allowed_clauses.contains(&e.as_str()))
+            {
+                let mes = format!("clause {} is not allowed for {:?}", name, func_type);
+                Logger::error(mes.as_str());
+                return None;
+            }
+            let channels =
+                Rule::required(argument_subgroups.get(1), "channel not found")?.to_string();
+            let channels = Rule::required(
+                Rule::get_channels_in_scope(channels, scope),
+                "channels parsing error",
+            )?;
+
+            let unary_func_expr = match func_type {
+                FuncType::OnCreate => {
+                    // func_type : Y, channels: Y, expressions: N, statements: Y
+                    let statements = argument_subgroups
+                        .get(2)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let statements = Rule::get_optional(statements, Rule::get_statements)?;
+                    let expiry = Rule::get_parsed_clause(&clauses, TTL, Rule::get_expiry)?;
+                    UnaryFuncExpr::new(func_type, channels, None, statements).with_expiry(expiry)
+                }
+                FuncType::OnRead => {
+                    // func_type : Y, channels: Y, expressions: Y, statements: N, projection: Y
+                    let expressions = argument_subgroups
+                        .get(2)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let expressions = Rule::get_optional(expressions, Rule::get_expressions)?;
+                    let projection = argument_subgroups
+                        .get(3)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let projection = Rule::get_optional(projection, Rule::get_projection)?;
+                    let joins = Rule::get_joins(&channels, &clauses);
+                    let join_count = clauses
+                        .iter()
+                        .filter(|(e, _)| e == JOIN || e == LEFTJOIN)
+                        .count();
+                    if joins.as_ref().map_or(0, |e| e.len()) != join_count {
+                        Logger::error("join parsing error");
+                        return None;
+                    }
+                    let group_by = Rule::get_parsed_clause(&clauses, GROUPBY, Rule::get_group_by)?;
+                    let having = Rule::get_parsed_clause(&clauses, HAVING, Rule::get_having)?;
+                    let order_by = Rule::get_parsed_clause(&clauses, ORDERBY, Rule::get_order_by)?;
+                    let limit = Rule::get_parsed_clause(&clauses, LIMIT, Rule::get_limit)?;
+                    let offset = Rule::get_parsed_clause(&clauses, OFFSET, Rule::get_offset)?;
+                    let unary_func_expr =
+                        UnaryFuncExpr::new(func_type, channels, expressions, None)
+                            .with_projection(projection)
+                            .with_joins(joins)
+                            .with_group_by(group_by)
+                            .with_having(having)
+                            .with_order_by(order_by)
+                            .with_limit(limit)
+                            .with_offset(offset);
+                    if unary_func_expr.is_aggregate() {
+                        let is_correct = Rule::check_group_projection(
+                            unary_func_expr.get_projection(),
+                            unary_func_expr.get_group_by(),
+                        );
+                        if !is_correct {
+                            return None;
+                        }
+                    } else if unary_func_expr.get_having().is_some() {
+                        Logger::error("having is used without groupBy or aggregate");
+                        return None;
+                    }
+                    // versions of joined records are not matched by time of version
+                    if history && unary_func_expr.get_joins().is_some() {
+                        Logger::error("history is used with join");
+                        return None;
+                    }
+                    unary_func_expr.with_as_of(as_of).with_history(history)
+                }
+                FuncType::OnUpsert => {
+                    // func_type : Y, channels: Y, expressions: Y, statements: Y
+                    // record is created from equalities of expressions and statements
+                    let expressions = argument_subgroups
+                        .get(2)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let expressions = Rule::required(
+                        Rule::get_expressions(expressions),
+                        "onUpsert needs filter",
+                    )?;
+                    let statements = argument_subgroups
+                        .get(3)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let statements = Rule::required(
+                        Rule::get_statements(statements),
+                        "onUpsert needs statements",
+                    )?;
+                    let unary_func_expr = UnaryFuncExpr::new(
+                        func_type,
+                        channels,
+                        Some(expressions),
+                        Some(statements),
+                    );
+                    unary_func_expr
+                }
+                FuncType::OnUpdate => {
+                    // func_type : Y, channels: Y, expressions: Y, statements: Y
+                    let expressions = argument_subgroups
+                        .get(2)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let expressions = Rule::get_optional(expressions, Rule::get_expressions)?;
+                    let statements = argument_subgroups
+                        .get(3)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let statements = Rule::get_optional(statements, Rule::get_statements)?;
+                    let unary_func_expr =
+                        UnaryFuncExpr::new(func_type, channels, expressions, statements);
+                    unary_func_expr
+                }
+                FuncType::OnDelete => {
+                    // func_type : Y, channels: Y, expressions: Y, statements: N
+                    // without expressions every record of channel is deleted
+                    let expressions = argument_subgroups
+                        .get(2)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let expressions = Rule::get_optional(expressions, Rule::get_expressions)?;
+                    let unary_func_expr =
+                        UnaryFuncExpr::new(func_type, channels, expressions, None);
+                    unary_func_expr
+                }
+                FuncType::OnWatch => {
+                    // func_type : Y, channels: Y, expressions: Y, statements: N
+                    let expressions = argument_subgroups
+                        .get(2)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let expressions = Rule::get_optional(expressions, Rule::get_expressions)?;
+                    UnaryFuncExpr::new(func_type, channels, expressions, None)
+                }
+                FuncType::OnIndex => {
+                    // func_type : Y, channels: Y, expressions: N, statements: N, projection: Y
+                    // indexed field is kept as projection of one field
+                    let projection = argument_subgroups
+                        .get(2)
+                        .unwrap_or(&ArgumentGroup::OtherGroup("".to_string()))
+                        .to_string();
+                    let projection = Rule::get_projection(projection).filter(|e| {
+                        e.len() == 1
+                            && e[0].get_alias().is_none()
+                            && matches!(e[0].get_expr(), ScalarExpr::Value(DataType::Symbol(_)))
+                    });
+                    let projection = Rule::required(projection, "onIndex needs one field")?;
+                    UnaryFuncExpr::new(func_type, channels, None, None)
+                        .with_projection(Some(projection))
+                }
+            };
+            let returning = Rule::get_parsed_clause(&clauses, RETURNING, Rule::get_returning)?;
+            unary_func_expressions.push(unary_func_expr.with_returning(returning));
+        }
+        if !unary_func_expressions.is_empty() {
+            return Some(unary_func_expressions);
+        }
+        None
+    }
+}
+
+pub struct ParserDefault;
 impl ParserDefault {
     pub fn from_unary_func_expr_callback<
         T: Into<String>,
@@ -289,7 +1484,7 @@ impl ParserDefault {
 impl Parser for ParserDefault {}
 
 mod test {
-    use crate::text_processing::parser::states::{Rule, ParserDefault};
+    use crate::text_processing::parser::states::{Parser, ParserDefault, Rule};
     // todo: add more tests
 
     #[test]
@@ -312,11 +1507,799 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_split_on_typed_group() -> Result<(), ()> {
+        let result = Rule::split_on_typed_group("onRead(users)()(name  as n, concat(a, 'b  c'))");
+        let test_vec = vec![
+            ("onRead".to_string(), false),
+            ("users".to_string(), true),
+            ("".to_string(), true),
+            ("name as n,concat(a,'b  c')".to_string(), true),
+        ];
+        assert_eq!(result, test_vec);
+        assert_eq!(
+            vec!["a", "concat(b,c)", "'d,e'"],
+            Rule::split_on_items("a,concat(b,c),'d,e'")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_projection() -> Result<(), ()> {
+        use crate::text_processing::ast::types::{ArithOperator, DataType, ScalarExpr};
+        let projection = Rule::get_projection("name as n,price*(qty+1),-2").unwrap();
+        assert_eq!(3, projection.len());
+        assert_eq!("n", projection[0].get_name());
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Symbol("name".to_string())),
+            projection[0].get_expr()
+        );
+        assert_eq!("price*(qty+1)", projection[1].get_name());
+        assert_eq!(
+            &ScalarExpr::Arithmetic(
+                Box::new(ScalarExpr::Value(DataType::Symbol("price".to_string()))),
+                ArithOperator::Mul,
+                Box::new(ScalarExpr::Arithmetic(
+                    Box::new(ScalarExpr::Value(DataType::Symbol("qty".to_string()))),
+                    ArithOperator::Add,
+                    Box::new(ScalarExpr::Value(DataType::Int(1)))
+                ))
+            ),
+            projection[1].get_expr()
+        );
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Int(-2)),
+            projection[2].get_expr()
+        );
+        // names of different expressions are different
+        let projection = Rule::get_projection("a*(b+1),a*b+1,a-(b-c),(a-b)-c").unwrap();
+        let names: Vec<String> = projection.iter().map(|e| e.get_name()).collect();
+        assert_eq!(vec!["a*(b+1)", "a*b+1", "a-(b-c)", "a-b-c"], names);
+        // same name of result field is rejected
+        assert_eq!(None, Rule::get_projection("a*b+1,(a*b)+1"));
+        assert_eq!(None, Rule::get_projection("name,id as name"));
+        assert_eq!(None, Rule::split_projection("a+"));
+        assert_eq!(None, Rule::split_projection("a as b c"));
+
+        let unary_func_expressions =
+            ParserDefault::from_unary_func_expr("onRead(users)(age > 18)(name, email as e)")
+                .unwrap();
+        let projection = unary_func_expressions[0].get_projection().as_ref().unwrap();
+        assert_eq!("name", projection[0].get_name());
+        assert_eq!("e", projection[1].get_name());
+        let unary_func_expressions =
+            ParserDefault::from_unary_func_expr("onRead(users)()(name)").unwrap();
+        assert_eq!(true, unary_func_expressions[0].get_binary_exprs().is_none());
+        assert_eq!(true, unary_func_expressions[0].get_projection().is_some());
+        assert_eq!(
+            true,
+            ParserDefault::from_unary_func_expr("onRead(users)()(name, name)").is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_order_by_limit_offset() -> Result<(), ()> {
+        use crate::text_processing::ast::types::{NullsOrder, SortDirection};
+        let order_by = Rule::get_order_by("created desc,name,age asc nulls first").unwrap();
+        assert_eq!(SortDirection::Desc, order_by[0].get_direction());
+        assert_eq!(NullsOrder::First, order_by[0].get_nulls());
+        assert_eq!("created", order_by[0].get_expr().to_string());
+        assert_eq!(SortDirection::Asc, order_by[1].get_direction());
+        assert_eq!(NullsOrder::Last, order_by[1].get_nulls());
+        assert_eq!(NullsOrder::First, order_by[2].get_nulls());
+        assert_eq!(
+            "descending",
+            Rule::get_order_by("descending").unwrap()[0]
+                .get_expr()
+                .to_string()
+        );
+
+        let unary_func_expressions = ParserDefault::from_unary_func_expr(
+            "onRead(users)(age > 18)(name) orderBy(created desc) limit(10) offset(20)",
+        )
+        .unwrap();
+        let unary_func_expr = &unary_func_expressions[0];
+        assert_eq!(1, unary_func_expr.get_order_by().as_ref().unwrap().len());
+        assert_eq!(Some(10), unary_func_expr.get_limit());
+        assert_eq!(Some(20), unary_func_expr.get_offset());
+
+        let unary_func_expressions =
+            ParserDefault::from_unary_func_expr("onRead(users) limit(5); onRead(users)").unwrap();
+        assert_eq!(2, unary_func_expressions.len());
+        assert_eq!(Some(5), unary_func_expressions[0].get_limit());
+        assert_eq!(None, unary_func_expressions[1].get_limit());
+
+        assert_eq!(None, Rule::get_limit("-1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_aggregates() -> Result<(), ()> {
+        use crate::text_processing::ast::types::{AggregateFunc, ScalarExpr};
+        let projection =
+            Rule::get_projection("country,count(*),count(distinct city) as c,sum(a)/count(a)")
+                .unwrap();
+        assert_eq!(
+            &ScalarExpr::Aggregate(AggregateFunc::Count, None),
+            projection[1].get_expr()
+        );
+        assert_eq!("count(*)", projection[1].get_name());
+        assert_eq!("count(distinct city)", projection[2].get_expr().to_string());
+        assert_eq!(true, projection[3].get_expr().has_aggregate());
+        assert_eq!(None, Rule::split_scalar_expr("sum(max(a))"));
+        assert_eq!(None, Rule::split_scalar_expr("unknown(a)"));
+
+        let unary_func_expressions = ParserDefault::from_unary_func_expr(
+            "onRead(users)()(country, count(*) as n) groupBy(country) having(n > 5) orderBy(n desc)",
+        )
+        .unwrap();
+        let unary_func_expr = &unary_func_expressions[0];
+        assert_eq!(true, unary_func_expr.is_aggregate());
+        assert_eq!(1, unary_func_expr.get_group_by().as_ref().unwrap().len());
+        assert_eq!(1, unary_func_expr.get_having().as_ref().unwrap().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_joins() -> Result<(), ()> {
+        use crate::text_processing::ast::types::{ChannelName, JoinKind};
+        let unary_func_expressions = ParserDefault::from_unary_func_expr(
+            "onRead(orders)(orders.total > 10)(orders.id, users.name, items.title) \
+             join(users)(orders.user_id == users.id) \
+             leftJoin(items)(items.order_id == orders.id) limit(5)",
+        )
+        .unwrap();
+        let joins = unary_func_expressions[0].get_joins().as_ref().unwrap();
+        assert_eq!(2, joins.len());
+        assert_eq!(JoinKind::Inner, joins[0].get_kind());
+        assert_eq!("users", joins[0].get_channel().to_string());
+        assert_eq!(JoinKind::Left, joins[1].get_kind());
+        let (key, joined_key) = joins[1].get_keys().unwrap();
+        assert_eq!(
+            ("orders.id", "items.order_id"),
+            (key.as_str(), joined_key.as_str())
+        );
+        assert_eq!(Some(5), unary_func_expressions[0].get_limit());
+
+        let orders = vec![ChannelName::from_string("orders").unwrap()];
+        let clauses = vec![(
+            "join".to_string(),
+            vec!["users".to_string(), "orders.user_id>users.id".to_string()],
+        )];
+        assert_eq!(None, Rule::get_joins(&orders, &clauses));
+        let clauses = vec![(
+            "join".to_string(),
+            vec!["users".to_string(), "orders.user_id==items.id".to_string()],
+        )];
+        assert_eq!(None, Rule::get_joins(&orders, &clauses));
+        // both fields of joined channel
+        let clauses = vec![(
+            "join".to_string(),
+            vec!["users".to_string(), "users.id==users.x".to_string()],
+        )];
+        assert_eq!(None, Rule::get_joins(&orders, &clauses));
+        // field of channel which is not read
+        let clauses = vec![(
+            "join".to_string(),
+            vec!["users".to_string(), "items.user_id==users.id".to_string()],
+        )];
+        assert_eq!(None, Rule::get_joins(&orders, &clauses));
+        // field of channel joined before
+        let clauses = vec![
+            (
+                "join".to_string(),
+                vec!["users".to_string(), "orders.user_id==users.id".to_string()],
+            ),
+            (
+                "join".to_string(),
+                vec!["teams".to_string(), "teams.id==users.team_id".to_string()],
+            ),
+        ];
+        assert_eq!(2, Rule::get_joins(&orders, &clauses).unwrap().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_not_parsed() -> Result<(), ()> {
+        let result =
+            ParserDefault::from_unary_func_expr("onRead(orders) join(users)(orders.id > users.id)");
+        assert_eq!(true, result.is_none());
+        let result = ParserDefault::from_unary_func_expr("onRead(orders) join(users)");
+        assert_eq!(true, result.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_unary_func_expr_upsert() -> Result<(), ()> {
+        use crate::text_processing::ast::types::FuncType;
+        let unary_func_expressions =
+            ParserDefault::from_unary_func_expr("onUpsert(users)(id == 5)(name: text = 'bob')")
+                .unwrap();
+        let unary_func_expr = &unary_func_expressions[0];
+        assert_eq!(&FuncType::OnUpsert, unary_func_expr.get_func_type());
+        assert_eq!(
+            1,
+            unary_func_expr.get_binary_exprs().as_ref().unwrap().len()
+        );
+        assert_eq!(
+            "name",
+            unary_func_expr.get_vars().as_ref().unwrap()[0].get_name()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_returning() -> Result<(), ()> {
+        let unary_func_expressions = ParserDefault::from_unary_func_expr(
+            "onCreate(users)(name: text = 'bob') returning(id, name as n); \
+             onUpdate(users)(id == 1)(age: int = 2) returning(age); \
+             onDelete(users) returning(id); \
+             onRead(users)",
+        )
+        .unwrap();
+        let names = |i: usize| -> Vec<String> {
+            unary_func_expressions[i]
+                .get_returning()
+                .iter()
+                .flatten()
+                .map(|e| e.get_name())
+                .collect()
+        };
+        assert_eq!(vec!["id", "n"], names(0));
+        assert_eq!(vec!["age"], names(1));
+        assert_eq!(vec!["id"], names(2));
+        assert_eq!(true, names(3).is_empty());
+        assert_eq!(None, Rule::get_returning("count(*)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_expiry() -> Result<(), ()> {
+        use crate::text_processing::ast::types::Expiry;
+
+        let unary_func_expressions = ParserDefault::from_unary_func_expr(
+            "onCreate(sessions)(token: text = 'x') ttl(3600); \
+             onCreate(cache)(key: text = 'k', expires_at: int = 0) ttl(expires_at) returning(key); \
+             onCreate(users)(name: text = 'bob')",
+        )
+        .unwrap();
+        assert_eq!(
+            &Some(Expiry::Ttl(3600)),
+            unary_func_expressions[0].get_expiry()
+        );
+        assert_eq!(
+            &Some(Expiry::Field("expires_at".to_string())),
+            unary_func_expressions[1].get_expiry()
+        );
+        assert_eq!(&None, unary_func_expressions[2].get_expiry());
+        assert_eq!(None, Rule::get_expiry("-1"));
+        assert_eq!(None, Rule::get_expiry("a + 1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ttl_in_update() -> Result<(), ()> {
+        let result = ParserDefault::from_unary_func_expr("onUpdate(users)()(a: int = 1) ttl(10)");
+        assert_eq!(true, result.is_none());
+        let result = ParserDefault::from_unary_func_expr("onCreate(users)(a: int = 1) ttl(-5)");
+        assert_eq!(true, result.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_read_modifiers() -> Result<(), ()> {
+        use crate::text_processing::ast::types::AsOf;
+
+        let unary_func_expressions = ParserDefault::from_unary_func_expr(
+            "onRead(users)(age > 18) as of version 12; \
+             onRead(users) history as of timestamp 1718000000000 orderBy(_version) limit(2); \
+             onRead(users)(id == 1)(id, _version) history",
+        )
+        .unwrap();
+        assert_eq!(
+            &Some(AsOf::Version(12)),
+            unary_func_expressions[0].get_as_of()
+        );
+        assert_eq!(false, unary_func_expressions[0].is_history());
+        assert_eq!(
+            &Some(AsOf::Timestamp(1718000000000)),
+            unary_func_expressions[1].get_as_of()
+        );
+        assert_eq!(true, unary_func_expressions[1].is_history());
+        assert_eq!(Some(2), unary_func_expressions[1].get_limit());
+        assert_eq!(&None, unary_func_expressions[2].get_as_of());
+        assert_eq!(true, unary_func_expressions[2].is_history());
+        assert_eq!(
+            2,
+            unary_func_expressions[2]
+                .get_projection()
+                .as_ref()
+                .unwrap()
+                .len()
+        );
+
+        let groups = Rule::get_argument_groups("onRead(users) as of version x");
+        assert_eq!(None, Rule::get_read_modifiers(&groups));
+        let groups = Rule::get_argument_groups("onRead(users) history history");
+        assert_eq!(None, Rule::get_read_modifiers(&groups));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_modifiers_not_allowed() -> Result<(), ()> {
+        let unary_func_expressions =
+            ParserDefault::from_unary_func_expr("onDelete(users)(id == 1) as of version 1");
+        assert_eq!(true, unary_func_expressions.is_none());
+        // versions of joined records are not matched by time of version
+        let unary_func_expressions = ParserDefault::from_unary_func_expr(
+            "onRead(users) join(orders)(orders.user_id == users.id) history",
+        );
+        assert_eq!(true, unary_func_expressions.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_placeholders() -> Result<(), ()> {
+        use crate::text_processing::ast::types::{DataType, ParamKey, Placeholder, ScalarExpr};
+
+        let unary_func_expressions = ParserDefault::from_unary_func_expr(
+            "onUpdate(users)(id == $1, name != :Name)(age: int = $2)",
+        )
+        .unwrap();
+        let binary_exprs = unary_func_expressions[0]
+            .get_binary_exprs()
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Param(Placeholder::new(
+                ParamKey::Position(1),
+                None
+            ))),
+            binary_exprs[0].get_rterm()
+        );
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Param(Placeholder::new(
+                ParamKey::Name("name".to_string()),
+                None
+            ))),
+            binary_exprs[1].get_rterm()
+        );
+        assert_eq!(
+            &DataType::Param(Placeholder::new(
+                ParamKey::Position(2),
+                Some("int".to_string())
+            )),
+            unary_func_expressions[0].get_vars().as_ref().unwrap()[0].get_value()
+        );
+        assert_eq!(None, Rule::split_projection("$1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_script() -> Result<(), ()> {
+        use crate::text_processing::ast::scope::Scope;
+        use crate::text_processing::ast::types::{DataType, ScalarExpr};
+
+        let mut scope = Scope::new();
+        let unary_func_expressions = ParserDefault::from_script(
+            "let adult: int = 6 * 3; let name = 'x;y'; let targets = (users, mynode.orders); \
+             onRead(:targets, audit)(age >= :adult); \
+             onUpdate(:targets)(name == :name)(age: int = :adult)",
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(2, unary_func_expressions.len());
+        let channels: Vec<String> = unary_func_expressions[0]
+            .get_channel_names()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(vec!["users", "mynode.orders", "audit"], channels);
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Int(18)),
+            unary_func_expressions[0]
+                .get_binary_exprs()
+                .as_ref()
+                .unwrap()[0]
+                .get_rterm()
+        );
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Text("'x;y'".to_string())),
+            unary_func_expressions[1]
+                .get_binary_exprs()
+                .as_ref()
+                .unwrap()[0]
+                .get_rterm()
+        );
+        assert_eq!(
+            &DataType::Int(18),
+            unary_func_expressions[1].get_vars().as_ref().unwrap()[0].get_value()
+        );
+
+        // scope is kept for next scripts of session
+        let unary_func_expressions =
+            ParserDefault::from_script("let old = :adult; onDelete(:targets)", &mut scope).unwrap();
+        assert_eq!(2, unary_func_expressions[0].get_channel_names().len());
+        assert_eq!(
+            Some(&DataType::Int(18)),
+            scope.get_params().get_by_name("old")
+        );
+
+        // type check
+        assert_eq!(
+            true,
+            ParserDefault::from_script("let adult: text = 18", &mut Scope::new()).is_none()
+        );
+        assert_eq!(
+            true,
+            ParserDefault::from_script("let a = b + 1", &mut Scope::new()).is_none()
+        );
+        assert_eq!(
+            true,
+            ParserDefault::from_script(
+                "let name = 'bob'; onUpdate(users)()(age: int = :name)",
+                &mut Scope::new()
+            )
+            .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_script_call() -> Result<(), ()> {
+        use crate::text_processing::ast::scope::Scope;
+        use crate::text_processing::ast::types::{DataType, ScalarExpr};
+
+        let mut scope = Scope::new();
+        let unary_func_expressions = ParserDefault::from_script(
+            "define active_users($min_age: int) = onRead(users)(age >= $min_age, active == true); \
+             let adult = 18; \
+             call active_users(21); \
+             call Active_Users(:adult)",
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(2, unary_func_expressions.len());
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Int(21)),
+            unary_func_expressions[0]
+                .get_binary_exprs()
+                .as_ref()
+                .unwrap()[0]
+                .get_rterm()
+        );
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Int(18)),
+            unary_func_expressions[1]
+                .get_binary_exprs()
+                .as_ref()
+                .unwrap()[0]
+                .get_rterm()
+        );
+
+        // argument type and count
+        assert_eq!(
+            true,
+            ParserDefault::from_script("call active_users('x')", &mut scope).is_none()
+        );
+        assert_eq!(
+            true,
+            ParserDefault::from_script("call active_users()", &mut scope).is_none()
+        );
+        assert_eq!(
+            true,
+            ParserDefault::from_script("call unknown(1)", &mut scope).is_none()
+        );
+        // undeclared parameter in body
+        assert_eq!(
+            true,
+            ParserDefault::from_script("define a($b: int) = onRead(users)(x == $c)", &mut scope)
+                .is_none()
+        );
+        // placeholder of body is not bound by let of caller
+        assert_eq!(
+            true,
+            ParserDefault::from_script(
+                "let c = 1; define a($b: int) = onRead(users)(x == $c)",
+                &mut scope
+            )
+            .is_none()
+        );
+        // invalid body is error of script
+        assert_eq!(
+            true,
+            ParserDefault::from_script(
+                "define a($b: int) = onDelete(users)(x == $b) limit(1)",
+                &mut scope
+            )
+            .is_none()
+        );
+        assert_eq!(
+            true,
+            ParserDefault::from_script("define a($b: int) = onCreate(users)(x = $b)", &mut scope)
+                .is_none()
+        );
+        assert_eq!(
+            true,
+            ParserDefault::from_script("define a($b: int) = onRead(users)(x >> $b)", &mut scope)
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_unary_func_expr_watch() -> Result<(), ()> {
+        use crate::text_processing::ast::types::FuncType;
+
+        let unary_func_expressions =
+            ParserDefault::from_unary_func_expr("onWatch(users, orders)(age > 18); onWatch(users)")
+                .unwrap();
+        assert_eq!(
+            &FuncType::OnWatch,
+            unary_func_expressions[0].get_func_type()
+        );
+        assert_eq!(2, unary_func_expressions[0].get_channel_names().len());
+        assert_eq!(
+            1,
+            unary_func_expressions[0]
+                .get_binary_exprs()
+                .as_ref()
+                .unwrap()
+                .len()
+        );
+        assert_eq!(&None, unary_func_expressions[1].get_binary_exprs());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_script_blocks() -> Result<(), ()> {
+        use crate::text_processing::ast::scope::Scope;
+        use crate::text_processing::ast::types::{BlockKind, DataType};
+
+        let blocks = ParserDefault::from_script_blocks(
+            "onRead(a); \
+             BEGIN; onUpdate(a)(x == 1)(y: int = 2); let z = 3; onDelete(b); commit; \
+             begin; onCreate(c)(x: int = 1); rollback; \
+             onRead(b)",
+            &mut Scope::new(),
+        )
+        .unwrap();
+        let kinds: Vec<BlockKind> = blocks.iter().map(|e| e.get_kind()).collect();
+        assert_eq!(
+            vec![
+                BlockKind::AutoCommit,
+                BlockKind::Commit,
+                BlockKind::Rollback,
+                BlockKind::AutoCommit
+            ],
+            kinds
+        );
+        assert_eq!(2, blocks[1].get_exprs().len());
+
+        let mut scope = Scope::new();
+        assert_eq!(
+            true,
+            ParserDefault::from_script_blocks("begin; onRead(a)", &mut scope).is_none()
+        );
+        assert_eq!(
+            true,
+            ParserDefault::from_script_blocks("begin; begin; commit", &mut scope).is_none()
+        );
+        assert_eq!(
+            true,
+            ParserDefault::from_script_blocks("onRead(a); rollback", &mut scope).is_none()
+        );
+
+        // let and define of rolled back block are discarded
+        let mut scope = Scope::new();
+        ParserDefault::from_script_blocks(
+            "let a = 1; begin; let a = 2; let b = 3; define d() = onRead(x); rollback",
+            &mut scope,
+        )
+        .unwrap();
+        assert_eq!(Some(&DataType::Int(1)), scope.get_params().get_by_name("a"));
+        assert_eq!(None, scope.get_params().get_by_name("b"));
+        assert_eq!(None, scope.get_definition("d"));
+
+        assert_eq!(
+            vec![
+                "onRead(a); let b = 1",
+                "begin; onRead(b); commit",
+                "begin; rollback",
+                "onRead(c)"
+            ],
+            ParserDefault::split_on_blocks(
+                "onRead(a); let b = 1; begin; onRead(b); commit; begin; rollback; onRead(c)"
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_statements_defaults() -> Result<(), ()> {
+        use crate::text_processing::ast::types::{DataType, DefaultExpr};
+
+        let vars = Rule::get_statements(
+            "id:int=nextval(users_id),created:int=now(),key:text=uuid(),day:int=60*60*24,neg:int=-5",
+        )
+        .unwrap();
+        assert_eq!(
+            &Some(DefaultExpr::NextVal("users_id".to_string())),
+            vars[0].get_default()
+        );
+        assert_eq!(&DataType::Int(0), vars[0].get_value());
+        assert_eq!(&Some(DefaultExpr::Now), vars[1].get_default());
+        assert_eq!(&Some(DefaultExpr::Uuid), vars[2].get_default());
+        assert_eq!(&DataType::Int(86400), vars[3].get_value());
+        assert_eq!(&None, vars[3].get_default());
+        assert_eq!(&DataType::Int(-5), vars[4].get_value());
+
+        assert_eq!(None, Rule::split_statement("key:int=uuid()"));
+        assert_eq!(None, Rule::split_statement("x:int=1.5*2"));
+        assert_eq!(None, Rule::split_statement("x:int=lower(y)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scalar_functions() -> Result<(), ()> {
+        use crate::text_processing::ast::functions::ScalarFunc;
+        use crate::text_processing::ast::types::{DataType, DefaultExpr, ScalarExpr};
+
+        let unary_func_expressions = ParserDefault::from_unary_func_expr(
+            "onRead(users)(lower(name) == 'bob', length(trim(nick)) > 2)\
+             (upper(name) as n, coalesce(nick, name), round(score, 1))",
+        )
+        .unwrap();
+        let exprs = unary_func_expressions[0]
+            .get_binary_exprs()
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            &ScalarExpr::Call(
+                ScalarFunc::Lower,
+                vec![ScalarExpr::Value(DataType::Symbol("name".to_string()))]
+            ),
+            exprs[0].get_lterm()
+        );
+        let projection = unary_func_expressions[0].get_projection().as_ref().unwrap();
+        assert_eq!("coalesce(nick,name)", projection[1].get_name());
+        assert_eq!("round(score,1)", projection[2].get_name());
+
+        // computed statement
+        let var = Rule::split_statement("full:text=concat(first,' ',last)").unwrap();
+        assert_eq!(
+            true,
+            matches!(var.get_default(), Some(DefaultExpr::Expr(_)))
+        );
+        // constant is evaluated at parsing
+        let var = Rule::split_statement("name:text=upper('bob')").unwrap();
+        assert_eq!(&DataType::Text("'BOB'".to_string()), var.get_value());
+        assert_eq!(&None, var.get_default());
+
+        // argument types are checked before execution
+        assert_eq!(None, Rule::split_scalar_expr("lower(18)"));
+        assert_eq!(None, Rule::split_scalar_expr("substr(name)"));
+        assert_eq!(None, Rule::split_scalar_expr("coalesce(1, 'a')"));
+        assert_eq!(None, Rule::split_statement("n:int=length(name)+0.5"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_unary_func_expr_index() -> Result<(), ()> {
+        use crate::text_processing::ast::types::{DataType, FuncType, ScalarExpr};
+
+        let expr = &ParserDefault::from_unary_func_expr("onIndex(users)(Age)").unwrap()[0];
+        assert_eq!(&FuncType::OnIndex, expr.get_func_type());
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Symbol("age".to_string())),
+            expr.get_projection().as_ref().unwrap()[0].get_expr()
+        );
+
+        // onIndex after another statement on the same line
+        let exprs =
+            ParserDefault::from_unary_func_expr("onRead(users); onIndex(users)(age)").unwrap();
+        assert_eq!(2, exprs.len());
+        assert_eq!(&FuncType::OnRead, exprs[0].get_func_type());
+        assert_eq!(&FuncType::OnIndex, exprs[1].get_func_type());
+        assert_eq!(
+            &ScalarExpr::Value(DataType::Symbol("age".to_string())),
+            exprs[1].get_projection().as_ref().unwrap()[0].get_expr()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_script_explain() -> Result<(), ()> {
+        use crate::text_processing::ast::scope::Scope;
+        use crate::text_processing::ast::types::ExplainFormat;
+
+        let exprs = ParserDefault::from_script(
+            "explain onRead(users)(age > 18); explain JSON onDelete(users); onRead(users)",
+            &mut Scope::new(),
+        )
+        .unwrap();
+        assert_eq!(Some(ExplainFormat::Text), exprs[0].get_explain());
+        assert_eq!(Some(ExplainFormat::Json), exprs[1].get_explain());
+        assert_eq!(None, exprs[2].get_explain());
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_of_expression() -> Result<(), ()> {
+        let result = ParserDefault::from_unary_func_expr("onIndex(users)(age + 1)");
+        assert_eq!(true, result.is_none());
+        let result = ParserDefault::from_unary_func_expr("onIndex(users)(age, name)");
+        assert_eq!(true, result.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_returning_in_read() -> Result<(), ()> {
+        let result = ParserDefault::from_unary_func_expr("onRead(users) returning(id)");
+        assert_eq!(true, result.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_upsert_without_filter() -> Result<(), ()> {
+        let result = ParserDefault::from_unary_func_expr("onUpsert(users)()(name: text = 'bob')");
+        assert_eq!(true, result.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ungrouped_field() -> Result<(), ()> {
+        let result =
+            ParserDefault::from_unary_func_expr("onRead(users)()(name, count(*)) groupBy(country)");
+        assert_eq!(true, result.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_having_without_group() -> Result<(), ()> {
+        let result = ParserDefault::from_unary_func_expr("onRead(users)()(name) having(name > 1)");
+        assert_eq!(true, result.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_clause_not_allowed() -> Result<(), ()> {
+        let result = ParserDefault::from_unary_func_expr("onDelete(users) limit(5)");
+        assert_eq!(true, result.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_channels() -> Result<(), ()> {
+        use crate::text_processing::ast::scope::Scope;
+        use crate::text_processing::ast::types::ChannelName;
+        assert_eq!(
+            Some(vec![
+                ChannelName::new(None, "a".to_string()),
+                ChannelName::new(Some("mynode".to_string()), "b".to_string())
+            ]),
+            Rule::get_channels("a,mynode.b")
+        );
+        assert_eq!(None, Rule::get_channels("a,b,a"));
+        assert_eq!(None, Rule::get_channels("a,'b'"));
+
+        // bare name is the channel of node path
+        let scope = Scope::new().with_node_path("mynode");
+        let channels = Rule::get_channels_in_scope("orders,mynode.orders", &scope);
+        assert_eq!(None, channels);
+        let channels = Rule::get_channels_in_scope("orders,other.orders", &scope);
+        assert_eq!(2, channels.unwrap().len());
+        Ok(())
+    }
+
     #[test]
     // proof of concept
     fn test_from_unary_func_expr() -> Result<(), ()> {
         use crate::text_processing::ast::types::DataType::Symbol;
-        use crate::text_processing::ast::types::{BinaryExpr, DataType, FuncType, UnaryFuncExpr};
+        use crate::text_processing::ast::types::{
+            BinaryExpr, CompareOperator, DataType, FuncType, UnaryFuncExpr,
+        };
         use crate::text_processing::parser::states::{Parser, ParserDefault, Rule};
         assert_eq!(
             true,
@@ -343,7 +2326,7 @@ mod test {
                 [BinaryExpr::new(
                     Symbol("x".to_string()),
                     DataType::Int(2),
-                    ">=".to_string()
+                    CompareOperator::Ge
                 )],
                 exprs
             )